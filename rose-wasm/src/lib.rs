@@ -1,7 +1,9 @@
 use config::*;
 use geometry::Point;
 use rose::*;
-use svg::{SvgBuilder, SvgConfig};
+use render::{RenderConfig, Scene};
+use seeds::SeedTiles;
+use svg::SvgBackend;
 use wasm_bindgen::prelude::*;
 
 // This function needs to have so many arguments because passing tuples or structs with `&str`s
@@ -15,9 +17,24 @@ pub fn get_svg(
     stroke_width: f64,
     draw_triangles: bool,
     draw_arcs: bool,
-) -> String {
+) -> Result<String, JsValue> {
+    let seed = get_seed_from_arg(seed.parse().map_err(JsValue::from_str)?);
+    let placement = seed.placement(Point(500.0, 500.0), 100.0);
+
+    // Only the Penrose tilings of rhombuses and kites and darts can be drawn here, since the other
+    // seeds need a different decomposition
+    let seed_triangles = match seed.into_tiles() {
+        SeedTiles::Robinson(triangles) => triangles,
+        _ => {
+            return Err(JsValue::from_str(
+                "only the seeds made of Robinson triangles can be drawn",
+            ))
+        }
+    };
+
     let color_scheme = ColorScheme::from_arg(color_scheme.parse().unwrap());
-    let svg_cfg = SvgConfig {
+    let scene = Scene::new(&RenderConfig {
+        placement,
         view_box_width: 1000,
         view_box_height: 1000,
         stroke_width,
@@ -28,17 +45,16 @@ pub fn get_svg(
         } else {
             None
         },
-    };
-
-    let seed = get_seed_from_arg(seed.parse().unwrap()).transform(Point(500.0, 500.0), 100.0);
+    })
+    .unwrap();
 
-    let triangles = tiling::generate_tiling(seed, num_generations as u64);
-    let mut builder = SvgBuilder::new(svg_cfg);
+    let triangles = tiling::generate_tiling(seed_triangles, num_generations as u64);
+    let mut backend = SvgBackend::new(Vec::new());
     if draw_triangles {
-        builder.add_all_polygons(triangles).unwrap();
+        scene.render(|| triangles.iter(), &mut backend).unwrap();
     } else {
         let quads = tiling::merge_pairs_hashing(triangles);
-        builder.add_all_polygons(quads).unwrap();
+        scene.render(|| quads.iter(), &mut backend).unwrap();
     }
-    builder.build_to_string().unwrap()
+    Ok(String::from_utf8(backend.into_inner()).unwrap())
 }
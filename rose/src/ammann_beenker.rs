@@ -0,0 +1,257 @@
+use crate::geometry::*;
+use crate::render::Polygon;
+use crate::silver::*;
+use crate::tiling::SubstitutionTriangle;
+
+// The Ammann-Beenker tiling is made of squares and rhombs with an angle of 45 degrees, and
+// decomposing a tile scales it down by the silver ratio δ == 1 + sqrt(2). Each side of a parent is
+// split into a side of a child, of length 1 / δ, and the diagonal of a child square, of length
+// sqrt(2) / δ. The order of the two parts is what tells a tile from its mirror image, like the
+// arrows on the edges of the usual Ammann-Beenker tiles, which point from the side of the child to
+// the diagonal.
+//
+// The squares are split along a diagonal into two right isosceles triangles, so that they can be
+// merged with the triangles of the squares across the edges of their parent, like the Robinson
+// triangles. The rhombs, however, are sometimes split along their long diagonal, and sometimes
+// along their short one, since one of the children of a rhomb triangle is always cut in half by the
+// base of its parent. Every tile is split the same way in both of its halves, and the two kinds of
+// rhomb triangles are told apart by the length of their bases.
+
+/// A triangle of the Ammann-Beenker tiling, which is half of a square or of a rhomb. The triangles
+/// share their base, from `a` to `c`, with the other half of their tile.
+///
+/// In the square triangles, `b` is the right angle, and the sides from `a` to `b` and from `b` to
+/// `c` are both split starting with the side of a child, which tells a triangle from its mirror
+/// image. The rhomb triangles are symmetric, so they don't need to be told apart from their mirror
+/// images.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct AmmannBeenkerTriangle {
+    pub triangle_type: TileType,
+    pub a: SilverPoint,
+    pub b: SilverPoint,
+    pub c: SilverPoint,
+}
+
+impl AmmannBeenkerTriangle {
+    pub fn square(a: SilverPoint, b: SilverPoint, c: SilverPoint) -> Self {
+        AmmannBeenkerTriangle {
+            triangle_type: TileType::Square,
+            a,
+            b,
+            c,
+        }
+    }
+
+    pub fn rhomb(a: SilverPoint, b: SilverPoint, c: SilverPoint) -> Self {
+        AmmannBeenkerTriangle {
+            triangle_type: TileType::Rhomb,
+            a,
+            b,
+            c,
+        }
+    }
+}
+
+impl Polygon for AmmannBeenkerTriangle {
+    type Vertex = SilverPoint;
+
+    fn polygon_type(&self) -> TileType {
+        self.triangle_type
+    }
+
+    fn vertices(&self) -> Vec<SilverPoint> {
+        vec![self.a, self.b, self.c]
+    }
+
+    // The matching rules of the Ammann-Beenker tiles are given by arrows on their edges, which
+    // can't be drawn as arcs
+    fn arcs(&self) -> Option<(Arc, Arc)> {
+        None
+    }
+}
+
+impl Polygon for Quadrilateral<SilverPoint> {
+    type Vertex = SilverPoint;
+
+    fn polygon_type(&self) -> TileType {
+        // The diagonal of a square is sqrt(2) times longer than its sides, unlike both diagonals of
+        // a rhomb
+        let (diagonal, side) = (
+            (self.c - self.a).norm_squared(),
+            (self.b - self.a).norm_squared(),
+        );
+        if diagonal == Silver::from(2) * side {
+            TileType::Square
+        } else {
+            TileType::Rhomb
+        }
+    }
+
+    fn vertices(&self) -> Vec<SilverPoint> {
+        vec![self.a, self.b, self.c, self.d]
+    }
+
+    fn arcs(&self) -> Option<(Arc, Arc)> {
+        None
+    }
+}
+
+impl SubstitutionTriangle for AmmannBeenkerTriangle {
+    type Vertex = SilverPoint;
+    const INFLATION_FACTOR: f64 = DELTA;
+
+    fn vertices(&self) -> [SilverPoint; 3] {
+        [self.a, self.b, self.c]
+    }
+
+    fn decompose(self) -> Vec<Self> {
+        decompose(self)
+    }
+}
+
+fn decompose(t: AmmannBeenkerTriangle) -> Vec<AmmannBeenkerTriangle> {
+    use AmmannBeenkerTriangle as T;
+    let AmmannBeenkerTriangle {
+        triangle_type,
+        a,
+        b,
+        c,
+    } = t;
+    match triangle_type {
+        TileType::Square => {
+            // There is a rhomb at `a` and another at `b`, each with a side along the start of the
+            // side of the parent from that vertex. The rest of those sides is the base of a square
+            // triangle, and the middle is filled with half of a square whose diagonal is on the
+            // base of the parent. The rhomb at `a` has its other side along the base, so it's 45
+            // degrees from the side from `a` to `b`, turning towards `c`
+            let (ab, bc) = ((b - a).div_delta(), (c - b).div_delta());
+            let diagonal = if ab.rotate_steps(2) == bc {
+                ab.rotate_steps(1)
+            } else {
+                ab.rotate_steps(-1)
+            };
+            let d = a + ab;
+            let e = a + diagonal;
+            let f = d + diagonal;
+            let g = b + bc;
+            let h = f + bc;
+            vec![
+                T::rhomb(a, d, f),
+                T::rhomb(a, e, f),
+                T::rhomb(b, g, h),
+                T::rhomb(b, f, h),
+                T::square(b, f, d),
+                T::square(c, h, g),
+                T::square(h, f, e),
+            ]
+        }
+        TileType::Rhomb if (c - a).norm_squared() > (b - a).norm_squared() => {
+            // The triangle is half of a rhomb split along its long diagonal, so `a` and `c` are the
+            // acute corners. There is a rhomb at each of them, a square triangle along the rest of
+            // each side, and half of a rhomb between `b` and the base, split along its short
+            // diagonal
+            let d = a + (c - a).div_delta();
+            let e = c + (a - c).div_delta();
+            let f = a + (b - a).div_delta();
+            let g = c + (b - c).div_delta();
+            vec![
+                T::rhomb(a, f, d),
+                T::rhomb(c, g, e),
+                T::square(b, d, f),
+                T::square(b, e, g),
+                T::rhomb(d, b, e),
+            ]
+        }
+        TileType::Rhomb => {
+            // The triangle is half of a rhomb split along its short diagonal, so `b` is an acute
+            // corner. There is a whole rhomb at `b`, a square triangle along the rest of each side,
+            // and half of a rhomb between the base and the rhomb at `b`, split along its long
+            // diagonal
+            let d = b + (a - b).div_delta();
+            let e = b + (c - b).div_delta();
+            let f = d + (e - b);
+            vec![
+                T::rhomb(b, d, f),
+                T::rhomb(b, e, f),
+                T::square(a, f, d),
+                T::square(c, f, e),
+                T::rhomb(a, f, c),
+            ]
+        }
+        _ => panic!("{:?} is not an Ammann-Beenker triangle", triangle_type),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeds;
+    use crate::tiling::*;
+    use std::collections::HashMap;
+
+    // The angles of the triangle at `a`, `b` and `c`, in multiples of 22.5 degrees
+    fn angles(t: &AmmannBeenkerTriangle) -> [u32; 3] {
+        match t.triangle_type {
+            TileType::Square => [2, 4, 2],
+            _ if (t.c - t.a).norm_squared() > (t.b - t.a).norm_squared() => [1, 6, 1],
+            _ => [3, 2, 3],
+        }
+    }
+
+    #[test]
+    fn test_tiles_fit() {
+        for seed in seeds::get_ammann_beenker_seeds().iter() {
+            let seed = seed.clone().into_ammann_beenker_triangles();
+            let side = seed[0].b - seed[0].a;
+            let triangles = generate_tiling(seed, 4);
+
+            // All sides of the tiles must be δ^4 times shorter than the seed's, and the bases of the
+            // triangles must be the diagonals of their tiles
+            let side = (0..4).fold(side, |side, _| side.div_delta()).norm_squared();
+            for t in &triangles {
+                assert_eq!((t.b - t.a).norm_squared(), side);
+                assert_eq!((t.c - t.b).norm_squared(), side);
+                let [_, b, _] = angles(t);
+                let expected = match b {
+                    4 => Silver::from(2) * side,
+                    6 => Silver(2, 1) * side,
+                    _ => Silver(2, -1) * side,
+                };
+                assert_eq!((t.c - t.a).norm_squared(), expected);
+            }
+
+            // If the triangles don't overlap or leave gaps, the angles around every vertex far from
+            // the boundary add up to 360 degrees
+            let mut sums = HashMap::new();
+            for t in &triangles {
+                for (v, angle) in [t.a, t.b, t.c].iter().zip(angles(t).iter()) {
+                    *sums.entry(*v).or_insert(0) += angle;
+                }
+            }
+            let interior: Vec<_> = sums
+                .iter()
+                .filter(|(v, _)| v.to_point().distance_to(Point::ZERO) < 0.5)
+                .collect();
+            assert!(interior.len() > 20);
+            assert!(interior.iter().all(|(_, &angle)| angle == 16));
+
+            // Every triangle far from the boundary must be merged with the other half of its tile
+            let tiles = merge_pairs(triangles.clone());
+            let unpaired = triangles.len() - 2 * tiles.len();
+            assert!(unpaired < triangles.len() / 5);
+        }
+    }
+
+    #[test]
+    fn test_tile_frequencies() {
+        // In the Ammann-Beenker tiling, there are sqrt(2) times more rhombs than squares
+        let triangles = generate_tiling(seeds::octagram().into_ammann_beenker_triangles(), 6);
+        let count = |tile_type| {
+            triangles
+                .iter()
+                .filter(|t| t.triangle_type == tile_type)
+                .count() as f64
+        };
+        assert!((count(TileType::Rhomb) / count(TileType::Square) - 2.0f64.sqrt()).abs() < 0.01);
+    }
+}
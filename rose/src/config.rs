@@ -1,5 +1,7 @@
 use crate::geometry::TileType;
+use crate::monotiles::Orientation;
 use crate::seeds;
+use crate::vertices::VertexConfiguration;
 
 /// Like `clap::arg_enum!`, but allows you to customize the name of the argument associated with
 /// each variant.
@@ -39,6 +41,13 @@ custom_arg_enum! {
         Kite = "kite",
         Dart = "dart",
         Pizza = "pizza",
+        Pentagon = "pentagon",
+        Star = "star",
+        Octagram = "octagram",
+        Square = "square",
+        Rhomb = "rhomb",
+        Hat = "hat",
+        Spectre = "spectre",
     }
 }
 
@@ -53,6 +62,46 @@ custom_arg_enum! {
     }
 }
 
+custom_arg_enum! {
+    pub enum FormatArgument {
+        Svg = "svg",
+        Png = "png",
+        Pdf = "pdf",
+        Eps = "eps",
+        Json = "json",
+        GeoJson = "geojson",
+        Cut = "cut",
+        Gcode = "gcode",
+        Dxf = "dxf",
+        Stl = "stl",
+        Obj = "obj",
+    }
+}
+
+impl FormatArgument {
+    /// Guesses the output format from the extension of the output file. Defaults to SVG if the
+    /// extension is not recognized. The cut format is also written to SVG files, so it's never
+    /// guessed.
+    pub fn from_file_name(file_name: &str) -> FormatArgument {
+        let extension = std::path::Path::new(file_name)
+            .extension()
+            .and_then(|e| e.to_str())
+            .map(str::to_ascii_lowercase);
+        match extension.as_deref() {
+            Some("png") => FormatArgument::Png,
+            Some("pdf") => FormatArgument::Pdf,
+            Some("eps") | Some("ps") => FormatArgument::Eps,
+            Some("json") => FormatArgument::Json,
+            Some("geojson") => FormatArgument::GeoJson,
+            Some("gcode") | Some("nc") => FormatArgument::Gcode,
+            Some("dxf") => FormatArgument::Dxf,
+            Some("stl") => FormatArgument::Stl,
+            Some("obj") => FormatArgument::Obj,
+            _ => FormatArgument::Svg,
+        }
+    }
+}
+
 pub struct ColorScheme {
     pub quad_colors: (&'static str, &'static str),
     pub stroke_color: &'static str,
@@ -96,6 +145,61 @@ impl ColorScheme {
         }
     }
 }
+
+// The colors of the vertex markers. Since a tiling has either the kite and dart configurations or
+// the rhombus ones, but never both, the two families share the same colors
+const VERTEX_COLORS: [&str; 8] = [
+    "#ffd700", "#ff7f0e", "#2ca02c", "#1f77b4", "#9467bd", "#e377c2", "#17becf", "#8c564b",
+];
+
+pub fn vertex_configuration_color(configuration: VertexConfiguration) -> &'static str {
+    let position = |family: &[VertexConfiguration]| family.iter().position(|&c| c == configuration);
+    let index = position(&VertexConfiguration::KITE_AND_DART)
+        .or_else(|| position(&VertexConfiguration::RHOMBUS))
+        .unwrap();
+    VERTEX_COLORS[index]
+}
+
+// The default colors of the supertile outlines, which are repeated if there are more levels
+const SUPERTILE_COLORS: [&str; 4] = ["#ffd700", "#30bbe5", "#50d35b", "#e377c2"];
+
+pub fn supertile_color(index: usize) -> &'static str {
+    SUPERTILE_COLORS[index % SUPERTILE_COLORS.len()]
+}
+
+// The default colors of the monotiles, one for each rotation by a multiple of 30 degrees. The
+// reflected tiles are darker, so that they stand out
+const MONOTILE_COLORS: [&str; 12] = [
+    "#f4a6a6", "#f4c7a6", "#f4e6a6", "#d7f4a6", "#a6f4b5", "#a6f4e0", "#a6e0f4", "#a6c0f4",
+    "#b5a6f4", "#d9a6f4", "#f4a6e6", "#f4a6c4",
+];
+const REFLECTED_MONOTILE_COLORS: [&str; 12] = [
+    "#a32c2c", "#a3602c", "#a3922c", "#76a32c", "#2ca345", "#2ca387", "#2c87a3", "#2c56a3",
+    "#452ca3", "#7e2ca3", "#a32c91", "#a32c5e",
+];
+
+pub fn monotile_color(orientation: Orientation) -> &'static str {
+    let colors = if orientation.reflected {
+        &REFLECTED_MONOTILE_COLORS
+    } else {
+        &MONOTILE_COLORS
+    };
+    colors[orientation.rotation as usize % colors.len()]
+}
+
+/// Parses a CSS color, and returns its red, green, blue and alpha components. This is needed by
+/// every output format other than SVG, which can use the color directly.
+pub fn parse_color(color: &str) -> std::io::Result<[u8; 4]> {
+    csscolorparser::parse(color)
+        .map(|c| c.to_rgba8())
+        .map_err(|_| {
+            std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!("invalid color: \"{}\"", color),
+            )
+        })
+}
+
 pub fn get_seed_from_arg(arg: SeedArgument) -> seeds::Seed {
     use SeedArgument::*;
     match arg {
@@ -105,5 +209,12 @@ pub fn get_seed_from_arg(arg: SeedArgument) -> seeds::Seed {
         Kite => seeds::tile(TileType::Kite),
         Dart => seeds::tile(TileType::Dart),
         Pizza => seeds::pizza(),
+        Pentagon => seeds::pentagon(),
+        Star => seeds::star(),
+        Octagram => seeds::octagram(),
+        Square => seeds::square(),
+        Rhomb => seeds::rhomb(),
+        Hat => seeds::hat(),
+        Spectre => seeds::spectre(),
     }
 }
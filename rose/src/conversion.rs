@@ -0,0 +1,169 @@
+use crate::geometry::*;
+
+use std::collections::HashSet;
+
+// A half-kite triangle has the same shape as a small rhombus triangle, but is phi times larger, and
+// a half-dart triangle has the same shape and size as a large rhombus triangle. When a small
+// rhombus triangle ABC is decomposed, its DCA and CDB children are a small and a large rhombus
+// triangle, so every half-kite can be split into a small and a large rhombus triangle, in the same
+// way. Doing that to every half-kite of a kite and dart tiling gives a rhombus tiling, and the
+// half-darts are left as they are. The vertices of each triangle are relabeled so that the edges
+// keep their matching rules
+
+/// Converts a kite and dart tiling to the rhombus tiling that has the same Robinson triangles. The
+/// rhombuses are 1/phi times the size of the kites. Triangles that are already rhombus triangles
+/// are kept as they are.
+pub fn to_rhombuses(triangles: Vec<RobinsonTriangle>) -> Vec<RobinsonTriangle> {
+    triangles
+        .into_iter()
+        .flat_map(|t| {
+            let RobinsonTriangle {
+                triangle_type,
+                a,
+                b,
+                c,
+            } = t;
+            match triangle_type {
+                TileType::Kite => {
+                    // The half-kite is the small rhombus triangle CAB, which is then decomposed
+                    let d = a + (c - a).div_phi();
+                    vec![
+                        RobinsonTriangle::new(d, b, c),
+                        RobinsonTriangle::new(b, d, a),
+                    ]
+                }
+                TileType::Dart => vec![RobinsonTriangle {
+                    triangle_type: TileType::LargeRhombus,
+                    a,
+                    b: c,
+                    c: b,
+                }],
+                _ => vec![t],
+            }
+        })
+        .collect()
+}
+
+/// Converts a rhombus tiling to the kite and dart tiling that has the same Robinson triangles, which
+/// is the inverse of `to_rhombuses`. Each small rhombus triangle is joined with the large rhombus
+/// triangle next to it to make a half-kite, and the remaining large rhombus triangles become
+/// half-darts. Small rhombus triangles that can't be joined, because the large rhombus triangle is
+/// missing from the boundary of the patch, are discarded. Triangles that are already kite and dart
+/// triangles are kept as they are.
+pub fn to_kites_and_darts(triangles: Vec<RobinsonTriangle>) -> Vec<RobinsonTriangle> {
+    let all: HashSet<_> = triangles.iter().cloned().collect();
+    let mut joined = HashSet::new();
+    let mut result = Vec::with_capacity(triangles.len());
+    for t in &triangles {
+        if t.triangle_type != TileType::SmallRhombus {
+            continue;
+        }
+
+        // The small rhombus triangle is the DCA child of the small rhombus triangle ABC that has
+        // the shape of the half-kite, and the large one is its CDB child. Since D is between B and
+        // A, with AD == AB / phi^2, B can be found from A and D
+        let RobinsonTriangle {
+            a: d, b: c, c: a, ..
+        } = *t;
+        let b = a + (d - a).mul_phi().mul_phi();
+        let large = RobinsonTriangle {
+            triangle_type: TileType::LargeRhombus,
+            a: c,
+            b: d,
+            c: b,
+        };
+        if all.contains(&large) {
+            result.push(RobinsonTriangle {
+                triangle_type: TileType::Kite,
+                a: b,
+                b: c,
+                c: a,
+            });
+            joined.insert(large);
+        }
+    }
+    for t in triangles {
+        match t.triangle_type {
+            TileType::SmallRhombus => (),
+            TileType::LargeRhombus if joined.contains(&t) => (),
+            TileType::LargeRhombus => result.push(RobinsonTriangle {
+                triangle_type: TileType::Dart,
+                a: t.a,
+                b: t.c,
+                c: t.b,
+            }),
+            _ => result.push(t),
+        }
+    }
+    result
+}
+
+/// Converts the tiling to the other family of Penrose tiles, that is, a rhombus tiling to kites and
+/// darts, or a kite and dart tiling to rhombuses.
+pub fn convert(triangles: Vec<RobinsonTriangle>) -> Vec<RobinsonTriangle> {
+    match triangles.first().map(|t| t.triangle_type) {
+        Some(TileType::SmallRhombus) | Some(TileType::LargeRhombus) => {
+            to_kites_and_darts(triangles)
+        }
+        _ => to_rhombuses(triangles),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiling::*;
+    use crate::validate::validate;
+
+    #[test]
+    fn test_conversion() {
+        let triangle_key = |t: &RobinsonTriangle| (t.double_base_median(), t.b);
+        for seed in crate::seeds::get_all_seeds().iter() {
+            let triangles = generate_tiling(seed.clone().into_triangles(), 5);
+            let is_rhombus = matches!(
+                triangles[0].triangle_type,
+                TileType::SmallRhombus | TileType::LargeRhombus
+            );
+            let converted = convert(triangles.clone());
+            let mut back = convert(converted.clone());
+            let mut original = triangles;
+
+            // The converted tiling must obey the matching rules, and have only tiles of the other
+            // family
+            assert_eq!(validate(&converted), vec![]);
+            assert!(converted.iter().all(|t| is_rhombus
+                != matches!(
+                    t.triangle_type,
+                    TileType::SmallRhombus | TileType::LargeRhombus
+                )));
+
+            // Converting back must give the same triangles
+            original.sort_by_key(triangle_key);
+            back.sort_by_key(triangle_key);
+            assert_eq!(original, back);
+        }
+    }
+
+    #[test]
+    fn test_conversion_counts() {
+        // A rhombus tiling has phi times more thick rhombuses than thin ones, and a kite and dart
+        // tiling has phi times more kites than darts. Converting one to the other must keep that
+        let count = |triangles: &[RobinsonTriangle], tile_type| {
+            triangles
+                .iter()
+                .filter(|t| t.triangle_type == tile_type)
+                .count() as f64
+        };
+        let rhombuses = generate_tiling(crate::seeds::rose().into_triangles(), 8);
+        let thick = count(&rhombuses, TileType::LargeRhombus);
+        let thin = count(&rhombuses, TileType::SmallRhombus);
+        assert!((thick / thin - PHI).abs() < 0.01);
+
+        let kites_and_darts = to_kites_and_darts(rhombuses);
+        let kites = count(&kites_and_darts, TileType::Kite);
+        let darts = count(&kites_and_darts, TileType::Dart);
+        assert_eq!(kites, thin);
+        assert_eq!(kites + darts, thick);
+        assert!((kites / darts - PHI).abs() < 0.01);
+    }
+}
@@ -0,0 +1,289 @@
+use crate::data::VertexKey;
+use crate::geometry::*;
+use crate::render::{Backend, Group, GroupKind, Scene, Tile};
+use crate::svg::{scale_float, SvgBackend, SCALING_FACTOR};
+
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
+
+// Laser cutting software usually chooses the operation by the stroke color, and these are the
+// colors most of it expects by default
+const CUT_COLOR: &str = "#ff0000";
+const ENGRAVE_COLOR: &str = "#0000ff";
+
+/// The edges of the tiles, with every edge shared by two tiles kept only once. The edges are kept
+/// in the view box coordinates, but the vertices are matched by their tiling coordinates, so that
+/// rounding errors don't split a shared edge in two.
+#[derive(Debug, Default, Clone)]
+pub struct EdgeSet {
+    vertex_indices: HashMap<VertexKey, usize>,
+    vertices: Vec<Point>,
+    edge_indices: HashSet<(usize, usize)>,
+    edges: Vec<(usize, usize)>,
+}
+
+impl EdgeSet {
+    pub fn new() -> Self {
+        EdgeSet::default()
+    }
+
+    /// Adds the edges of the tile that aren't in the set yet.
+    pub fn add_tile(&mut self, tile: &Tile) {
+        let indices: Vec<_> = tile
+            .vertices
+            .iter()
+            .zip(tile.tiling_vertices)
+            .map(|(&p, &v)| self.vertex_index(p, VertexKey::new(v)))
+            .collect();
+        for (i, &a) in indices.iter().enumerate() {
+            let b = indices[(i + 1) % indices.len()];
+            if a != b && self.edge_indices.insert((a.min(b), a.max(b))) {
+                self.edges.push((a, b));
+            }
+        }
+    }
+
+    fn vertex_index(&mut self, point: Point, key: VertexKey) -> usize {
+        let vertices = &mut self.vertices;
+        *self.vertex_indices.entry(key).or_insert_with(|| {
+            vertices.push(point);
+            vertices.len() - 1
+        })
+    }
+
+    pub fn num_vertices(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.edges.len()
+    }
+
+    /// Every edge, as a path of two points, in the order in which they were added.
+    pub fn edges(&self) -> Vec<Vec<Point>> {
+        self.edges
+            .iter()
+            .map(|&(a, b)| vec![self.vertices[a], self.vertices[b]])
+            .collect()
+    }
+
+    /// Joins the edges into paths, so that each edge is in exactly one path, and there are as few
+    /// paths as this greedy method can manage. Paths start at the vertices with an odd number of
+    /// edges, which is where at least one path must end, and after those run out, the remaining
+    /// edges form closed paths, whose last point is the same as the first.
+    pub fn join_paths(&self) -> Vec<Vec<Point>> {
+        let mut neighbors = vec![Vec::new(); self.vertices.len()];
+        for (edge, &(a, b)) in self.edges.iter().enumerate() {
+            neighbors[a].push((b, edge));
+            neighbors[b].push((a, edge));
+        }
+        let mut used = vec![false; self.edges.len()];
+
+        // The index of the first neighbor of each vertex that may still have an unused edge
+        let mut next = vec![0; self.vertices.len()];
+        let mut unused_edge = |vertex: usize| {
+            while let Some(&(neighbor, edge)) = neighbors[vertex].get(next[vertex]) {
+                next[vertex] += 1;
+                if !used[edge] {
+                    used[edge] = true;
+                    return Some(neighbor);
+                }
+            }
+            None
+        };
+
+        let odd = (0..self.vertices.len()).filter(|&v| neighbors[v].len() % 2 == 1);
+        let starts: Vec<_> = odd.chain(0..self.vertices.len()).collect();
+        let mut paths = Vec::new();
+        for start in starts {
+            while let Some(mut vertex) = unused_edge(start) {
+                let mut path = vec![self.vertices[start]];
+                loop {
+                    path.push(self.vertices[vertex]);
+                    match unused_edge(vertex) {
+                        Some(neighbor) => vertex = neighbor,
+                        None => break,
+                    }
+                }
+                paths.push(path);
+            }
+        }
+        paths
+    }
+}
+
+/// Writes the scene as an SVG file for laser cutters and plotters. Instead of filled polygons, it
+/// has a layer with every edge of the tiles, each written only once, so that the edges shared by
+/// two tiles aren't cut twice, and a layer with the arcs, outlines and markers, which are meant to
+/// be engraved. One view box unit is a millimeter, so the scale sets the size of the tiles in
+/// millimeters.
+///
+/// The edges are kept in memory until the whole scene is drawn. If `join_paths` is set, they are
+/// joined into long paths, so that the cutter doesn't have to stop as often.
+pub struct CutBackend<W> {
+    out: W,
+    join_paths: bool,
+    edges: EdgeSet,
+
+    // The engraved elements are written by an SVG backend into a buffer, and only copied to the
+    // output at the end, since the layer to cut goes after them
+    engrave: SvgBackend<Vec<u8>>,
+}
+
+impl<W: Write> CutBackend<W> {
+    pub fn new(join_paths: bool, out: W) -> Self {
+        CutBackend {
+            out,
+            join_paths,
+            edges: EdgeSet::new(),
+            engrave: SvgBackend::new(Vec::new()),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+}
+
+impl<W: Write> Backend for CutBackend<W> {
+    fn begin(&mut self, scene: &Scene) -> io::Result<()> {
+        // The engraving layer comes first, so that the pieces are still in place when it's done
+        write!(
+            self.out,
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<svg width=\"{}mm\" height=\"{}mm\" \
+            viewBox=\"0 0 {} {}\" fill=\"none\" stroke-width=\"{}\" stroke-linecap=\"round\" \
+            stroke-linejoin=\"round\" xmlns=\"http://www.w3.org/2000/svg\" \
+            xmlns:inkscape=\"http://www.inkscape.org/namespaces/inkscape\">\n  <g id=\"engrave\" \
+            inkscape:groupmode=\"layer\" inkscape:label=\"Engrave\" stroke=\"{}\">\n",
+            scene.view_box_width,
+            scene.view_box_height,
+            scene.view_box_width * SCALING_FACTOR,
+            scene.view_box_height * SCALING_FACTOR,
+            scale_float(scene.stroke_width),
+            ENGRAVE_COLOR,
+        )
+    }
+
+    fn begin_group(&mut self, group: &Group) -> io::Result<()> {
+        match &group.kind {
+            GroupKind::Polygons(_) => Ok(()),
+            GroupKind::Markers { name, .. } | GroupKind::Outlines { name, .. } => {
+                writeln!(self.engrave.get_mut(), r#"    <g class="{}">"#, name)
+            }
+            GroupKind::FirstArcs | GroupKind::SecondArcs => {
+                writeln!(self.engrave.get_mut(), "    <g>")
+            }
+        }
+    }
+
+    fn draw_polygon(&mut self, tile: &Tile) -> io::Result<()> {
+        self.edges.add_tile(tile);
+        Ok(())
+    }
+
+    fn draw_arc(&mut self, arc: Arc) -> io::Result<()> {
+        self.engrave.draw_arc(arc)
+    }
+
+    fn draw_marker(&mut self, center: Point, radius: f64) -> io::Result<()> {
+        self.engrave.draw_marker(center, radius)
+    }
+
+    // The lines are engraved with the same width as everything else, since a laser can't make
+    // them any wider
+    fn draw_line(&mut self, start: Point, end: Point) -> io::Result<()> {
+        self.engrave.draw_line(start, end)
+    }
+
+    fn end_group(&mut self, group: &Group) -> io::Result<()> {
+        match group.kind {
+            GroupKind::Polygons(_) => Ok(()),
+            _ => writeln!(self.engrave.get_mut(), "    </g>"),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        self.out.write_all(self.engrave.get_mut())?;
+        writeln!(
+            self.out,
+            "  </g>\n  <g id=\"cut\" inkscape:groupmode=\"layer\" inkscape:label=\"Cut\" \
+            stroke=\"{}\">",
+            CUT_COLOR,
+        )?;
+        let paths = if self.join_paths {
+            self.edges.join_paths()
+        } else {
+            self.edges.edges()
+        };
+        for path in paths {
+            write!(self.out, "    <polyline points=\"")?;
+            for (i, p) in path.iter().enumerate() {
+                let separator = if i == 0 { "" } else { " " };
+                write!(
+                    self.out,
+                    "{}{},{}",
+                    separator,
+                    scale_float(p.0),
+                    scale_float(p.1)
+                )?;
+            }
+            writeln!(self.out, "\" />")?;
+        }
+        write!(self.out, "  </g>\n</svg>\n")?;
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(join_paths: bool, arc_colors: Option<(&str, &str)>) -> (String, EdgeSet, usize) {
+        let mut backend = CutBackend::new(join_paths, Vec::new());
+        let num_tiles =
+            crate::render::render_seed(crate::seeds::rose(), 3, arc_colors, &mut backend);
+        let edges = backend.edges.clone();
+        (
+            String::from_utf8(backend.into_inner()).unwrap(),
+            edges,
+            num_tiles,
+        )
+    }
+
+    #[test]
+    fn test_shared_edges() {
+        let (svg, edges, num_tiles) = export(false, None);
+
+        // Euler's formula for a patch without holes, which counts every shared edge once
+        assert_eq!(edges.num_vertices() + num_tiles, edges.num_edges() + 1);
+        assert_eq!(svg.matches("<polyline").count(), edges.num_edges());
+        assert!(svg.contains("width=\"100mm\" height=\"100mm\""));
+        assert!(!svg.contains("<path"));
+    }
+
+    #[test]
+    fn test_join_paths() {
+        let (svg, edges, _) = export(true, Some(("green", "yellow")));
+        let paths = edges.join_paths();
+        assert_eq!(svg.matches("<polyline").count(), paths.len());
+        assert!(paths.len() * 2 < edges.num_edges());
+
+        // Every edge is in exactly one path
+        let key = |p: Point| (scale_float(p.0), scale_float(p.1));
+        let mut segments: Vec<_> = paths
+            .iter()
+            .flat_map(|path| path.windows(2))
+            .map(|w| (key(w[0]).min(key(w[1])), key(w[0]).max(key(w[1]))))
+            .collect();
+        assert_eq!(segments.len(), edges.num_edges());
+        segments.sort_unstable();
+        segments.dedup();
+        assert_eq!(segments.len(), edges.num_edges());
+
+        // The arcs are engraved before anything is cut
+        let (engrave, cut) = svg.split_at(svg.find("id=\"cut\"").unwrap());
+        assert!(engrave.contains("id=\"engrave\""));
+        assert!(engrave.matches("<path").count() > 0);
+        assert!(!cut.contains("<path"));
+    }
+}
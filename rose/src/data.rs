@@ -0,0 +1,332 @@
+use crate::geometry::*;
+use crate::render::{Backend, Scene, Tile, TilingVertex};
+
+use std::collections::HashMap;
+use std::io::{self, Write};
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum DataFormat {
+    Json,
+    GeoJson,
+}
+
+/// Writes the tiles as structured data, instead of drawing them. Each tile is written with its id,
+/// type, generation and vertices, both as floating point numbers and as exact coordinates, all in
+/// the tiling coordinates, before the view box placement is applied. The colors, arcs, markers and
+/// outlines of the scene are ignored.
+///
+/// An exact vertex is written as `[[a, b], [c, d]]`, meaning the point `(a + bφ) + (c + dφ)ζ`, where
+/// ζ is the unit vector that makes a 36 degree angle with the x axis. For the Ammann-Beenker tiles,
+/// it means `(a + b * sqrt(2)) + (c + d * sqrt(2))ξ` instead, where ξ is the unit vector at 45
+/// degrees. The tiles generated from user-defined rules and the monotiles have no exact
+/// coordinates, so they are written as `null`.
+///
+/// If `indexed_vertices` is set, every vertex is written only once, in a list at the end of the
+/// document, and the tiles refer to their vertices by their indices in that list.
+pub struct DataBackend<W> {
+    format: DataFormat,
+    generation: u64,
+    out: W,
+    first_tile: bool,
+
+    // Only used if the vertices are indexed. The list is needed to write the vertices in the order
+    // of their indices
+    indexed_vertices: bool,
+    vertex_indices: HashMap<VertexKey, usize>,
+    vertex_list: Vec<TilingVertex>,
+}
+
+impl<W: Write> DataBackend<W> {
+    /// Creates a backend that writes every tile as belonging to the given generation.
+    pub fn new(format: DataFormat, generation: u64, indexed_vertices: bool, out: W) -> Self {
+        DataBackend {
+            format,
+            generation,
+            out,
+            first_tile: true,
+            indexed_vertices,
+            vertex_indices: HashMap::new(),
+            vertex_list: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn vertex_index(&mut self, v: TilingVertex) -> usize {
+        let list = &mut self.vertex_list;
+        *self
+            .vertex_indices
+            .entry(VertexKey::new(v))
+            .or_insert_with(|| {
+                list.push(v);
+                list.len() - 1
+            })
+    }
+
+    // Writes a list of vertices, either as their indices or as their coordinates
+    fn write_vertices(&mut self, vertices: &[TilingVertex]) -> io::Result<()> {
+        write!(self.out, "[")?;
+        for (i, &v) in vertices.iter().enumerate() {
+            let separator = if i == 0 { "" } else { ", " };
+            if self.indexed_vertices {
+                let index = self.vertex_index(v);
+                write!(self.out, "{}{}", separator, index)?;
+            } else {
+                write!(self.out, "{}", separator)?;
+                write_vertex(&mut self.out, v)?;
+            }
+        }
+        write!(self.out, "]")
+    }
+
+    fn write_geojson_feature(&mut self, tile: &Tile) -> io::Result<()> {
+        // The y axis points down in the tiling coordinates, but up in GeoJSON, so the y coordinates
+        // are flipped. The vertices are also ordered counterclockwise, as GeoJSON recommends
+        let mut vertices = tile.tiling_vertices.to_vec();
+        let flipped = |v: TilingVertex| {
+            let Point(x, y) = v.point;
+            Point(x, -y)
+        };
+        let signed_area: f64 = (0..vertices.len())
+            .map(|i| {
+                let (p, q) = (vertices[i], vertices[(i + 1) % vertices.len()]);
+                flipped(p).cross(flipped(q))
+            })
+            .sum();
+        if signed_area < 0.0 {
+            vertices.reverse();
+        }
+
+        write!(
+            self.out,
+            "{{\"type\": \"Feature\", \"id\": {}, \"geometry\": {{\"type\": \"Polygon\", \
+            \"coordinates\": [[",
+            tile.id
+        )?;
+
+        // The first vertex is repeated at the end to close the ring
+        for (i, &v) in vertices.iter().chain(vertices.first()).enumerate() {
+            let Point(x, y) = flipped(v);
+            let separator = if i == 0 { "" } else { ", " };
+            write!(self.out, "{}[{}, {}]", separator, x, y)?;
+        }
+        write!(
+            self.out,
+            "]]}}, \"properties\": {{\"type\": \"{}\", \"generation\": {}, \"{}\": ",
+            tile.tile_type.name(),
+            self.generation,
+            if self.indexed_vertices {
+                "vertices"
+            } else {
+                "exact"
+            },
+        )?;
+        if self.indexed_vertices {
+            self.write_vertices(&vertices)?;
+        } else {
+            // The floating point coordinates are already in the geometry, so only the exact ones
+            // are written here
+            write!(self.out, "[")?;
+            for (i, v) in vertices.iter().enumerate() {
+                let separator = if i == 0 { "" } else { ", " };
+                write!(self.out, "{}", separator)?;
+                write_exact(&mut self.out, v.exact)?;
+            }
+            write!(self.out, "]")?;
+        }
+        write!(self.out, "}}}}")
+    }
+}
+
+impl<W: Write> Backend for DataBackend<W> {
+    fn begin(&mut self, _: &Scene) -> io::Result<()> {
+        match self.format {
+            DataFormat::Json => write!(self.out, "{{\n  \"tiles\": ["),
+            DataFormat::GeoJson => write!(
+                self.out,
+                "{{\n  \"type\": \"FeatureCollection\",\n  \"features\": ["
+            ),
+        }
+    }
+
+    // The tiles are written in the order of the groups they are in, but they can be sorted by
+    // their ids to get the order in which they were generated
+    fn draw_polygon(&mut self, tile: &Tile) -> io::Result<()> {
+        let separator = if self.first_tile { "" } else { "," };
+        self.first_tile = false;
+        write!(self.out, "{}\n    ", separator)?;
+        match self.format {
+            DataFormat::Json => {
+                write!(
+                    self.out,
+                    "{{\"id\": {}, \"type\": \"{}\", \"generation\": {}, \"vertices\": ",
+                    tile.id,
+                    tile.tile_type.name(),
+                    self.generation,
+                )?;
+                self.write_vertices(tile.tiling_vertices)?;
+                write!(self.out, "}}")
+            }
+            DataFormat::GeoJson => self.write_geojson_feature(tile),
+        }
+    }
+
+    fn draw_arc(&mut self, _: Arc) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn draw_marker(&mut self, _: Point, _: f64) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn draw_line(&mut self, _: Point, _: Point) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        write!(self.out, "\n  ]")?;
+
+        // In GeoJSON, the vertex list is a foreign member of the feature collection, which is
+        // allowed by the specification
+        if self.indexed_vertices {
+            write!(self.out, ",\n  \"vertices\": [")?;
+            for (i, &v) in self.vertex_list.iter().enumerate() {
+                let separator = if i == 0 { "" } else { "," };
+                write!(self.out, "{}\n    ", separator)?;
+                write_vertex(&mut self.out, v)?;
+            }
+            write!(self.out, "\n  ]")?;
+        }
+        write!(self.out, "\n}}\n")?;
+        self.out.flush()
+    }
+}
+
+/// A key that identifies a vertex of the tiling, to find the vertices that tiles share. Vertices
+/// without exact coordinates are compared by their floating point coordinates, rounded so that the
+/// errors in computing them don't make the same vertex look like two different ones.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum VertexKey {
+    Exact([[i64; 2]; 2]),
+    Rounded(i64, i64),
+}
+
+impl VertexKey {
+    pub fn new(v: TilingVertex) -> Self {
+        match v.exact {
+            Some(exact) => VertexKey::Exact(exact),
+            None => {
+                let Point(x, y) = v.point;
+                VertexKey::Rounded((x * 1e6).round() as i64, (y * 1e6).round() as i64)
+            }
+        }
+    }
+}
+
+fn write_exact<W: Write>(out: &mut W, exact: Option<[[i64; 2]; 2]>) -> io::Result<()> {
+    match exact {
+        Some([p, q]) => write!(out, "[[{}, {}], [{}, {}]]", p[0], p[1], q[0], q[1]),
+        None => write!(out, "null"),
+    }
+}
+
+fn write_vertex<W: Write>(out: &mut W, v: TilingVertex) -> io::Result<()> {
+    let Point(x, y) = v.point;
+    write!(out, "{{\"x\": {}, \"y\": {}, \"exact\": ", x, y)?;
+    write_exact(out, v.exact)?;
+    write!(out, "}}")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::Value;
+
+    fn export(format: DataFormat, indexed_vertices: bool) -> (Value, usize) {
+        // The tiles of the rose seed share a vertex at the origin
+        let mut backend = DataBackend::new(format, 1, indexed_vertices, Vec::new());
+        let num_tiles = crate::render::render_seed(crate::seeds::rose(), 1, None, &mut backend);
+        let data = String::from_utf8(backend.into_inner()).unwrap();
+        (serde_json::from_str(&data).unwrap(), num_tiles)
+    }
+
+    #[test]
+    fn test_json_export() {
+        let (json, num_tiles) = export(DataFormat::Json, false);
+        let tiles = json["tiles"].as_array().unwrap();
+        assert_eq!(tiles.len(), num_tiles);
+        assert!(json.get("vertices").is_none());
+        for tile in tiles {
+            assert_eq!(tile["generation"], 1);
+            assert_eq!(tile["vertices"].as_array().unwrap().len(), 4);
+        }
+        let is_origin = |v: &Value| {
+            v["x"].as_f64() == Some(0.0)
+                && v["y"].as_f64() == Some(0.0)
+                && v["exact"] == serde_json::json!([[0, 0], [0, 0]])
+        };
+        assert!(tiles
+            .iter()
+            .flat_map(|t| t["vertices"].as_array().unwrap())
+            .any(is_origin));
+
+        // With indexed vertices, each vertex is written only once
+        let (indexed, _) = export(DataFormat::Json, true);
+        let vertices = indexed["vertices"].as_array().unwrap();
+        let mut exact: Vec<_> = vertices.iter().map(|v| v["exact"].to_string()).collect();
+        exact.sort_unstable();
+        exact.dedup();
+        assert_eq!(exact.len(), vertices.len());
+        assert_eq!(vertices.iter().filter(|v| is_origin(v)).count(), 1);
+
+        let indexed_tiles = indexed["tiles"].as_array().unwrap();
+        assert_eq!(indexed_tiles.len(), num_tiles);
+        for (tile, indexed_tile) in tiles.iter().zip(indexed_tiles) {
+            assert_eq!(tile["id"], indexed_tile["id"]);
+            for (v, i) in tile["vertices"]
+                .as_array()
+                .unwrap()
+                .iter()
+                .zip(indexed_tile["vertices"].as_array().unwrap())
+            {
+                assert_eq!(v, &vertices[i.as_u64().unwrap() as usize]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_geojson_export() {
+        for &indexed_vertices in &[false, true] {
+            let (geojson, num_tiles) = export(DataFormat::GeoJson, indexed_vertices);
+            assert_eq!(geojson["type"], "FeatureCollection");
+            let features = geojson["features"].as_array().unwrap();
+            assert_eq!(features.len(), num_tiles);
+            let num_vertices = geojson["vertices"].as_array().map_or(0, |v| v.len());
+            for feature in features {
+                assert_eq!(feature["type"], "Feature");
+                assert_eq!(feature["geometry"]["type"], "Polygon");
+                let rings = feature["geometry"]["coordinates"].as_array().unwrap();
+                assert_eq!(rings.len(), 1);
+
+                // Every ring is closed, so it has one more position than the tile has vertices
+                let ring = rings[0].as_array().unwrap();
+                assert_eq!(ring.first(), ring.last());
+                let properties = &feature["properties"];
+                if indexed_vertices {
+                    let indices = properties["vertices"].as_array().unwrap();
+                    assert_eq!(ring.len(), indices.len() + 1);
+                    for i in indices {
+                        assert!((i.as_u64().unwrap() as usize) < num_vertices);
+                    }
+                } else {
+                    assert_eq!(
+                        ring.len(),
+                        properties["exact"].as_array().unwrap().len() + 1
+                    );
+                }
+            }
+        }
+    }
+}
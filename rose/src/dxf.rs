@@ -0,0 +1,456 @@
+use crate::geometry::*;
+use crate::render::{Backend, Group, GroupKind, Scene, Tile};
+
+use std::io::{self, Write};
+
+// An entity of the drawing, in the view box coordinates, with the y axis flipped
+#[derive(Debug, Clone)]
+enum Entity {
+    Polyline(Vec<Point>),
+    Line(Point, Point),
+    Circle(Point, f64),
+
+    // The angles are in degrees, and the arc goes counterclockwise from the first to the second
+    Arc(Point, f64, f64, f64),
+}
+
+// The handles of the parts of the drawing that are always there. The handles of the layers start
+// at 0x10, and the ones of the entities after them
+const BLOCK_RECORD_TABLE: usize = 0x1;
+const LAYER_TABLE: usize = 0x2;
+const DEFAULT_LAYER: usize = 0x3;
+const ROOT_DICTIONARY: usize = 0x4;
+
+// The handles of a block: its record in the BLOCK_RECORD table, and the entities that begin and end
+// it in the BLOCKS section
+struct BlockHandles {
+    record: usize,
+    begin: usize,
+    end: usize,
+}
+
+const MODEL_SPACE: usize = 0x5;
+const BLOCKS: [(&str, BlockHandles); 2] = [
+    (
+        "*Model_Space",
+        BlockHandles {
+            record: MODEL_SPACE,
+            begin: 0x6,
+            end: 0x7,
+        },
+    ),
+    (
+        "*Paper_Space",
+        BlockHandles {
+            record: 0x8,
+            begin: 0x9,
+            end: 0xA,
+        },
+    ),
+];
+
+/// Writes the scene as a DXF drawing, for CAD software. Each tile is a closed polyline, on a layer
+/// named after its type, like "kite" or "small_rhombus", and the arcs, supertile outlines and
+/// vertex markers are arcs, lines and circles on their own layers. Each layer has the color of the
+/// group its elements were drawn with. The drawing is in millimeters, and flipped with `flip_y`.
+///
+/// By default, one view box unit is a millimeter. If `edge_length` is set, the drawing is scaled
+/// instead so that the longest edge of the tiles has that length, in millimeters, which is the
+/// size the tiles are manufactured at. Since that's only known after every tile is drawn, the
+/// whole drawing is kept in memory until then.
+pub struct DxfBackend<W> {
+    out: W,
+    edge_length: Option<f64>,
+    view_box_height: f64,
+
+    // The name and color of each layer, in the order in which they were first used
+    layers: Vec<(String, [u8; 4])>,
+    entities: Vec<(usize, Entity)>,
+    group_color: [u8; 4],
+    group_layer: usize,
+    longest_edge: f64,
+}
+
+impl<W: Write> DxfBackend<W> {
+    pub fn new(edge_length: Option<f64>, out: W) -> Self {
+        DxfBackend {
+            out,
+            edge_length,
+            view_box_height: 0.0,
+            layers: Vec::new(),
+            entities: Vec::new(),
+            group_color: [0, 0, 0, 255],
+            group_layer: 0,
+            longest_edge: 0.0,
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn layer(&mut self, name: &str) -> usize {
+        match self.layers.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                self.layers.push((name.to_string(), self.group_color));
+                self.layers.len() - 1
+            }
+        }
+    }
+
+    // Every value is written on its own line, after the line with its group code, which says what
+    // the value means
+    fn write_pair(&mut self, code: u16, value: impl std::fmt::Display) -> io::Result<()> {
+        writeln!(self.out, "{:>3}\n{}", code, value)
+    }
+
+    fn write_point(&mut self, Point(x, y): Point, scale: f64) -> io::Result<()> {
+        self.write_pair(10, format_args!("{:.6}", x * scale))?;
+        self.write_pair(20, format_args!("{:.6}", y * scale))
+    }
+
+    // The common part of every entity: its type, its handle, which must be unique in the file, the
+    // handle of the block record that owns it, and its layer
+    fn write_block_entity_start(
+        &mut self,
+        kind: &str,
+        handle: usize,
+        owner: usize,
+        layer: &str,
+    ) -> io::Result<()> {
+        self.write_pair(0, kind)?;
+        self.write_pair(5, format_args!("{:X}", handle))?;
+        self.write_pair(330, format_args!("{:X}", owner))?;
+        self.write_pair(100, "AcDbEntity")?;
+        self.write_pair(8, layer)
+    }
+
+    // The drawn entities are all in the model space
+    fn write_entity_start(&mut self, kind: &str, handle: usize, layer: usize) -> io::Result<()> {
+        let name = self.layers[layer].0.clone();
+        self.write_block_entity_start(kind, handle, MODEL_SPACE, &name)
+    }
+
+    fn write_section_start(&mut self, name: &str) -> io::Result<()> {
+        self.write_pair(0, "SECTION")?;
+        self.write_pair(2, name)
+    }
+
+    fn write_table_start(&mut self, name: &str, handle: usize, len: usize) -> io::Result<()> {
+        self.write_pair(0, "TABLE")?;
+        self.write_pair(2, name)?;
+        self.write_pair(5, format_args!("{:X}", handle))?;
+        self.write_pair(330, 0)?;
+        self.write_pair(100, "AcDbSymbolTable")?;
+        self.write_pair(70, len)
+    }
+
+    fn write_record_start(&mut self, kind: &str, handle: usize, table: usize) -> io::Result<()> {
+        self.write_pair(0, kind)?;
+        self.write_pair(5, format_args!("{:X}", handle))?;
+        self.write_pair(330, format_args!("{:X}", table))?;
+        self.write_pair(100, "AcDbSymbolTableRecord")
+    }
+
+    // The handle seed is the first handle that isn't used in the file
+    fn write_header(&mut self, handle_seed: usize) -> io::Result<()> {
+        self.write_section_start("HEADER")?;
+        self.write_pair(9, "$ACADVER")?;
+        self.write_pair(1, "AC1015")?;
+        self.write_pair(9, "$HANDSEED")?;
+        self.write_pair(5, format_args!("{:X}", handle_seed))?;
+
+        // The units of the drawing, where 4 means millimeters
+        self.write_pair(9, "$INSUNITS")?;
+        self.write_pair(70, 4)?;
+        self.write_pair(0, "ENDSEC")?;
+
+        // There are no custom objects, so there are no classes to declare
+        self.write_section_start("CLASSES")?;
+        self.write_pair(0, "ENDSEC")
+    }
+
+    // Every R2000 drawing has a model space and a paper space block, even when they are empty, and
+    // the entities belong to the model space. The layers are written with their true colors, and
+    // with white as the indexed color, for the software that doesn't support true colors
+    fn write_tables(&mut self) -> io::Result<()> {
+        self.write_section_start("TABLES")?;
+        self.write_table_start("BLOCK_RECORD", BLOCK_RECORD_TABLE, 2)?;
+        for (name, handle) in BLOCKS {
+            self.write_record_start("BLOCK_RECORD", handle.record, BLOCK_RECORD_TABLE)?;
+            self.write_pair(100, "AcDbBlockTableRecord")?;
+            self.write_pair(2, name)?;
+        }
+        self.write_pair(0, "ENDTAB")?;
+
+        // Layer "0" always exists, and the blocks are on it
+        let mut layers = vec![("0".to_string(), [255, 255, 255, 255])];
+        layers.extend(self.layers.iter().cloned());
+        self.write_table_start("LAYER", LAYER_TABLE, layers.len())?;
+        for (i, (name, [r, g, b, _])) in layers.into_iter().enumerate() {
+            let handle = if i == 0 { DEFAULT_LAYER } else { 0x10 + i - 1 };
+            self.write_record_start("LAYER", handle, LAYER_TABLE)?;
+            self.write_pair(100, "AcDbLayerTableRecord")?;
+            self.write_pair(2, name)?;
+            self.write_pair(70, 0)?;
+            self.write_pair(62, 7)?;
+            self.write_pair(420, (r as u32) << 16 | (g as u32) << 8 | b as u32)?;
+            self.write_pair(6, "CONTINUOUS")?;
+        }
+        self.write_pair(0, "ENDTAB")?;
+        self.write_pair(0, "ENDSEC")
+    }
+
+    fn write_blocks(&mut self) -> io::Result<()> {
+        self.write_section_start("BLOCKS")?;
+        for (name, handle) in BLOCKS {
+            self.write_block_entity_start("BLOCK", handle.begin, handle.record, "0")?;
+            self.write_pair(100, "AcDbBlockBegin")?;
+            self.write_pair(2, name)?;
+            self.write_pair(70, 0)?;
+            self.write_point(Point(0.0, 0.0), 1.0)?;
+            self.write_pair(30, format_args!("{:.6}", 0.0))?;
+            self.write_pair(3, name)?;
+            self.write_pair(1, "")?;
+            self.write_block_entity_start("ENDBLK", handle.end, handle.record, "0")?;
+            self.write_pair(100, "AcDbBlockEnd")?;
+        }
+        self.write_pair(0, "ENDSEC")
+    }
+
+    // The objects are only the root dictionary, which is empty
+    fn write_objects(&mut self) -> io::Result<()> {
+        self.write_section_start("OBJECTS")?;
+        self.write_pair(0, "DICTIONARY")?;
+        self.write_pair(5, format_args!("{:X}", ROOT_DICTIONARY))?;
+        self.write_pair(330, 0)?;
+        self.write_pair(100, "AcDbDictionary")?;
+        self.write_pair(281, 1)?;
+        self.write_pair(0, "ENDSEC")
+    }
+}
+
+impl<W: Write> Backend for DxfBackend<W> {
+    fn begin(&mut self, scene: &Scene) -> io::Result<()> {
+        self.view_box_height = scene.view_box_height as f64;
+        Ok(())
+    }
+
+    fn begin_group(&mut self, group: &Group) -> io::Result<()> {
+        self.group_color = group.color.rgba;
+        self.group_layer = match &group.kind {
+            // The layers of the tiles depend on their types, so they are chosen for each tile
+            GroupKind::Polygons(_) => return Ok(()),
+            GroupKind::FirstArcs => self.layer("first_arcs"),
+            GroupKind::SecondArcs => self.layer("second_arcs"),
+            GroupKind::Markers { name, .. } | GroupKind::Outlines { name, .. } => self.layer(name),
+        };
+        Ok(())
+    }
+
+    fn draw_polygon(&mut self, tile: &Tile) -> io::Result<()> {
+        let layer = self.layer(tile.tile_type.name());
+        let vertices: Vec<_> = tile
+            .vertices
+            .iter()
+            .map(|&p| flip_y(p, self.view_box_height))
+            .collect();
+        for (i, &p) in vertices.iter().enumerate() {
+            let edge = p.distance_to(vertices[(i + 1) % vertices.len()]);
+            self.longest_edge = self.longest_edge.max(edge);
+        }
+        self.entities.push((layer, Entity::Polyline(vertices)));
+        Ok(())
+    }
+
+    fn draw_arc(&mut self, arc: Arc) -> io::Result<()> {
+        let (start, center, end, _) = arc;
+        let [start, center, end] = [start, center, end].map(|p| flip_y(p, self.view_box_height));
+
+        // DXF arcs always go counterclockwise, so the ends are swapped if the arc doesn't
+        let (start, end) = if arc_is_counterclockwise(arc, false) {
+            (start, end)
+        } else {
+            (end, start)
+        };
+        let angle = |p: Point| (p.1 - center.1).atan2(p.0 - center.0).to_degrees();
+        let arc = Entity::Arc(center, center.distance_to(start), angle(start), angle(end));
+        self.entities.push((self.group_layer, arc));
+        Ok(())
+    }
+
+    fn draw_marker(&mut self, center: Point, radius: f64) -> io::Result<()> {
+        let circle = Entity::Circle(flip_y(center, self.view_box_height), radius);
+        self.entities.push((self.group_layer, circle));
+        Ok(())
+    }
+
+    fn draw_line(&mut self, start: Point, end: Point) -> io::Result<()> {
+        let line = Entity::Line(
+            flip_y(start, self.view_box_height),
+            flip_y(end, self.view_box_height),
+        );
+        self.entities.push((self.group_layer, line));
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let scale = match self.edge_length {
+            Some(length) if self.longest_edge > 0.0 => length / self.longest_edge,
+            _ => 1.0,
+        };
+        // The handles of the entities start after the ones of the layers
+        let first_handle = 0x10 + self.layers.len();
+        self.write_header(first_handle + self.entities.len())?;
+        self.write_tables()?;
+        self.write_blocks()?;
+        self.write_section_start("ENTITIES")?;
+        for (i, (layer, entity)) in std::mem::take(&mut self.entities).into_iter().enumerate() {
+            let handle = first_handle + i;
+            match entity {
+                Entity::Polyline(vertices) => {
+                    self.write_entity_start("LWPOLYLINE", handle, layer)?;
+                    self.write_pair(100, "AcDbPolyline")?;
+                    self.write_pair(90, vertices.len())?;
+
+                    // The polyline is closed
+                    self.write_pair(70, 1)?;
+                    for p in vertices {
+                        self.write_point(p, scale)?;
+                    }
+                }
+                Entity::Line(start, end) => {
+                    self.write_entity_start("LINE", handle, layer)?;
+                    self.write_pair(100, "AcDbLine")?;
+                    self.write_point(start, scale)?;
+                    self.write_pair(11, format_args!("{:.6}", end.0 * scale))?;
+                    self.write_pair(21, format_args!("{:.6}", end.1 * scale))?;
+                }
+                Entity::Circle(center, radius) => {
+                    self.write_entity_start("CIRCLE", handle, layer)?;
+                    self.write_pair(100, "AcDbCircle")?;
+                    self.write_point(center, scale)?;
+                    self.write_pair(40, format_args!("{:.6}", radius * scale))?;
+                }
+                Entity::Arc(center, radius, start_angle, end_angle) => {
+                    self.write_entity_start("ARC", handle, layer)?;
+                    self.write_pair(100, "AcDbCircle")?;
+                    self.write_point(center, scale)?;
+                    self.write_pair(40, format_args!("{:.6}", radius * scale))?;
+                    self.write_pair(100, "AcDbArc")?;
+                    self.write_pair(50, format_args!("{:.6}", start_angle))?;
+                    self.write_pair(51, format_args!("{:.6}", end_angle))?;
+                }
+            }
+        }
+        self.write_pair(0, "ENDSEC")?;
+        self.write_objects()?;
+        self.write_pair(0, "EOF")?;
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Reads a DXF file as a list of group codes and values
+    fn pairs(dxf: &str) -> Vec<(u16, String)> {
+        let lines: Vec<_> = dxf.lines().collect();
+        lines
+            .chunks(2)
+            .map(|pair| (pair[0].trim().parse().unwrap(), pair[1].to_string()))
+            .collect()
+    }
+
+    // Reads the entities of a section as lists of group codes and values
+    fn section(pairs: &[(u16, String)], name: &str) -> Vec<Vec<(u16, String)>> {
+        let start = pairs
+            .iter()
+            .position(|p| *p == (2, name.to_string()))
+            .unwrap()
+            + 1;
+        let mut entities: Vec<Vec<(u16, String)>> = Vec::new();
+        for pair in pairs[start..].iter().take_while(|p| p.1 != "ENDSEC") {
+            if pair.0 == 0 {
+                entities.push(Vec::new());
+            }
+            entities.last_mut().unwrap().push(pair.clone());
+        }
+        entities
+    }
+
+    #[test]
+    fn test_dxf_export() {
+        let mut backend = DxfBackend::new(Some(20.0), Vec::new());
+        let num_tiles = crate::render::render_seed(
+            crate::seeds::rose(),
+            2,
+            Some(("green", "yellow")),
+            &mut backend,
+        );
+        let dxf = String::from_utf8(backend.into_inner()).unwrap();
+        assert!(dxf.ends_with("  0\nEOF\n"));
+        assert!(dxf.contains("  2\nsmall_rhombus\n 70\n0\n 62\n7\n420\n16711680\n"));
+        assert!(dxf.contains("  2\nlarge_rhombus\n 70\n0\n 62\n7\n420\n255\n"));
+
+        // The sections of an R2000 drawing are all there, in order
+        let pairs = pairs(&dxf);
+        let sections: Vec<_> = pairs
+            .windows(2)
+            .filter(|w| w[0].1 == "SECTION")
+            .map(|w| w[1].1.as_str())
+            .collect();
+        assert_eq!(
+            sections,
+            ["HEADER", "CLASSES", "TABLES", "BLOCKS", "ENTITIES", "OBJECTS"]
+        );
+        let blocks = section(&pairs, "BLOCKS");
+        let names: Vec<_> = blocks
+            .iter()
+            .filter_map(|b| b.iter().find(|p| p.0 == 2))
+            .collect();
+        assert_eq!(names.len(), 2);
+        assert!(names
+            .iter()
+            .all(|p| p.1 == "*Model_Space" || p.1 == "*Paper_Space"));
+
+        // The handles are unique, and the handle seed, which is the first value with their group
+        // code, is after all of them
+        let mut handles: Vec<_> = pairs
+            .iter()
+            .filter(|p| p.0 == 5)
+            .map(|p| usize::from_str_radix(&p.1, 16).unwrap())
+            .collect();
+        let seed = handles.remove(0);
+        let num_handles = handles.len();
+        handles.sort();
+        handles.dedup();
+        assert_eq!(handles.len(), num_handles);
+        assert!(handles.iter().all(|&h| h < seed));
+
+        let entities = section(&pairs, "ENTITIES");
+        let count = |kind: &str| entities.iter().filter(|e| e[0].1 == kind).count();
+        assert_eq!(count("LWPOLYLINE"), num_tiles);
+        assert_eq!(count("ARC"), 2 * num_tiles);
+        assert_eq!(count("LWPOLYLINE") + count("ARC"), entities.len());
+
+        // Every edge of the rhombuses has the requested length
+        let value = |e: &[(u16, String)], code| -> Vec<f64> {
+            e.iter()
+                .filter(|p| p.0 == code)
+                .map(|p| p.1.parse().unwrap())
+                .collect()
+        };
+        for polyline in entities.iter().filter(|e| e[0].1 == "LWPOLYLINE") {
+            let (xs, ys) = (value(polyline, 10), value(polyline, 20));
+            assert_eq!(xs.len(), 4);
+            for i in 0..4 {
+                let (j, k) = (i, (i + 1) % 4);
+                let edge = Point(xs[j], ys[j]).distance_to(Point(xs[k], ys[k]));
+                assert!((edge - 20.0).abs() < 1e-5);
+            }
+        }
+    }
+}
@@ -0,0 +1,312 @@
+use crate::cut::EdgeSet;
+use crate::geometry::*;
+use crate::render::{Backend, Scene, Tile};
+
+use std::io::{self, Write};
+
+/// The settings of the machine that the G-code is written for.
+#[derive(Debug, Clone)]
+pub struct GcodeConfig {
+    /// The speed of the drawing moves, in millimeters per minute.
+    pub feed_rate: f64,
+
+    /// The commands that lift the pen or the tool, and lower it, each written on its own line.
+    pub pen_up: String,
+    pub pen_down: String,
+}
+
+/// A part of a path, in the machine coordinates.
+#[derive(Debug, Copy, Clone)]
+pub enum Segment {
+    Line(Point, Point),
+    Arc {
+        start: Point,
+        center: Point,
+        end: Point,
+        clockwise: bool,
+    },
+}
+
+impl Segment {
+    pub fn start(&self) -> Point {
+        match *self {
+            Segment::Line(start, _) | Segment::Arc { start, .. } => start,
+        }
+    }
+
+    pub fn end(&self) -> Point {
+        match *self {
+            Segment::Line(_, end) | Segment::Arc { end, .. } => end,
+        }
+    }
+
+    /// The same segment, followed in the opposite direction.
+    pub fn reversed(self) -> Self {
+        match self {
+            Segment::Line(start, end) => Segment::Line(end, start),
+            Segment::Arc {
+                start,
+                center,
+                end,
+                clockwise,
+            } => Segment::Arc {
+                start: end,
+                center,
+                end: start,
+                clockwise: !clockwise,
+            },
+        }
+    }
+}
+
+/// Sorts the paths so that the pen travels a short distance between them, starting from `from`.
+/// Each path may be reversed, so that it starts at its end closest to the pen. This picks the
+/// nearest path every time, which isn't optimal, but is usually good enough, and takes quadratic
+/// time in the number of paths.
+pub fn order_paths(mut paths: Vec<Vec<Segment>>, from: Point) -> Vec<Vec<Segment>> {
+    let mut ordered = Vec::with_capacity(paths.len());
+    let mut position = from;
+    while !paths.is_empty() {
+        let distance = |p: Point| position.distance_to(p);
+        let (index, reverse) = paths
+            .iter()
+            .enumerate()
+            .flat_map(|(i, path)| {
+                let (start, end) = (path[0].start(), path[path.len() - 1].end());
+                vec![(i, false, distance(start)), (i, true, distance(end))]
+            })
+            .min_by(|a, b| a.2.total_cmp(&b.2))
+            .map(|(i, reverse, _)| (i, reverse))
+            .unwrap();
+        let mut path = paths.swap_remove(index);
+        if reverse {
+            path = path.into_iter().rev().map(Segment::reversed).collect();
+        }
+        position = path[path.len() - 1].end();
+        ordered.push(path);
+    }
+    ordered
+}
+
+/// The distance that the pen travels between the paths, without drawing, starting from `from`.
+pub fn travel_distance(paths: &[Vec<Segment>], from: Point) -> f64 {
+    let mut position = from;
+    let mut distance = 0.0;
+    for path in paths {
+        distance += position.distance_to(path[0].start());
+        position = path[path.len() - 1].end();
+    }
+    distance
+}
+
+// Numbers are written in millimeters, with a precision of a micrometer, which is more than any
+// plotter needs
+fn write_point<W: Write>(out: &mut W, command: &str, Point(x, y): Point) -> io::Result<()> {
+    write!(out, "{} X{:.3} Y{:.3}", command, x, y)
+}
+
+/// Writes the scene as G-code, for pen plotters and CNC machines. Like the cut format, every edge
+/// of the tiles is drawn once, and the edges are joined into paths, which are drawn together with
+/// the arcs, outlines and markers, in the order that makes the pen travel the least between them.
+/// The colors are ignored. The coordinates are in millimeters, one for each view box unit, and
+/// flipped with `flip_y`.
+pub struct GcodeBackend<W> {
+    config: GcodeConfig,
+    out: W,
+    view_box_height: f64,
+    edges: EdgeSet,
+    paths: Vec<Vec<Segment>>,
+}
+
+impl<W: Write> GcodeBackend<W> {
+    pub fn new(config: GcodeConfig, out: W) -> Self {
+        GcodeBackend {
+            config,
+            out,
+            view_box_height: 0.0,
+            edges: EdgeSet::new(),
+            paths: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    // Writes the moves of a path. Unless the path continues the previous one, the pen is lifted,
+    // and moved to the start of the path first
+    fn write_path(&mut self, path: &[Segment], continues: bool) -> io::Result<()> {
+        if !continues {
+            writeln!(self.out, "{}", self.config.pen_up)?;
+            write_point(&mut self.out, "G0", path[0].start())?;
+            writeln!(self.out)?;
+            writeln!(self.out, "{}", self.config.pen_down)?;
+        }
+        for segment in path {
+            match *segment {
+                Segment::Line(_, end) => write_point(&mut self.out, "G1", end)?,
+                Segment::Arc {
+                    start,
+                    center,
+                    end,
+                    clockwise,
+                } => {
+                    let command = if clockwise { "G2" } else { "G3" };
+                    write_point(&mut self.out, command, end)?;
+
+                    // The center is given relative to the start of the arc
+                    let Point(i, j) = center - start;
+                    write!(self.out, " I{:.3} J{:.3}", i, j)?;
+                }
+            }
+            writeln!(self.out, " F{}", self.config.feed_rate)?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Backend for GcodeBackend<W> {
+    fn begin(&mut self, scene: &Scene) -> io::Result<()> {
+        self.view_box_height = scene.view_box_height as f64;
+        writeln!(self.out, "; Penrose tiling generated by rose")?;
+        writeln!(self.out, "G21 ; millimeters")?;
+        writeln!(self.out, "G90 ; absolute coordinates")
+    }
+
+    fn draw_polygon(&mut self, tile: &Tile) -> io::Result<()> {
+        self.edges.add_tile(tile);
+        Ok(())
+    }
+
+    fn draw_arc(&mut self, arc: Arc) -> io::Result<()> {
+        let (start, center, end, _) = arc;
+        let [start, center, end] = [start, center, end].map(|p| flip_y(p, self.view_box_height));
+        self.paths.push(vec![Segment::Arc {
+            start,
+            center,
+            end,
+            clockwise: !arc_is_counterclockwise(arc, false),
+        }]);
+        Ok(())
+    }
+
+    // A marker is a full circle, which is an arc that ends where it starts
+    fn draw_marker(&mut self, center: Point, radius: f64) -> io::Result<()> {
+        let center = flip_y(center, self.view_box_height);
+        let start = center + Point(radius, 0.0);
+        self.paths.push(vec![Segment::Arc {
+            start,
+            center,
+            end: start,
+            clockwise: true,
+        }]);
+        Ok(())
+    }
+
+    fn draw_line(&mut self, start: Point, end: Point) -> io::Result<()> {
+        let segment = Segment::Line(
+            flip_y(start, self.view_box_height),
+            flip_y(end, self.view_box_height),
+        );
+        self.paths.push(vec![segment]);
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let mut paths = std::mem::take(&mut self.paths);
+        for points in self.edges.join_paths() {
+            let points: Vec<_> = points
+                .into_iter()
+                .map(|p| flip_y(p, self.view_box_height))
+                .collect();
+            paths.push(
+                points
+                    .windows(2)
+                    .map(|w| Segment::Line(w[0], w[1]))
+                    .collect(),
+            );
+        }
+        let mut position = None;
+        for path in order_paths(paths, Point::ZERO) {
+            let continues = position.is_some_and(|p| close(p, path[0].start()));
+            self.write_path(&path, continues)?;
+            position = Some(path[path.len() - 1].end());
+        }
+        writeln!(self.out, "{}", self.config.pen_up)?;
+        write_point(&mut self.out, "G0", Point::ZERO)?;
+        writeln!(self.out, "\nM2")?;
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_order_paths() {
+        // Three segments along a line, in the worst order, and one of them backwards
+        let paths = vec![
+            vec![Segment::Line(Point(4.0, 0.0), Point(5.0, 0.0))],
+            vec![Segment::Line(Point(1.0, 0.0), Point(0.0, 0.0))],
+            vec![Segment::Line(Point(2.0, 0.0), Point(3.0, 0.0))],
+        ];
+        assert_eq!(travel_distance(&paths, Point::ZERO), 10.0);
+        let ordered = order_paths(paths, Point::ZERO);
+        assert_eq!(travel_distance(&ordered, Point::ZERO), 2.0);
+        assert!(close(ordered[0][0].start(), Point::ZERO));
+
+        let arc = Segment::Arc {
+            start: Point(1.0, 0.0),
+            center: Point::ZERO,
+            end: Point(0.0, 1.0),
+            clockwise: false,
+        };
+        let reversed = arc.reversed();
+        assert!(close(reversed.start(), arc.end()));
+        assert!(close(reversed.end(), arc.start()));
+        assert!(matches!(
+            reversed,
+            Segment::Arc {
+                clockwise: true,
+                ..
+            }
+        ));
+    }
+
+    #[test]
+    fn test_gcode_export() {
+        let config = GcodeConfig {
+            feed_rate: 500.0,
+            pen_up: "M5".to_string(),
+            pen_down: "M3".to_string(),
+        };
+        let mut backend = GcodeBackend::new(config, Vec::new());
+        let num_tiles = crate::render::render_seed(
+            crate::seeds::rose(),
+            2,
+            Some(("green", "yellow")),
+            &mut backend,
+        );
+        let gcode = String::from_utf8(backend.into_inner()).unwrap();
+
+        // Every arc is drawn, and the pen goes down after each move to the start of a path that
+        // doesn't continue the previous one
+        let arcs = gcode.matches("\nG2 ").count() + gcode.matches("\nG3 ").count();
+        assert_eq!(arcs, 2 * num_tiles);
+        assert_eq!(
+            gcode.matches("\nM3\n").count(),
+            gcode.matches("\nG0 ").count() - 1
+        );
+        assert_eq!(
+            gcode.matches("\nM3\n").count() + 1,
+            gcode.matches("\nM5\n").count()
+        );
+        assert!(gcode.contains("G21"));
+        assert!(gcode.ends_with("G0 X0.000 Y0.000\nM2\n"));
+        assert!(gcode
+            .lines()
+            .filter(|l| l.starts_with("G1"))
+            .all(|l| l.ends_with(" F500")));
+    }
+}
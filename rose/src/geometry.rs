@@ -1,3 +1,6 @@
+use crate::golden::*;
+use std::fmt::Debug;
+use std::hash::Hash;
 use std::ops;
 
 // Unfortunately, Rust doesn't yet allow square roots in constant contexts
@@ -21,6 +24,7 @@ impl Close for f64 {
     }
 }
 
+#[cfg(test)]
 macro_rules! assert_close {
     ($a:expr, $b:expr $(,)?) => {
         assert!(close($a, $b))
@@ -39,6 +43,22 @@ pub trait Transform: Sized {
     fn mirror_y(&self) -> Self;
 }
 
+/// A point whose coordinates can be represented exactly, as `p + qω`, where ω is a unit vector, and
+/// `p` and `q` are numbers of the form `a + bx`, for integers `a` and `b` and an irrational `x`.
+/// Like the Penrose tilings with `GoldenPoint`, every tiling whose edges point in a few fixed
+/// directions can be built with one of these, so that its vertices can be compared and hashed
+/// exactly.
+pub trait ExactPoint:
+    Debug + Copy + Eq + Ord + Hash + Send + Sync + ops::Add<Output = Self> + ops::Sub<Output = Self>
+{
+    /// Converts the point to floating point coordinates.
+    fn to_point(self) -> Point;
+
+    /// Returns the integer coefficients of the point, as `[[a, b], [c, d]]`, meaning the point
+    /// `(a + bx) + (c + dx)ω`.
+    fn coefficients(self) -> [[i64; 2]; 2];
+}
+
 #[derive(Debug, Copy, Clone)]
 pub struct Point(pub f64, pub f64);
 
@@ -53,6 +73,10 @@ impl Point {
         *self / Point::ZERO.distance_to(*self)
     }
 
+    pub fn dot(&self, other: Point) -> f64 {
+        self.0 * other.0 + self.1 * other.1
+    }
+
     pub fn cross(&self, other: Point) -> f64 {
         self.0 * other.1 - self.1 * other.0
     }
@@ -154,58 +178,239 @@ impl Line {
     }
 }
 
-#[derive(Debug, PartialEq, Copy, Clone)]
+/// An axis-aligned rectangle, given by its minimum and maximum corners.
+#[derive(Debug, Copy, Clone)]
+pub struct Rectangle {
+    pub min: Point,
+    pub max: Point,
+}
+
+impl Rectangle {
+    /// Returns the smallest rectangle that contains all of the given points.
+    pub fn bounding(points: &[Point]) -> Self {
+        let first = points[0];
+        points.iter().fold(
+            Rectangle {
+                min: first,
+                max: first,
+            },
+            |Rectangle { min, max }, p| Rectangle {
+                min: Point(min.0.min(p.0), min.1.min(p.1)),
+                max: Point(max.0.max(p.0), max.1.max(p.1)),
+            },
+        )
+    }
+
+    pub fn contains(&self, p: Point) -> bool {
+        (self.min.0..=self.max.0).contains(&p.0) && (self.min.1..=self.max.1).contains(&p.1)
+    }
+
+    pub fn intersects(&self, other: &Rectangle) -> bool {
+        self.min.0 <= other.max.0
+            && other.min.0 <= self.max.0
+            && self.min.1 <= other.max.1
+            && other.min.1 <= self.max.1
+    }
+
+    /// Returns true if the rectangle intersects the convex polygon with the given vertices.
+    pub fn intersects_convex_polygon(&self, vertices: &[Point]) -> bool {
+        // By the separating axis theorem, two convex polygons don't intersect if and only if there
+        // is a line perpendicular to one of their sides that separates them. For the rectangle,
+        // these are the coordinate axes, which is the same as checking the bounding box
+        if !self.intersects(&Rectangle::bounding(vertices)) {
+            return false;
+        }
+        let corners = self.corners();
+        let n = vertices.len();
+        (0..n).all(|i| {
+            let side = vertices[(i + 1) % n] - vertices[i];
+            let normal = Point(-side.1, side.0);
+            let project = |points: &[Point]| {
+                points
+                    .iter()
+                    .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), p| {
+                        let x = p.dot(normal);
+                        (min.min(x), max.max(x))
+                    })
+            };
+            let (min_a, max_a) = project(&corners);
+            let (min_b, max_b) = project(vertices);
+            min_a <= max_b && min_b <= max_a
+        })
+    }
+
+    /// Returns the rectangle grown by `margin` in every direction.
+    pub fn expand(&self, margin: f64) -> Self {
+        let margin = Point(margin, margin);
+        Rectangle {
+            min: self.min - margin,
+            max: self.max + margin,
+        }
+    }
+
+    pub fn corners(&self) -> [Point; 4] {
+        [
+            self.min,
+            Point(self.max.0, self.min.1),
+            self.max,
+            Point(self.min.0, self.max.1),
+        ]
+    }
+}
+
+/// Which of the two tile colors a tile is filled with.
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
+pub enum QuadColor {
+    First,
+    Second,
+}
+
+#[derive(Debug, PartialEq, Eq, Hash, Copy, Clone)]
 pub enum TileType {
     SmallRhombus,
     LargeRhombus,
     Kite,
     Dart,
+
+    // The tiles of Penrose's first tiling, P1, which can't be split in Robinson triangles. The
+    // pentagons are told apart by how many other pentagons they share a side with
+    Pentagon2,
+    Pentagon3,
+    Pentagon5,
+    Star,
+    Boat,
+    Diamond,
+
+    // The tiles of the Ammann-Beenker tiling, which have angles that are multiples of 45 degrees
+    Square,
+    Rhomb,
+
+    // The aperiodic monotiles, which are only ever drawn whole
+    Hat,
+    Spectre,
 }
 
 impl TileType {
-    pub fn base_to_side_ratio(self) -> f64 {
+    pub const ALL: [TileType; 14] = [
+        TileType::SmallRhombus,
+        TileType::LargeRhombus,
+        TileType::Kite,
+        TileType::Dart,
+        TileType::Pentagon2,
+        TileType::Pentagon3,
+        TileType::Pentagon5,
+        TileType::Star,
+        TileType::Boat,
+        TileType::Diamond,
+        TileType::Square,
+        TileType::Rhomb,
+        TileType::Hat,
+        TileType::Spectre,
+    ];
+
+    /// The name of the tile type, as it is written in the exported data and in rule files.
+    pub fn name(self) -> &'static str {
+        match self {
+            TileType::SmallRhombus => "small_rhombus",
+            TileType::LargeRhombus => "large_rhombus",
+            TileType::Kite => "kite",
+            TileType::Dart => "dart",
+            TileType::Pentagon2 => "pentagon_2",
+            TileType::Pentagon3 => "pentagon_3",
+            TileType::Pentagon5 => "pentagon_5",
+            TileType::Star => "star",
+            TileType::Boat => "boat",
+            TileType::Diamond => "diamond",
+            TileType::Square => "square",
+            TileType::Rhomb => "rhomb",
+            TileType::Hat => "hat",
+            TileType::Spectre => "spectre",
+        }
+    }
+
+    /// Returns the tile type with the given name, as given by `name`.
+    pub fn from_name(name: &str) -> Option<Self> {
+        TileType::ALL.iter().copied().find(|t| t.name() == name)
+    }
+
+    /// The tile color that tiles of this type are filled with. Each tiling splits its tile types
+    /// between the two colors.
+    pub fn quad_color(self) -> QuadColor {
         match self {
-            TileType::SmallRhombus => PHI_INVERSE,
-            TileType::LargeRhombus => PHI,
+            TileType::SmallRhombus | TileType::Dart => QuadColor::First,
+            TileType::LargeRhombus | TileType::Kite => QuadColor::Second,
+            TileType::Star | TileType::Boat | TileType::Diamond => QuadColor::First,
+            TileType::Pentagon2 | TileType::Pentagon3 | TileType::Pentagon5 => QuadColor::Second,
+            TileType::Rhomb => QuadColor::First,
+            TileType::Square => QuadColor::Second,
+            TileType::Hat | TileType::Spectre => QuadColor::Second,
+        }
+    }
+
+    /// Returns the ratio of the base to the sides of the Robinson triangle. Panics for the P1,
+    /// Ammann-Beenker and monotile tile types, which aren't made of Robinson triangles.
+    pub fn base_to_side_ratio(self) -> Golden {
+        match self {
+            TileType::SmallRhombus => Golden::PHI_INVERSE,
+            TileType::LargeRhombus => Golden::PHI,
             // For the Kite and Dart tiles, we consider the ratio of the base to the largest side
-            TileType::Kite => 1.0,
-            TileType::Dart => PHI_INVERSE,
+            TileType::Kite => Golden::ONE,
+            TileType::Dart => Golden::PHI_INVERSE,
+            _ => panic!("{:?} is not a Robinson triangle", self),
         }
     }
 }
 
-#[derive(Clone)]
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
 pub struct RobinsonTriangle {
     pub triangle_type: TileType,
-    pub a: Point,
-    pub b: Point,
-    pub c: Point,
+    pub a: GoldenPoint,
+    pub b: GoldenPoint,
+    pub c: GoldenPoint,
 }
 
 impl RobinsonTriangle {
-    pub fn infer_triangle_type(a: Point, b: Point, c: Point) -> TileType {
+    pub fn infer_triangle_type(a: GoldenPoint, b: GoldenPoint, c: GoldenPoint) -> TileType {
+        RobinsonTriangle::try_infer_triangle_type(a, b, c)
+            .expect("Triangle sides are of invalid ratio")
+    }
+
+    /// Returns the type of the Robinson triangle with the given vertices, or `None` if the
+    /// triangle doesn't have the shape of any of them.
+    pub fn try_infer_triangle_type(
+        a: GoldenPoint,
+        b: GoldenPoint,
+        c: GoldenPoint,
+    ) -> Option<TileType> {
+        // Since the coordinates are exact, we can compare the squares of the side lengths directly,
+        // instead of comparing the ratios between them
         let (ab, bc, ca) = (
-            Line(a, b).length(),
-            Line(b, c).length(),
-            Line(c, a).length(),
+            (b - a).norm_squared(),
+            (c - b).norm_squared(),
+            (a - c).norm_squared(),
         );
-        let ratio = ca / ab;
+        let has_ratio = |tile_type: TileType| {
+            let ratio = tile_type.base_to_side_ratio();
+            ca == ratio * ratio * ab
+        };
 
         // For the rhombus triangles, we must make sure that the triangle is isoceles
-        if close(ab, bc) && close(ratio, TileType::LargeRhombus.base_to_side_ratio()) {
-            TileType::LargeRhombus
-        } else if close(ab, bc) && close(ratio, TileType::SmallRhombus.base_to_side_ratio()) {
-            TileType::SmallRhombus
-        } else if close(ratio, TileType::Kite.base_to_side_ratio()) {
-            TileType::Kite
-        } else if close(ratio, TileType::Dart.base_to_side_ratio()) {
-            TileType::Dart
+        if ab == Golden::ZERO {
+            None
+        } else if ab == bc && has_ratio(TileType::LargeRhombus) {
+            Some(TileType::LargeRhombus)
+        } else if ab == bc && has_ratio(TileType::SmallRhombus) {
+            Some(TileType::SmallRhombus)
+        } else if has_ratio(TileType::Kite) {
+            Some(TileType::Kite)
+        } else if has_ratio(TileType::Dart) {
+            Some(TileType::Dart)
         } else {
-            panic!("Triangle sides are of invalid ratio")
+            None
         }
     }
 
-    pub fn new(a: Point, b: Point, c: Point) -> Self {
+    pub fn new(a: GoldenPoint, b: GoldenPoint, c: GoldenPoint) -> Self {
         let triangle_type = RobinsonTriangle::infer_triangle_type(a, b, c);
         RobinsonTriangle {
             triangle_type,
@@ -215,12 +420,17 @@ impl RobinsonTriangle {
         }
     }
 
-    pub fn from_base(a: Point, c: Point, triangle_type: TileType, right_handed: bool) -> Self {
+    pub fn from_base(
+        a: GoldenPoint,
+        c: GoldenPoint,
+        triangle_type: TileType,
+        right_handed: bool,
+    ) -> Self {
         let rotation = {
-            // The angle at the a vertex
+            // The angle at the a vertex, in multiples of 36 degrees
             let a_angle = match triangle_type {
-                TileType::SmallRhombus => 72.0,
-                _ => 36.0,
+                TileType::SmallRhombus => 2,
+                _ => 1,
             };
 
             // If the triangle is right-handed -- that is, if the a to b to c path makes a right
@@ -233,13 +443,11 @@ impl RobinsonTriangle {
             }
         };
 
-        // Normalized direction vector from a to b
-        let direction_to_b = (c - a).rotate(rotation).normalized();
-
         // The length of ab is simply the base length divided by the base to side ratio, since in
-        // Kite and Dart triangles the largest side is ab
-        let ab_length = Line(a, c).length() / triangle_type.base_to_side_ratio();
-        let b = a + ab_length * direction_to_b;
+        // Kite and Dart triangles the largest side is ab. All ratios are powers of phi, so they
+        // always have an inverse
+        let side_to_base_ratio = triangle_type.base_to_side_ratio().recip().unwrap();
+        let b = a + side_to_base_ratio * (c - a).rotate_steps(rotation);
 
         RobinsonTriangle {
             triangle_type,
@@ -249,15 +457,28 @@ impl RobinsonTriangle {
         }
     }
 
-    /// Returns the median point of the triangle's base.
-    pub fn base_median(&self) -> Point {
-        (self.a + self.c) / 2.0
+    /// Returns the triangle that is the reflection of this one across its base. Together, the two
+    /// triangles make up a whole tile.
+    pub fn mirror(&self) -> Self {
+        let c = RobinsonTriangle::from_base(self.a, self.c, self.triangle_type, true);
+        if c.b == self.b {
+            RobinsonTriangle::from_base(self.a, self.c, self.triangle_type, false)
+        } else {
+            c
+        }
+    }
+
+    /// Returns the sum of the triangle's base vertices, which is twice its base median. Unlike the
+    /// median itself, this can always be represented exactly, so it can be used to find triangles
+    /// that share the same base.
+    pub fn double_base_median(&self) -> GoldenPoint {
+        self.a + self.c
     }
 }
 
 impl Close for RobinsonTriangle {
     fn is_close(&self, other: &Self) -> bool {
-        close(self.a, other.a) && close(self.b, other.b) && close(self.c, other.c)
+        self == other
     }
 }
 
@@ -281,11 +502,80 @@ impl Transform for RobinsonTriangle {
 
 pub type Arc = (Point, Point, Point, bool); // Start, center, end, large angle flag
 
-pub struct Quadrilateral {
-    pub a: Point,
-    pub b: Point,
-    pub c: Point,
-    pub d: Point,
+/// Returns true if the arc goes counterclockwise from its start to its end, when the y axis points
+/// up. The arc is given in coordinates where the y axis points up if `y_up` is set, and down, like
+/// the tiling and view box coordinates, otherwise, which mirrors the arc.
+pub fn arc_is_counterclockwise((start, center, end, large_angle_flag): Arc, y_up: bool) -> bool {
+    // A positive cross product means that the shortest rotation from the start to the end is
+    // counterclockwise. If the arc is supposed to be the largest rotation, it goes the other way
+    let shortest_counterclockwise = (start - center).cross(end - center) > 0.0;
+    (shortest_counterclockwise != large_angle_flag) == y_up
+}
+
+/// Flips a point of a view box with the given height upside down, so that the origin is at the
+/// bottom left corner and the y axis points up, like in CAD software and on most machines.
+pub fn flip_y(Point(x, y): Point, view_box_height: f64) -> Point {
+    Point(x, view_box_height - y)
+}
+
+/// Approximates a circular arc with cubic Bézier curves, each covering at most a quarter turn.
+/// Returns the control points and the end point of each curve, in order. The first curve starts
+/// at the start of the arc.
+pub fn arc_to_cubics((start, center, end, large_angle_flag): Arc) -> Vec<[Point; 3]> {
+    use std::f64::consts::PI;
+
+    // Find the angle the arc sweeps, going from start to end. If the arc is supposed to take the
+    // longest way around, we have to go in the other direction
+    let radius = Line(start, center).length();
+    let start_angle = (start.1 - center.1).atan2(start.0 - center.0);
+    let end_angle = (end.1 - center.1).atan2(end.0 - center.0);
+    let mut sweep = end_angle - start_angle;
+    if sweep > PI {
+        sweep -= 2.0 * PI;
+    } else if sweep <= -PI {
+        sweep += 2.0 * PI;
+    }
+    if large_angle_flag {
+        sweep -= 2.0 * PI * sweep.signum();
+    }
+
+    let num_segments = (sweep.abs() / (PI / 2.0)).ceil().max(1.0) as usize;
+    let step = sweep / num_segments as f64;
+    let handle = 4.0 / 3.0 * (step / 4.0).tan() * radius;
+    let point_at = |angle: f64| center + radius * Point(angle.cos(), angle.sin());
+    let tangent_at = |angle: f64| Point(-angle.sin(), angle.cos());
+    (0..num_segments)
+        .map(|i| {
+            let from = start_angle + step * i as f64;
+            let to = start_angle + step * (i + 1) as f64;
+            [
+                point_at(from) + handle * tangent_at(from),
+                point_at(to) - handle * tangent_at(to),
+                point_at(to),
+            ]
+        })
+        .collect()
+}
+
+/// A tile made of two triangles that share their base, from `a` to `c`. The vertices are exact
+/// points of any kind, but they are `GoldenPoint`s for the Penrose tilings.
+#[derive(Debug, Clone)]
+pub struct Quadrilateral<P = GoldenPoint> {
+    pub a: P,
+    pub b: P,
+    pub c: P,
+    pub d: P,
+}
+
+impl Quadrilateral {
+    /// Splits the quadrilateral into the two Robinson triangles that compose it. The triangles
+    /// share their base, from `a` to `c`, and are mirror images of each other.
+    pub fn split(&self) -> [RobinsonTriangle; 2] {
+        [
+            RobinsonTriangle::new(self.a, self.b, self.c),
+            RobinsonTriangle::new(self.a, self.d, self.c),
+        ]
+    }
 }
 
 // Useful for tests
@@ -299,6 +589,8 @@ pub fn random_point<R: rand::Rng>(rng: &mut R, min: f64, max: f64) -> Point {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::golden::random_golden_point;
+    use rand::Rng;
 
     #[test]
     fn test_point_arithmetic() {
@@ -336,14 +628,15 @@ mod tests {
         }
     }
 
-    fn run_transform_tests<T: Transform + Close + Clone, R: rand::Rng>(obj: &T, rng: &mut R) {
+    // The angle `x` is passed by the caller, since exact types can only be rotated by multiples of
+    // 36 degrees
+    fn run_transform_tests<T: Transform + Close + Clone>(obj: &T, x: f64) {
         assert_close!(obj.mirror_x().mirror_x(), obj.clone());
         assert_close!(obj.mirror_y().mirror_y(), obj.clone());
         assert_close!(obj.mirror_x().mirror_y(), obj.mirror_y().mirror_x());
         assert_close!(obj.mirror_x().mirror_y(), obj.rotate(180.0));
         assert_close!(obj.rotate(0.0), obj.clone());
         assert_close!(obj.rotate(360.0), obj.clone());
-        let x = rng.gen_range(0.0..360.0);
         assert_close!(obj.rotate(x).rotate(x), obj.rotate(2.0 * x));
         assert_close!(obj.rotate(x).rotate(-x), obj.clone());
     }
@@ -353,7 +646,7 @@ mod tests {
         let mut rng = rand::thread_rng();
         for _ in 0..10_000 {
             let a = random_point(&mut rng, -1000.0, 1000.0);
-            run_transform_tests(&a, &mut rng);
+            run_transform_tests(&a, rng.gen_range(0.0..360.0));
         }
     }
 
@@ -361,19 +654,14 @@ mod tests {
     fn test_triangle_transform() {
         let mut rng = rand::thread_rng();
         for _ in 0..10_000 {
-            let a = random_point(&mut rng, -1000.0, 1000.0);
-            let c = random_point(&mut rng, -1000.0, 1000.0);
+            let a = random_golden_point(&mut rng, -1000, 1000);
+            let c = random_golden_point(&mut rng, -1000, 1000);
+            let x = rng.gen_range(0..10) as f64 * 36.0;
             use TileType::*;
             let types = [SmallRhombus, LargeRhombus, Kite, Dart];
             for &triangle_type in &types {
-                run_transform_tests(
-                    &RobinsonTriangle::from_base(a, c, triangle_type, false),
-                    &mut rng,
-                );
-                run_transform_tests(
-                    &RobinsonTriangle::from_base(a, c, triangle_type, true),
-                    &mut rng,
-                );
+                run_transform_tests(&RobinsonTriangle::from_base(a, c, triangle_type, false), x);
+                run_transform_tests(&RobinsonTriangle::from_base(a, c, triangle_type, true), x);
             }
         }
     }
@@ -382,20 +670,55 @@ mod tests {
     fn test_triangle_from_base() {
         let mut rng = rand::thread_rng();
         for _ in 0..10_000 {
-            let a = random_point(&mut rng, -1000.0, 1000.0);
-            let c = random_point(&mut rng, -1000.0, 1000.0);
+            let a = random_golden_point(&mut rng, -1000, 1000);
+            let c = random_golden_point(&mut rng, -1000, 1000);
             use TileType::*;
             let types = [SmallRhombus, LargeRhombus, Kite, Dart];
             for &triangle_type in &types {
-                assert_eq!(
-                    RobinsonTriangle::from_base(a, c, triangle_type, false).triangle_type,
-                    triangle_type
-                );
-                assert_eq!(
-                    RobinsonTriangle::from_base(a, c, triangle_type, true).triangle_type,
-                    triangle_type
-                );
+                // Since `from_base` doesn't infer the triangle type, we have to check it by creating
+                // the triangle again from its vertices
+                for &right_handed in &[false, true] {
+                    let t = RobinsonTriangle::from_base(a, c, triangle_type, right_handed);
+                    assert_eq!(
+                        RobinsonTriangle::new(t.a, t.b, t.c).triangle_type,
+                        triangle_type
+                    );
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_arc_to_cubics() {
+        let mut rng = rand::thread_rng();
+        let point_on_curve = |from: Point, [c1, c2, to]: [Point; 3], t: f64| {
+            let s = 1.0 - t;
+            s * s * s * from + 3.0 * s * s * t * c1 + 3.0 * s * t * t * c2 + t * t * t * to
+        };
+        for _ in 0..1000 {
+            let center = random_point(&mut rng, -1000.0, 1000.0);
+            let radius = rng.gen_range(0.1..100.0);
+            let start = center + radius * Point(1.0, 0.0).rotate(rng.gen_range(0.0..360.0));
+            let end = center + radius * Point(1.0, 0.0).rotate(rng.gen_range(0.0..360.0));
+
+            // The middle points of the short and long arcs must be on opposite sides of the chord
+            let mut sides = Vec::new();
+            for &large_angle_flag in &[false, true] {
+                let curves = arc_to_cubics((start, center, end, large_angle_flag));
+                assert_close!(curves.last().unwrap()[2], end);
+
+                let mut from = start;
+                for &curve in &curves {
+                    for &t in &[0.25, 0.5, 0.75] {
+                        let p = point_on_curve(from, curve, t);
+                        assert!((p.distance_to(center) - radius).abs() < 1e-3 * radius);
+                    }
+                    from = curve[2];
+                }
+                let middle = point_on_curve(start, curves[0], 0.5);
+                sides.push((end - start).cross(middle - start) > 0.0);
             }
+            assert_ne!(sides[0], sides[1]);
         }
     }
 }
@@ -0,0 +1,385 @@
+use crate::geometry::*;
+use std::cmp::Ordering;
+use std::ops;
+
+// The sine of 36 degrees, which is the y coordinate of the unit vector ζ. It's equal to
+// sqrt(10 - 2 * sqrt(5)) / 4
+const SIN_36: f64 = 0.5877852522924731;
+
+/// An element of the ring Z[φ], that is, a number of the form `a + bφ`, where `a` and `b` are
+/// integers. Since φ is irrational, this representation is unique, so two numbers are equal if and
+/// only if their coefficients are equal. This makes equality and hashing exact.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Golden(pub i64, pub i64);
+
+impl Golden {
+    pub const ZERO: Golden = Golden(0, 0);
+    pub const ONE: Golden = Golden(1, 0);
+    pub const PHI: Golden = Golden(0, 1);
+    pub const PHI_INVERSE: Golden = Golden(-1, 1); // == phi - 1
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 + self.1 as f64 * PHI
+    }
+
+    /// Multiplies the number by φ. Since φ^2 == φ + 1, this is the same as
+    /// `(a + bφ)φ == b + (a + b)φ`.
+    pub fn mul_phi(self) -> Self {
+        Golden(self.1, self.0 + self.1)
+    }
+
+    /// Divides the number by φ. Since 1 / φ == φ - 1, this is the same as
+    /// `(a + bφ) / φ == (b - a) + aφ`.
+    pub fn div_phi(self) -> Self {
+        Golden(self.1 - self.0, self.0)
+    }
+
+    /// The conjugate of `a + bφ` is `a + b(1 - φ)`, that is, the result of replacing φ with the
+    /// other root of `x^2 - x - 1`.
+    pub fn conjugate(self) -> Self {
+        Golden(self.0 + self.1, -self.1)
+    }
+
+    /// The norm of a number is the product of the number with its conjugate. It is always an
+    /// integer.
+    pub fn norm(self) -> i64 {
+        self.0 * self.0 + self.0 * self.1 - self.1 * self.1
+    }
+
+    /// Returns the multiplicative inverse of the number, if it is in Z[φ]. That is only the case for
+    /// numbers whose norm is 1 or -1, like the powers of φ.
+    pub fn recip(self) -> Option<Self> {
+        match self.norm() {
+            1 => Some(self.conjugate()),
+            -1 => Some(-self.conjugate()),
+            _ => None,
+        }
+    }
+
+    /// Returns 1 if the number is positive, -1 if it is negative, and 0 if it is zero. This is
+    /// computed exactly, without converting the number to floating point.
+    pub fn signum(self) -> i64 {
+        // We have that a + bφ == (s + t * sqrt(5)) / 2, where s == 2a + b and t == b. If s and t
+        // have the same sign, that is the sign of the number. Otherwise, the sign is given by
+        // whichever term has the largest absolute value, which we find by comparing their squares.
+        // Since sqrt(5) is irrational, the squares can never be equal, unless both are zero
+        let s = 2 * self.0 as i128 + self.1 as i128;
+        let t = self.1 as i128;
+        if s >= 0 && t >= 0 {
+            (s + t).signum() as i64
+        } else if s <= 0 && t <= 0 {
+            -1
+        } else if s * s > 5 * t * t {
+            s.signum() as i64
+        } else {
+            t.signum() as i64
+        }
+    }
+}
+
+impl From<i64> for Golden {
+    fn from(n: i64) -> Self {
+        Golden(n, 0)
+    }
+}
+
+impl Ord for Golden {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (*self - *other).signum().cmp(&0)
+    }
+}
+
+impl PartialOrd for Golden {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ops::Add for Golden {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Golden(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+impl ops::Sub for Golden {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Golden(self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+
+impl ops::Neg for Golden {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Golden(-self.0, -self.1)
+    }
+}
+
+impl ops::Mul for Golden {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        // (a + bφ)(c + dφ) == ac + (ad + bc)φ + bdφ^2 == (ac + bd) + (ad + bc + bd)φ
+        let (Golden(a, b), Golden(c, d)) = (self, rhs);
+        Golden(a * c + b * d, a * d + b * c + b * d)
+    }
+}
+
+impl ops::Mul<Golden> for i64 {
+    type Output = Golden;
+    fn mul(self, rhs: Golden) -> Golden {
+        Golden(self * rhs.0, self * rhs.1)
+    }
+}
+
+/// A point whose coordinates can be represented exactly. Each point is represented as `p + qζ`,
+/// where `p` and `q` are in Z[φ], and ζ is the unit vector that makes a 36 degree angle with the x
+/// axis. This is enough to represent every vertex of a Penrose tiling built from a seed with
+/// integer side lengths, since every edge points in one of the ten directions given by rotating ζ,
+/// and decomposing a tile only divides its edges by φ.
+///
+/// Note that the ordering of points is lexicographic on their coefficients. It is consistent with
+/// equality, but has no geometric meaning.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct GoldenPoint(pub Golden, pub Golden);
+
+impl GoldenPoint {
+    pub const ZERO: GoldenPoint = GoldenPoint(Golden::ZERO, Golden::ZERO);
+    pub const ONE: GoldenPoint = GoldenPoint(Golden::ONE, Golden::ZERO);
+
+    /// Creates a point on the x axis.
+    pub fn real(x: Golden) -> Self {
+        GoldenPoint(x, Golden::ZERO)
+    }
+
+    /// Converts the point to floating point coordinates.
+    pub fn to_point(self) -> Point {
+        let GoldenPoint(p, q) = self;
+        let q = q.to_f64();
+        // The x coordinate of ζ is cos(36) == phi / 2
+        Point(p.to_f64() + q * PHI / 2.0, q * SIN_36)
+    }
+
+    /// Returns the square of the distance from the origin to the point.
+    pub fn norm_squared(self) -> Golden {
+        // The conjugate of ζ is φ - ζ, so (p + qζ)(p + q(φ - ζ)) == p^2 + pqφ + q^2(ζ(φ - ζ)).
+        // And since ζ^2 == φζ - 1, we have that ζ(φ - ζ) == 1
+        let GoldenPoint(p, q) = self;
+        p * p + p * q * Golden::PHI + q * q
+    }
+
+    /// Rotates the point around the origin by `steps` multiples of 36 degrees.
+    pub fn rotate_steps(self, steps: i64) -> Self {
+        // Multiplying p + qζ by ζ gives pζ + q(φζ - 1) == -q + (p + qφ)ζ
+        (0..steps.rem_euclid(10)).fold(self, |GoldenPoint(p, q), _| {
+            GoldenPoint(-q, p + q.mul_phi())
+        })
+    }
+
+    /// Returns the image of the point under the "star map", the field automorphism that sends ζ
+    /// to ζ^7. It maps each of the five unit vectors at multiples of 72 degrees to the one with
+    /// twice its angle, so it takes a vertex of the tiling, seen as the projection of a point in the
+    /// five-dimensional integer lattice, to the projection of the same lattice point onto the
+    /// internal space used by the cut-and-project method.
+    pub fn star(self) -> Self {
+        // The star map takes φ to its conjugate, and ζ^7 == -ζ^2 == 1 - φζ. So p + qζ is mapped to
+        // p' + q'(1 - φζ) == (p' + q') - q'φζ
+        let GoldenPoint(p, q) = self;
+        let (p, q) = (p.conjugate(), q.conjugate());
+        GoldenPoint(p + q, -q.mul_phi())
+    }
+
+    pub fn mul_phi(self) -> Self {
+        GoldenPoint(self.0.mul_phi(), self.1.mul_phi())
+    }
+
+    pub fn div_phi(self) -> Self {
+        GoldenPoint(self.0.div_phi(), self.1.div_phi())
+    }
+}
+
+impl ExactPoint for GoldenPoint {
+    fn to_point(self) -> Point {
+        GoldenPoint::to_point(self)
+    }
+
+    fn coefficients(self) -> [[i64; 2]; 2] {
+        let GoldenPoint(p, q) = self;
+        [[p.0, p.1], [q.0, q.1]]
+    }
+}
+
+impl From<Golden> for GoldenPoint {
+    fn from(x: Golden) -> Self {
+        GoldenPoint::real(x)
+    }
+}
+
+// Exact types are only close if they are exactly equal. This lets them be used with the tests and
+// helpers written for floating point types.
+impl Close for GoldenPoint {
+    fn is_close(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl Transform for GoldenPoint {
+    /// Rotates the point around the origin. The angle must be a multiple of 36 degrees, since
+    /// those are the only rotations that can be represented exactly.
+    fn rotate(&self, angle: f64) -> Self {
+        let steps = angle / 36.0;
+        assert!(
+            close(steps, steps.round()),
+            "Angle must be a multiple of 36 degrees"
+        );
+        self.rotate_steps(steps.round() as i64)
+    }
+
+    fn mirror_x(&self) -> Self {
+        -self.mirror_y()
+    }
+
+    fn mirror_y(&self) -> Self {
+        // Negating the y coordinate is the same as taking the complex conjugate. The conjugate of
+        // p + qζ is p + q(φ - ζ) == (p + qφ) - qζ
+        let GoldenPoint(p, q) = *self;
+        GoldenPoint(p + q.mul_phi(), -q)
+    }
+}
+
+impl ops::Add for GoldenPoint {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        GoldenPoint(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+impl ops::Sub for GoldenPoint {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        GoldenPoint(self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+
+impl ops::Neg for GoldenPoint {
+    type Output = Self;
+    fn neg(self) -> Self {
+        GoldenPoint(-self.0, -self.1)
+    }
+}
+
+impl ops::Mul<GoldenPoint> for Golden {
+    type Output = GoldenPoint;
+    fn mul(self, rhs: GoldenPoint) -> GoldenPoint {
+        GoldenPoint(self * rhs.0, self * rhs.1)
+    }
+}
+
+/// Maps exact tiling coordinates to the floating point coordinates used in the output, by scaling
+/// them and then translating them by `origin`. Since the mapping is a similarity, it preserves the
+/// shape of the tiles.
+#[derive(Debug, Copy, Clone)]
+pub struct Placement {
+    pub origin: Point,
+    pub scale: f64,
+}
+
+impl Placement {
+    pub fn apply<P: ExactPoint>(&self, p: P) -> Point {
+        self.apply_point(p.to_point())
+    }
+
+    pub fn apply_point(&self, p: Point) -> Point {
+        self.scale * p + self.origin
+    }
+
+    /// Maps a point in the output coordinates back to the tiling coordinates.
+    pub fn invert_point(&self, p: Point) -> Point {
+        (p - self.origin) / self.scale
+    }
+
+    /// Maps a rectangle in the output coordinates, like the SVG view box, back to the tiling
+    /// coordinates.
+    pub fn invert_rectangle(&self, r: Rectangle) -> Rectangle {
+        Rectangle::bounding(&[self.invert_point(r.min), self.invert_point(r.max)])
+    }
+}
+
+// Useful for tests
+#[cfg(test)]
+pub fn random_golden<R: rand::Rng>(rng: &mut R, min: i64, max: i64) -> Golden {
+    Golden(rng.gen_range(min..max), rng.gen_range(min..max))
+}
+
+#[cfg(test)]
+pub fn random_golden_point<R: rand::Rng>(rng: &mut R, min: i64, max: i64) -> GoldenPoint {
+    GoldenPoint(random_golden(rng, min, max), random_golden(rng, min, max))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    #[test]
+    fn test_golden_arithmetic() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..10_000 {
+            let a = random_golden(&mut rng, -1000, 1000);
+            let b = random_golden(&mut rng, -1000, 1000);
+            assert_close!((a + b).to_f64(), a.to_f64() + b.to_f64());
+            assert_close!((a - b).to_f64(), a.to_f64() - b.to_f64());
+            assert_close!((a * b).to_f64() / 1e6, a.to_f64() * b.to_f64() / 1e6);
+            assert_close!(a.mul_phi().to_f64(), a.to_f64() * PHI);
+            assert_close!(a.div_phi().to_f64(), a.to_f64() / PHI);
+            assert_eq!(a.mul_phi().div_phi(), a);
+            // Zero is checked on its own, since its floating point sign is positive
+            if a != Golden::ZERO {
+                assert_eq!(a.signum() as f64, a.to_f64().signum());
+            }
+            assert_eq!(a.cmp(&b), a.to_f64().partial_cmp(&b.to_f64()).unwrap());
+        }
+        assert_eq!(Golden::ZERO.signum(), 0);
+        assert_eq!(Golden::PHI * Golden::PHI_INVERSE, Golden::ONE);
+        assert_eq!(Golden::PHI.recip(), Some(Golden::PHI_INVERSE));
+        assert_eq!(Golden::from(2).recip(), None);
+    }
+
+    #[test]
+    fn test_golden_point_conversion() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..10_000 {
+            let a = random_golden_point(&mut rng, -1000, 1000);
+            let b = random_golden_point(&mut rng, -1000, 1000);
+            let (fa, fb) = (a.to_point(), b.to_point());
+            assert_close!((a + b).to_point(), fa + fb);
+            assert_close!((a - b).to_point(), fa - fb);
+            assert_close!(
+                a.norm_squared().to_f64().sqrt(),
+                Point::ZERO.distance_to(fa)
+            );
+            assert_close!(a.mirror_x().to_point(), fa.mirror_x());
+            assert_close!(a.mirror_y().to_point(), fa.mirror_y());
+            let steps = rng.gen_range(-20..20);
+            assert_close!(
+                a.rotate_steps(steps).to_point(),
+                fa.rotate(steps as f64 * 36.0)
+            );
+        }
+    }
+
+    #[test]
+    fn test_star_map() {
+        let mut rng = rand::thread_rng();
+        for i in 0..5 {
+            let e = GoldenPoint::ONE.rotate_steps(2 * i);
+            assert_eq!(e.star(), GoldenPoint::ONE.rotate_steps(4 * i));
+        }
+        for _ in 0..10_000 {
+            let a = random_golden_point(&mut rng, -1000, 1000);
+            let b = random_golden_point(&mut rng, -1000, 1000);
+            assert_eq!((a + b).star(), a.star() + b.star());
+            assert_eq!(a.star().star().star().star(), a);
+        }
+    }
+}
@@ -0,0 +1,204 @@
+use crate::geometry::*;
+use crate::render::Polygon;
+use crate::validate::{boundary_edges, Edge, EdgeType, MatchingTile};
+
+// The dimensions of the knobs, as fractions of the length of their edge. Each knob is a round head
+// on a straight neck, and it's small enough that the notches don't reach the other edges of the
+// tile, even at its sharpest corners, which have an angle of 36 degrees
+const NECK_WIDTH: f64 = 0.05;
+const HEAD_RADIUS: f64 = 0.09;
+const HEAD_HEIGHT: f64 = 0.13;
+
+// The heads are approximated by straight segments that turn by at most this angle, in degrees
+const HEAD_STEP: f64 = 15.0;
+
+/// The position of the knob along an edge of the given type, as a fraction of the length of the
+/// edge, from its start. The edge types that can be on the same tile have their knobs in different
+/// places, so that each of them only fits with another edge of the same type.
+pub fn knob_position(edge_type: EdgeType) -> f64 {
+    match edge_type {
+        EdgeType::RhombusSide1 | EdgeType::KiteDartSide1 => 0.42,
+        EdgeType::RhombusSide2 | EdgeType::KiteDartSide2 => 0.58,
+        EdgeType::SmallRhombusBase
+        | EdgeType::LargeRhombusBase
+        | EdgeType::KiteBase
+        | EdgeType::DartBase => 0.5,
+    }
+}
+
+/// Returns the outline of a jigsaw edge, from the start of the edge to its end. The knob always
+/// sticks out to the left of the edge, so the tile on that side has a notch instead, and a tile
+/// that has the edge in the opposite orientation would need a knob in the same place. Together
+/// with the positions of the knobs, this means that two tiles only fit together if the edge has the
+/// same type and orientation in both, which is exactly the matching rules.
+pub fn edge_outline(edge: &Edge) -> Vec<Point> {
+    let (start, end) = (edge.start.to_point(), edge.end.to_point());
+    let along = end - start;
+    let left = Point(-along.1, along.0);
+
+    // Points are given in a frame where the edge goes from (0, 0) to (1, 0)
+    let point = |x: f64, y: f64| start + x * along + y * left;
+    let position = knob_position(edge.edge_type);
+
+    // Where the sides of the neck meet the head
+    let shoulder = (HEAD_RADIUS * HEAD_RADIUS - NECK_WIDTH * NECK_WIDTH).sqrt();
+    let neck_height = HEAD_HEIGHT - shoulder;
+
+    let mut points = vec![
+        point(0.0, 0.0),
+        point(position - NECK_WIDTH, 0.0),
+        point(position - NECK_WIDTH, neck_height),
+    ];
+
+    // The head goes clockwise, from the left side of the neck, over the top, to its right side
+    let from = (-shoulder).atan2(-NECK_WIDTH) + 2.0 * std::f64::consts::PI;
+    let to = (-shoulder).atan2(NECK_WIDTH);
+    let steps = ((from - to).to_degrees() / HEAD_STEP).ceil() as usize;
+    for i in 1..steps {
+        let angle = from + (to - from) * i as f64 / steps as f64;
+        points.push(point(
+            position + HEAD_RADIUS * angle.cos(),
+            HEAD_HEIGHT + HEAD_RADIUS * angle.sin(),
+        ));
+    }
+    points.extend_from_slice(&[
+        point(position + NECK_WIDTH, neck_height),
+        point(position + NECK_WIDTH, 0.0),
+        point(1.0, 0.0),
+    ]);
+    points
+}
+
+/// A Penrose tile whose edges have been replaced by jigsaw edges, so that it can be cut as a
+/// puzzle piece that only fits with its neighbors in the ways the matching rules allow. The
+/// pieces must be placed face up, since flipping one over mirrors its knobs.
+#[derive(Debug, Clone)]
+pub struct JigsawTile {
+    pub tile_type: TileType,
+
+    /// The vertices of the outline, in the tiling coordinates.
+    pub vertices: Vec<Point>,
+
+    /// The matching arcs of the original tile, which can be engraved on the piece.
+    pub arcs: Option<(Arc, Arc)>,
+}
+
+impl JigsawTile {
+    /// Returns `None` if the tile doesn't have the shape of a Penrose tile.
+    pub fn new<T: MatchingTile + Polygon>(tile: &T) -> Option<Self> {
+        let corners = MatchingTile::vertices(tile);
+        let mut vertices = Vec::new();
+        for (i, edge) in boundary_edges(tile)?.iter().enumerate() {
+            // The orientation of the edge is given by the matching rules, so the tile may go
+            // through it backwards
+            let mut outline = edge_outline(edge);
+            if edge.start != corners[i] {
+                outline.reverse();
+            }
+            outline.pop();
+            vertices.extend(outline);
+        }
+        Some(JigsawTile {
+            tile_type: tile.polygon_type(),
+            vertices,
+            arcs: tile.arcs(),
+        })
+    }
+}
+
+impl Polygon for JigsawTile {
+    type Vertex = Point;
+
+    fn polygon_type(&self) -> TileType {
+        self.tile_type
+    }
+
+    fn vertices(&self) -> Vec<Point> {
+        self.vertices.clone()
+    }
+
+    fn arcs(&self) -> Option<(Arc, Arc)> {
+        self.arcs
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cut::EdgeSet;
+    use crate::golden::GoldenPoint;
+    use crate::render::{IntoTilingVertex, Tile};
+
+    fn segments_cross(a: Point, b: Point, c: Point, d: Point) -> bool {
+        let side = |p: Point, q: Point, r: Point| (q - p).cross(r - p);
+        side(a, b, c) * side(a, b, d) < 0.0 && side(c, d, a) * side(c, d, b) < 0.0
+    }
+
+    // Checks that the outline of every piece is a simple polygon, and that the pieces fit together
+    // without gaps, by counting the edges and vertices of the puzzle with Euler's formula
+    fn check_pieces(pieces: &[JigsawTile]) {
+        let mut edges = EdgeSet::new();
+        for (id, piece) in pieces.iter().enumerate() {
+            let v = &piece.vertices;
+            let n = v.len();
+            for i in 0..n {
+                for j in i + 2..n {
+                    if (j + 1) % n != i {
+                        let (a, b) = (v[i], v[(i + 1) % n]);
+                        assert!(!segments_cross(a, b, v[j], v[(j + 1) % n]));
+                    }
+                }
+            }
+            let tiling_vertices: Vec<_> = v.iter().map(|p| p.into_tiling_vertex()).collect();
+            edges.add_tile(&Tile {
+                id,
+                tile_type: piece.tile_type,
+                vertices: v,
+                tiling_vertices: &tiling_vertices,
+                arcs: None,
+            });
+        }
+        assert_eq!(edges.num_vertices() + pieces.len(), edges.num_edges() + 1);
+    }
+
+    #[test]
+    fn test_jigsaw_pieces() {
+        for seed in &[crate::seeds::rose(), crate::seeds::tile(TileType::Kite)] {
+            let triangles = crate::tiling::generate_tiling(seed.clone().into_triangles(), 4);
+            let quads = crate::tiling::merge_pairs(triangles.clone());
+            let pieces: Vec<_> = quads.iter().map(|q| JigsawTile::new(q).unwrap()).collect();
+            check_pieces(&pieces);
+            let pieces: Vec<_> = triangles
+                .iter()
+                .map(|t| JigsawTile::new(t).unwrap())
+                .collect();
+            check_pieces(&pieces);
+        }
+    }
+
+    #[test]
+    fn test_knobs_match_rules() {
+        // A knob only fits into the notch of an edge with the same type and orientation
+        let edge = |edge_type, start, end| Edge {
+            edge_type,
+            start,
+            end,
+        };
+        let (a, b) = (GoldenPoint::ZERO, GoldenPoint::ONE);
+        let first = edge_outline(&edge(EdgeType::RhombusSide1, a, b));
+        let mut reversed = edge_outline(&edge(EdgeType::RhombusSide1, b, a));
+        reversed.reverse();
+        let second = edge_outline(&edge(EdgeType::RhombusSide2, a, b));
+        let same = |p: &[Point], q: &[Point]| {
+            p.len() == q.len() && p.iter().zip(q).all(|(&p, &q)| close(p, q))
+        };
+        assert!(same(
+            &first,
+            &edge_outline(&edge(EdgeType::RhombusSide1, a, b))
+        ));
+        assert!(!same(&first, &reversed));
+        assert!(!same(&first, &second));
+        assert_close!(first[0], a.to_point());
+        assert_close!(first[first.len() - 1], b.to_point());
+    }
+}
@@ -1,6 +1,27 @@
+pub mod ammann_beenker;
 pub mod config;
+pub mod conversion;
+pub mod cut;
+pub mod data;
+pub mod dxf;
+pub mod gcode;
 #[macro_use]
 pub mod geometry;
+pub mod golden;
+pub mod jigsaw;
+pub mod mesh;
+pub mod monotiles;
+pub mod pentagons;
+pub mod pentagrid;
+pub mod print;
+pub mod projection;
+pub mod raster;
+pub mod render;
+pub mod rules;
 pub mod seeds;
+pub mod silver;
 pub mod svg;
 pub mod tiling;
+pub mod topology;
+pub mod validate;
+pub mod vertices;
@@ -5,22 +5,45 @@ extern crate structopt;
 use rose::*;
 
 use config::*;
+use cut::CutBackend;
+use data::{DataBackend, DataFormat};
+use dxf::DxfBackend;
+use gcode::{GcodeBackend, GcodeConfig};
 use geometry::*;
-use svg::*;
+use golden::Placement;
+use jigsaw::JigsawTile;
+use mesh::{MeshBackend, MeshConfig, MeshFormat};
+use print::{PageConfig, PrintBackend, PrintFormat};
+use raster::RasterBackend;
+use render::{Backend, Polygon, RenderConfig, Scene};
+use seeds::SeedTiles;
+use svg::SvgBackend;
+use topology::Topology;
+use validate::MatchingTile;
 
+use std::collections::{BTreeMap, BTreeSet};
 use std::fs::File;
+use std::io::{self, BufWriter, Write};
 use structopt::StructOpt;
 
 #[derive(StructOpt, Debug)]
 #[structopt(name = "rose", about = "A Penrose tiling generator")]
 struct RoseArguments {
     /// Number of decomposition steps. A larger value results in more, smaller tiles. CAUTION:
-    /// values larger than 10 may take a long time to run, and will result in a very large SVG file
+    /// values larger than 10 may take a long time to run, and will result in a very large SVG file,
+    /// unless a large scale is used so that most of the tiles fall outside of the view box. The
+    /// tiles are written to the file as they are generated, so memory usage stays low either way
     #[structopt(short, long, default_value = "6")]
     num_generations: u64,
 
     /// Seed to use for generating the tiling. Some seeds may result in tilings that look right, but
-    /// aren't actual Penrose tilings
+    /// aren't actual Penrose tilings. The "pentagon" and "star" seeds generate Penrose's original
+    /// tiling of pentagons, stars, boats and diamonds, whose tiles shrink by phi^2 in each
+    /// decomposition step, so they need fewer generations. The "octagram", "square" and "rhomb"
+    /// seeds generate the octagonal Ammann-Beenker tiling instead, whose tiles shrink by 1 +
+    /// sqrt(2). The "hat" and "spectre" seeds generate the tilings of those aperiodic monotiles,
+    /// whose tiles are grouped into metatiles about 2.6 times wider in each generation, so they
+    /// need fewer generations too
     #[structopt(
         long,
         possible_values = SeedArgument::variants(),
@@ -28,6 +51,51 @@ struct RoseArguments {
     )]
     seed: SeedArgument,
 
+    /// Generate the tiling using de Bruijn's pentagrid method instead of decomposing a seed. Expects
+    /// the five grid offsets, which must add up to an integer for the result to be an actual Penrose
+    /// tiling. The rhombuses have the same size they would have after the given number of
+    /// generations
+    #[structopt(
+        long,
+        number_of_values = 5,
+        allow_hyphen_values = true,
+        conflicts_with_all = &["seed", "draw-triangles"],
+        value_name = "offset",
+    )]
+    pentagrid: Vec<f64>,
+
+    /// Generate the tiling from a set of substitution rules instead of a seed. Expects the name of a
+    /// built-in ruleset ("penrose-rhombus" or "penrose-kite-dart"), or a TOML or JSON file that
+    /// describes the prototiles and how they decompose. The tiles are drawn as they are, so the
+    /// options that need the Robinson triangles can't be used with it
+    #[structopt(
+        long,
+        conflicts_with_all = &[
+            "seed",
+            "pentagrid",
+            "convert",
+            "draw-triangles",
+            "mark-vertices",
+            "supertiles",
+            "jigsaw",
+        ],
+        value_name = "ruleset",
+    )]
+    rules: Option<String>,
+
+    /// Replace the edges of the tiles with jigsaw knobs and notches, one shape for each type of
+    /// edge, so that the tiles can be cut as puzzle pieces that only fit together in the ways the
+    /// matching rules allow. Works best with the "cut" and "dxf" formats. Every piece is kept in
+    /// memory until the whole tiling is drawn, so very deep tilings need a lot of memory
+    #[structopt(long)]
+    jigsaw: bool,
+
+    /// Convert the tiling to the other family of Penrose tiles, from rhombuses to kites and darts or
+    /// the other way around. Both tilings are made of the same Robinson triangles, so they show the
+    /// same underlying pattern. This needs the whole tiling to be kept in memory
+    #[structopt(long)]
+    convert: bool,
+
     /// Set a custom scale for the tiling. This number represents the side length of a rhombus
     /// before any decomposition, in SVG units. By default, the scale is half of the view box width
     #[structopt(long)]
@@ -49,6 +117,11 @@ struct RoseArguments {
     #[structopt(short = "a", long)]
     draw_arcs: bool,
 
+    /// Keep the tiles that fall outside of the view box. By default, they are discarded during
+    /// generation, which is much faster when using a large scale
+    #[structopt(long)]
+    no_clip: bool,
+
     /// Set the stroke width for the SVG, in SVG units
     #[structopt(long, default_value = "1")]
     stroke_width: f64,
@@ -75,16 +148,193 @@ struct RoseArguments {
     #[structopt(long, requires = "draw-arcs", value_names = &["first-color", "second-color"])]
     arc_colors: Vec<String>,
 
+    /// Mark every interior vertex with a circle, colored according to its configuration, that is,
+    /// the arrangement of the tiles around it, such as the "sun" or the "star". This needs the whole
+    /// tiling to be kept in memory
+    #[structopt(long)]
+    mark_vertices: bool,
+
+    /// Draw the outlines of the supertiles of the given generation over the tiles. The supertiles
+    /// of generation k are the tiles that the tiling had after k decomposition steps, so k must be
+    /// smaller than the number of generations. Can be passed multiple times to draw more than one
+    /// generation. Can't be used with "--pentagrid"
+    #[structopt(
+        long,
+        number_of_values = 1,
+        conflicts_with = "pentagrid",
+        value_name = "generation"
+    )]
+    supertiles: Vec<u64>,
+
+    /// Set the color of the supertile outlines. Can be passed multiple times, to set the color of
+    /// each generation passed to "--supertiles", in the same order. If fewer colors are given, they
+    /// are repeated
+    #[structopt(
+        long = "supertile-color",
+        number_of_values = 1,
+        requires = "supertiles",
+        value_name = "color"
+    )]
+    supertile_colors: Vec<String>,
+
+    /// Set the width of the supertile outlines, in SVG units. Can be passed multiple times, in the
+    /// same way as "--supertile-color". By default, the outlines are wider for larger supertiles
+    #[structopt(
+        long = "supertile-width",
+        number_of_values = 1,
+        requires = "supertiles",
+        value_name = "width"
+    )]
+    supertile_widths: Vec<f64>,
+
+    /// Set the output format. By default, it is guessed from the output file extension. The "cut"
+    /// format is an SVG file for laser cutters and plotters, with every tile edge drawn only once,
+    /// in millimeters, and the arcs on a separate layer to be engraved. The "dxf" format writes each
+    /// tile as a closed polyline on a layer for its type, and the "stl" and "obj" formats extrude
+    /// each tile into a 3D printable piece
+    #[structopt(short = "f", long, possible_values = FormatArgument::variants())]
+    format: Option<FormatArgument>,
+
+    /// For cut output, join the tile edges into long continuous paths, so that the cutter or
+    /// plotter has to stop and move fewer times
+    #[structopt(long)]
+    join_paths: bool,
+
+    /// For G-code output, set the speed of the drawing moves, in millimeters per minute
+    #[structopt(long, default_value = "1000")]
+    feed_rate: f64,
+
+    /// For G-code output, set the command that lifts the pen or the tool
+    #[structopt(long, default_value = "G0 Z5", allow_hyphen_values = true)]
+    pen_up: String,
+
+    /// For G-code output, set the command that lowers the pen or the tool
+    #[structopt(long, default_value = "G1 Z0", allow_hyphen_values = true)]
+    pen_down: String,
+
+    /// For DXF output, scale the drawing so that the longest edge of the tiles has the given length,
+    /// in millimeters. By default, one view box unit is a millimeter. Can't be used with "--jigsaw",
+    /// since the jigsaw edges aren't straight
+    #[structopt(long, conflicts_with = "jigsaw", value_name = "millimeters")]
+    edge_length: Option<f64>,
+
+    /// For STL and OBJ output, set the thickness of the pieces, in millimeters
+    #[structopt(long, default_value = "3")]
+    thickness: f64,
+
+    /// For STL and OBJ output, set the gap between neighboring pieces, in millimeters, so that
+    /// they fit together after printing
+    #[structopt(long, default_value = "0.2")]
+    gap: f64,
+
+    /// For STL and OBJ output, set the height of the ridges that emboss the matching arcs on the
+    /// pieces, in millimeters. The ridges are as wide as the stroke width, and are only added if
+    /// the "--draw-arcs" flag was set
+    #[structopt(long, default_value = "1")]
+    ridge_height: f64,
+
+    /// Set the PNG image width, in pixels. By default, it is the same as the view box width
+    #[structopt(long)]
+    pixel_width: Option<u32>,
+
+    /// Set the PNG image height, in pixels. By default, it is the same as the view box height
+    #[structopt(long)]
+    pixel_height: Option<u32>,
+
+    /// Set the page size for PDF and EPS output. Expects either a paper size (a3, a4, a5, letter,
+    /// legal or tabloid), or the width and height with units, like "210x297mm" or "8.5inx11in"
+    #[structopt(long, default_value = "a4", parse(try_from_str = print::parse_page_size))]
+    page_size: (f64, f64),
+
+    /// Set the page margin for PDF and EPS output, with units, like "10mm" or "0.5in"
+    #[structopt(long, default_value = "10mm", parse(try_from_str = print::parse_length))]
+    margin: f64,
+
+    /// For JSON and GeoJSON output, write each vertex only once, in a shared list, and refer to the
+    /// vertices of each tile by their indices in that list
+    #[structopt(long)]
+    indexed_vertices: bool,
+
     /// Output file
     #[structopt(required = true)]
     output_file: String,
 }
 
+fn jigsaw_piece<T: MatchingTile + Polygon>(tile: &T) -> std::io::Result<JigsawTile> {
+    JigsawTile::new(tile).ok_or_else(|| {
+        std::io::Error::new(
+            std::io::ErrorKind::InvalidData,
+            format!(
+                "a {} doesn't have the shape of a Penrose tile, so it has no jigsaw edges",
+                tile.polygon_type().name()
+            ),
+        )
+    })
+}
+
+// An output file that is only created when something is first written to it. The backends are
+// created before anything is written, so if one of them can't be created, no file is left behind
+struct LazyFile {
+    path: String,
+    file: Option<File>,
+}
+
+impl Write for LazyFile {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        match &mut self.file {
+            Some(file) => file.write(buf),
+            None => self.file.insert(File::create(&self.path)?).write(buf),
+        }
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        match &mut self.file {
+            Some(file) => file.flush(),
+            None => Ok(()),
+        }
+    }
+}
+
 fn main() -> std::io::Result<()> {
     let args: RoseArguments = RoseArguments::from_args();
 
+    let center = Point(
+        args.view_box_width as f64 / 2.0,
+        args.view_box_height as f64 / 2.0,
+    );
+    let scale = args.scale.unwrap_or(args.view_box_width as f64 / 2.0);
+    let seed = get_seed_from_arg(args.seed);
+    let ruleset = match &args.rules {
+        Some(name) => Some(rules::Ruleset::load(name)?),
+        None => None,
+    };
+    let placement = if let SeedTiles::Monotile(monotile) = seed.tiles() {
+        // The monotiles keep their size, and the largest metatile is scaled to the size of a seed
+        Placement {
+            origin: center,
+            scale: scale / monotiles::radius(*monotile, args.num_generations),
+        }
+    } else if let Some(ruleset) = &ruleset {
+        // The tiles generated from a ruleset keep the size of the prototiles, so the tiling grows
+        // in each generation, and we scale it down to the size of the seed
+        Placement {
+            origin: center,
+            scale: scale / ruleset.inflation.powi(args.num_generations as i32),
+        }
+    } else if args.pentagrid.is_empty() {
+        seed.placement(center, scale)
+    } else {
+        // The pentagrid rhombuses have unit side length, so we scale them down to the size they
+        // would have after decomposing a seed
+        Placement {
+            origin: center,
+            scale: scale / PHI.powi(args.num_generations as i32),
+        }
+    };
+
     let scheme = ColorScheme::from_arg(args.color_scheme);
-    let config = SvgConfig {
+    let config = RenderConfig {
+        placement,
         view_box_width: args.view_box_width,
         view_box_height: args.view_box_height,
         stroke_width: args.stroke_width,
@@ -107,28 +357,294 @@ fn main() -> std::io::Result<()> {
             None
         },
     };
-    let mut builder = SvgBuilder::new(config);
+    // The region of the tiling that is visible in the view box
+    let view_box = placement.invert_rectangle(Rectangle {
+        min: Point::ZERO,
+        max: Point(args.view_box_width as f64, args.view_box_height as f64),
+    });
+    let (no_clip, num_generations) = (args.no_clip, args.num_generations);
 
-    let center = Point(
-        args.view_box_width as f64 / 2.0,
-        args.view_box_height as f64 / 2.0,
-    );
-    let scale = args.scale.unwrap_or(args.view_box_width as f64 / 2.0);
-    let seed = get_seed_from_arg(args.seed).transform(center, scale);
-    let triangles = tiling::generate_tiling(seed, args.num_generations);
-    if args.draw_triangles {
-        builder
-            .add_all_polygons(triangles)
-            .expect("Error writing to string");
+    // Only the Robinson triangles are Penrose tiles that can be converted, drawn as triangles, and
+    // grouped into supertiles, and only their matching rules give the knobs of the jigsaw pieces.
+    // The other seeds are generated with their own functions, and can't be used with the options
+    // that need those
+    let (seed_names, can_draw_triangles) = match seed.tiles() {
+        SeedTiles::Robinson(_) => (None, true),
+        SeedTiles::Pentagons(_) => (Some("the pentagon and star seeds"), false),
+
+        // The Ammann-Beenker triangles are decomposed and merged in the same way as the Robinson
+        // triangles, so they can be drawn as triangles
+        SeedTiles::AmmannBeenker(_) => (Some("the octagram, square and rhomb seeds"), true),
+        SeedTiles::Monotile(_) => (Some("the hat and spectre seeds"), false),
+    };
+    let options = [
+        ("--convert", args.convert),
+        (
+            "--draw-triangles",
+            args.draw_triangles && !can_draw_triangles,
+        ),
+        ("--mark-vertices", args.mark_vertices),
+        ("--supertiles", !args.supertiles.is_empty()),
+        ("--jigsaw", args.jigsaw),
+    ];
+    if let (Some(seed_names), Some((option, _))) =
+        (seed_names, options.iter().find(|&&(_, used)| used))
+    {
+        return Err(std::io::Error::new(
+            std::io::ErrorKind::InvalidInput,
+            format!("\"{}\" can't be used with {}", option, seed_names),
+        ));
+    }
+
+    let mut robinson_seed = Vec::new();
+    let mut ammann_beenker_seed = None;
+    let mut pentagon_tiles = None;
+    let mut monotile_tiles = None;
+    match seed.into_tiles() {
+        SeedTiles::Robinson(triangles) => robinson_seed = triangles,
+        SeedTiles::Pentagons(tiles) => {
+            pentagon_tiles = Some(if no_clip {
+                pentagons::generate_tiling(tiles, num_generations)
+            } else {
+                pentagons::generate_tiling_clipped(tiles, num_generations, view_box)
+            })
+        }
+        SeedTiles::AmmannBeenker(triangles) => ammann_beenker_seed = Some(triangles),
+        SeedTiles::Monotile(monotile) => {
+            monotile_tiles = Some(if no_clip {
+                monotiles::generate_tiling(monotile, num_generations)
+            } else {
+                monotiles::generate_tiling_clipped(monotile, num_generations, view_box)
+            })
+        }
+    }
+    let rule_tiles = ruleset.map(|ruleset| {
+        if no_clip {
+            ruleset.generate_tiling(num_generations)
+        } else {
+            ruleset.generate_tiling_clipped(num_generations, view_box)
+        }
+    });
+    let generate = || {
+        if no_clip {
+            tiling::iter_tiling(robinson_seed.clone(), num_generations)
+        } else {
+            tiling::iter_tiling_clipped(robinson_seed.clone(), num_generations, view_box)
+        }
+    };
+    let pentagrid_quads = if args.pentagrid.is_empty() {
+        None
+    } else {
+        let mut offsets = [0.0; 5];
+        offsets.copy_from_slice(&args.pentagrid);
+        let quads = pentagrid::generate_tiling(offsets, view_box);
+        if args.convert {
+            let triangles = quads.iter().flat_map(|q| q.split().to_vec()).collect();
+            Some(tiling::merge_pairs(conversion::convert(triangles)))
+        } else {
+            Some(quads)
+        }
+    };
+
+    // Converting the tiling needs all of the triangles at once, so they can't be generated as they
+    // are drawn
+    let converted = if args.convert && pentagrid_quads.is_none() {
+        Some(conversion::convert(generate().collect()))
+    } else {
+        None
+    };
+    let triangles = || -> Box<dyn Iterator<Item = RobinsonTriangle> + '_> {
+        match &converted {
+            Some(triangles) => Box::new(triangles.iter().cloned()),
+            None => Box::new(generate()),
+        }
+    };
+
+    // The colors are validated here, so that the output file isn't created if any of them is
+    // invalid
+    let mut scene = Scene::new(&config)?;
+
+    // Unless the user passed their own colors, the monotiles are colored by their orientation
+    if let (Some(tiles), true) = (&monotile_tiles, args.colors.is_empty()) {
+        let orientations: BTreeSet<_> = tiles.iter().map(|t| t.orientation).collect();
+        let colors: Vec<_> = orientations
+            .into_iter()
+            .map(|o| (o, monotile_color(o)))
+            .collect();
+        scene.color_by_orientation(&colors)?;
+    }
+    if args.mark_vertices {
+        let quads: Vec<Quadrilateral> = match &pentagrid_quads {
+            Some(quads) => quads.clone(),
+            None => tiling::merge_pairs_streaming(triangles()).collect(),
+        };
+        let topology = Topology::new(&quads);
+        let mut positions = BTreeMap::new();
+        for vertex in 0..topology.num_vertices() {
+            if let Some(configuration) = vertices::classify_vertex(&topology, vertex) {
+                positions
+                    .entry(configuration)
+                    .or_insert_with(Vec::new)
+                    .push(topology.vertex(vertex));
+            }
+        }
+
+        // The markers are a fraction of the length of the tile sides
+        let radius = scale / PHI.powi(args.num_generations as i32) / 8.0;
+        for (configuration, positions) in positions {
+            let color = vertex_configuration_color(configuration);
+            scene.add_markers(configuration.name(), color, radius, positions)?;
+        }
+    }
+    if !args.supertiles.is_empty() {
+        let last_level = *args.supertiles.iter().max().unwrap();
+        if last_level >= num_generations {
+            return Err(std::io::Error::new(
+                std::io::ErrorKind::InvalidInput,
+                format!(
+                    "supertiles of generation {} don't exist in a tiling of {} generations",
+                    last_level, num_generations
+                ),
+            ));
+        }
+        let bounds = if no_clip { None } else { Some(view_box) };
+        let generations =
+            tiling::generate_tiling_with_ancestors(robinson_seed.clone(), last_level, bounds);
+
+        // The outlines of the larger supertiles are drawn over the ones of the smaller supertiles
+        let mut levels: Vec<_> = args.supertiles.iter().copied().enumerate().collect();
+        levels.sort_by_key(|&(_, level)| std::cmp::Reverse(level));
+        for (i, level) in levels {
+            let color = match args.supertile_colors.len() {
+                0 => supertile_color(i),
+                n => &args.supertile_colors[i % n],
+            };
+            let width = match args.supertile_widths.len() {
+                0 => args.stroke_width * (1 + num_generations - level) as f64,
+                n => args.supertile_widths[i % n],
+            };
+            let supertiles = &generations[level as usize];
+            let outlines = if args.convert {
+                tiling::tile_outlines(&conversion::convert(supertiles.clone()))
+            } else {
+                tiling::tile_outlines(supertiles)
+            };
+            scene.add_outlines(&format!("supertiles-{}", level), color, width, outlines)?;
+        }
+    }
+    // The jigsaw pieces are all made before the output file is created, so that a tile without
+    // jigsaw edges is an error instead of a partial output
+    let jigsaw_pieces = if !args.jigsaw {
+        None
+    } else {
+        let pieces: std::io::Result<Vec<_>> = match &pentagrid_quads {
+            Some(quads) => quads.iter().map(jigsaw_piece).collect(),
+            None if args.draw_triangles => triangles().map(|t| jigsaw_piece(&t)).collect(),
+            None => tiling::merge_pairs_streaming(triangles())
+                .map(|q| jigsaw_piece(&q))
+                .collect(),
+        };
+        Some(pieces?)
+    };
+    let format = match args.format {
+        Some(format) => format,
+        None => FormatArgument::from_file_name(&args.output_file),
+    };
+    let out_file = BufWriter::new(LazyFile {
+        path: args.output_file.clone(),
+        file: None,
+    });
+    let mut backend: Box<dyn Backend> = match format {
+        FormatArgument::Svg => Box::new(SvgBackend::new(out_file)),
+        FormatArgument::Png => Box::new(RasterBackend::new(
+            args.pixel_width.unwrap_or(args.view_box_width as u32),
+            args.pixel_height.unwrap_or(args.view_box_height as u32),
+            out_file,
+        )?),
+        FormatArgument::Pdf | FormatArgument::Eps => {
+            let page = PageConfig {
+                width: args.page_size.0,
+                height: args.page_size.1,
+                margin: args.margin,
+            };
+            let print_format = match format {
+                FormatArgument::Pdf => PrintFormat::Pdf,
+                _ => PrintFormat::Eps,
+            };
+            Box::new(PrintBackend::new(page, print_format, out_file)?)
+        }
+        FormatArgument::Json | FormatArgument::GeoJson => {
+            let data_format = match format {
+                FormatArgument::Json => DataFormat::Json,
+                _ => DataFormat::GeoJson,
+            };
+            Box::new(DataBackend::new(
+                data_format,
+                num_generations,
+                args.indexed_vertices,
+                out_file,
+            ))
+        }
+        FormatArgument::Cut => Box::new(CutBackend::new(args.join_paths, out_file)),
+        FormatArgument::Gcode => {
+            let config = GcodeConfig {
+                feed_rate: args.feed_rate,
+                pen_up: args.pen_up.clone(),
+                pen_down: args.pen_down.clone(),
+            };
+            Box::new(GcodeBackend::new(config, out_file))
+        }
+        FormatArgument::Dxf => Box::new(DxfBackend::new(args.edge_length, out_file)),
+        FormatArgument::Stl | FormatArgument::Obj => {
+            let config = MeshConfig {
+                thickness: args.thickness,
+                gap: args.gap,
+                ridge_height: args.ridge_height,
+            };
+            let mesh_format = match format {
+                FormatArgument::Stl => MeshFormat::Stl,
+                _ => MeshFormat::Obj,
+            };
+            Box::new(MeshBackend::new(mesh_format, config, out_file))
+        }
+    };
+
+    // When decomposing a seed, the tiles are generated and drawn as they are needed, instead of
+    // all at once, so that large tilings don't need to be kept in memory
+    if let Some(tiles) = &pentagon_tiles {
+        scene.render(|| tiles.iter(), backend.as_mut())
+    } else if let Some(tiles) = &monotile_tiles {
+        scene.render(|| tiles.iter(), backend.as_mut())
+    } else if let Some(tiles) = &rule_tiles {
+        scene.render(|| tiles.iter(), backend.as_mut())
+    } else if let Some(seed) = ammann_beenker_seed {
+        let triangles = || {
+            if no_clip {
+                tiling::iter_tiling(seed.clone(), num_generations)
+            } else {
+                tiling::iter_tiling_clipped(seed.clone(), num_generations, view_box)
+            }
+        };
+        if args.draw_triangles {
+            scene.render(triangles, backend.as_mut())
+        } else {
+            scene.render(
+                || tiling::merge_pairs_streaming(triangles()),
+                backend.as_mut(),
+            )
+        }
+    } else if let Some(pieces) = &jigsaw_pieces {
+        scene.render(|| pieces.iter(), backend.as_mut())
+    } else if let Some(quads) = &pentagrid_quads {
+        scene.render(|| quads.iter(), backend.as_mut())
+    } else if args.draw_triangles {
+        scene.render(triangles, backend.as_mut())
     } else {
-        // If the user didn't pass the "--draw-triangles" flag, we must merge the triangles and add
-        // the resulting rhombuses to the SVG
-        let quads = tiling::merge_pairs_hashing(triangles);
-        builder
-            .add_all_polygons(quads)
-            .expect("Error writing to string");
+        // If the user didn't pass the "--draw-triangles" flag, we must merge the triangles and
+        // draw the resulting rhombuses
+        scene.render(
+            || tiling::merge_pairs_streaming(triangles()),
+            backend.as_mut(),
+        )
     }
-    let mut out_file = File::create(args.output_file)?;
-    builder.build(&mut out_file)?;
-    Ok(())
 }
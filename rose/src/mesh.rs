@@ -0,0 +1,543 @@
+use crate::geometry::*;
+use crate::render::{Backend, GroupKind, Scene, Tile};
+
+use std::io::{self, Write};
+
+// The arcs are approximated by straight segments that turn by at most this angle, in degrees
+const ARC_STEP: f64 = 6.0;
+
+// The corners of the inset tiles move by at most this many times the inset distance, so that very
+// sharp corners don't end up far away from the tile
+const MITER_LIMIT: f64 = 4.0;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum MeshFormat {
+    Stl,
+    Obj,
+}
+
+/// The dimensions of the printed pieces, in millimeters.
+#[derive(Debug, Copy, Clone)]
+pub struct MeshConfig {
+    pub thickness: f64,
+
+    /// The distance between neighboring pieces, so that they fit together after printing. Each
+    /// tile is shrunk by half of it on every side.
+    pub gap: f64,
+
+    /// The height of the ridges over the arcs, above the top of the pieces. The ridges are as wide
+    /// as the stroke width of the scene.
+    pub ridge_height: f64,
+}
+
+/// A triangle mesh. The triangles are given by the indices of their vertices, counterclockwise
+/// when seen from the outside.
+#[derive(Debug, Default, Clone)]
+pub struct Mesh {
+    pub vertices: Vec<[f64; 3]>,
+    pub triangles: Vec<[usize; 3]>,
+}
+
+impl Mesh {
+    /// Adds the solid between the heights `bottom` and `top` whose horizontal section is the given
+    /// polygon, which may be in either orientation. Returns an error if the polygon isn't simple.
+    pub fn add_prism(&mut self, polygon: &[Point], bottom: f64, top: f64) -> io::Result<()> {
+        let mut polygon = polygon.to_vec();
+        if signed_area(&polygon) < 0.0 {
+            polygon.reverse();
+        }
+        let triangles = triangulate(&polygon).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "the outline of a piece isn't a simple polygon",
+            )
+        })?;
+        let n = polygon.len();
+        let first = self.vertices.len();
+        for &z in &[bottom, top] {
+            self.vertices
+                .extend(polygon.iter().map(|&Point(x, y)| [x, y, z]));
+        }
+        let (bottom, top) = (first, first + n);
+        for [a, b, c] in triangles {
+            self.triangles.push([bottom + a, bottom + c, bottom + b]);
+            self.triangles.push([top + a, top + b, top + c]);
+        }
+        for i in 0..n {
+            let j = (i + 1) % n;
+            self.triangles.push([bottom + i, bottom + j, top + j]);
+            self.triangles.push([bottom + i, top + j, top + i]);
+        }
+        Ok(())
+    }
+}
+
+// Twice the signed area of a polygon, which is positive if it's counterclockwise
+fn signed_area(polygon: &[Point]) -> f64 {
+    let n = polygon.len();
+    (0..n).map(|i| polygon[i].cross(polygon[(i + 1) % n])).sum()
+}
+
+/// Splits a simple counterclockwise polygon into triangles, given by the indices of their vertices,
+/// by cutting off one ear at a time. This takes quadratic time, which is fine for the polygons of
+/// a tiling, that have few vertices. Returns `None` if the polygon isn't simple, or isn't
+/// counterclockwise, since then it may have no ears left to cut off.
+pub fn triangulate(polygon: &[Point]) -> Option<Vec<[usize; 3]>> {
+    let mut remaining: Vec<_> = (0..polygon.len()).collect();
+    let mut triangles = Vec::with_capacity(polygon.len().saturating_sub(2));
+    while remaining.len() > 3 {
+        let n = remaining.len();
+        let corner = |i: usize| {
+            let [a, b, c] = [(i + n - 1) % n, i, (i + 1) % n].map(|j| remaining[j]);
+            [a, b, c]
+        };
+        let is_ear = |i: usize| {
+            let [a, b, c] = corner(i).map(|j| polygon[j]);
+            (b - a).cross(c - b) > 1e-9
+                && remaining.iter().all(|&j| {
+                    let p = polygon[j];
+                    [a, b, c].iter().any(|&q| close(p, q))
+                        || (b - a).cross(p - a) < 0.0
+                        || (c - b).cross(p - b) < 0.0
+                        || (a - c).cross(p - c) < 0.0
+                })
+        };
+        // Collinear vertices, like the ones between two edges of the same direction, can be cut
+        // off as empty triangles once there are no ears left
+        let i = (0..n).find(|&i| is_ear(i)).or_else(|| {
+            (0..n).find(|&i| {
+                let [a, b, c] = corner(i).map(|j| polygon[j]);
+                (b - a).cross(c - b).abs() <= 1e-9
+            })
+        })?;
+        triangles.push(corner(i));
+        remaining.remove(i);
+    }
+    if remaining.len() == 3 {
+        triangles.push([remaining[0], remaining[1], remaining[2]]);
+    }
+    Some(triangles)
+}
+
+/// Moves every edge of a simple polygon inwards by `distance`, keeping the directions of the edges.
+/// The corners sharper than about 29 degrees can't move that far, so their edges move less.
+pub fn inset(polygon: &[Point], distance: f64) -> Vec<Point> {
+    let n = polygon.len();
+    let sign = signed_area(polygon).signum();
+
+    // The normal of each edge that points inside the polygon
+    let normal = |i: usize| {
+        let Point(x, y) = (polygon[(i + 1) % n] - polygon[i]).normalized();
+        sign * Point(-y, x)
+    };
+    (0..n)
+        .map(|i| {
+            let (a, b) = (normal((i + n - 1) % n), normal(i));
+
+            // The corner moves along the bisector of the normals, far enough that both edges move
+            // by the same distance. That's sqrt(2 / (1 + cos)) times the distance, where cos is the
+            // cosine of the angle between the normals, which is limited to the miter limit
+            let cos = a.dot(b).max(2.0 / (MITER_LIMIT * MITER_LIMIT) - 1.0);
+            polygon[i] + (distance / (1.0 + cos)) * (a + b)
+        })
+        .collect()
+}
+
+// Keeps the part of a polygon on the inner side of the line through `a` and `b`, where `sign` is
+// the sign of the area of the polygons whose inner side it is
+fn clip(polygon: &[Point], a: Point, b: Point, sign: f64) -> Vec<Point> {
+    let side = |p: Point| sign * (b - a).cross(p - a);
+    let n = polygon.len();
+    let mut clipped = Vec::with_capacity(n + 1);
+    for i in 0..n {
+        let (p, q) = (polygon[i], polygon[(i + 1) % n]);
+        let (s, t) = (side(p), side(q));
+        if s >= 0.0 {
+            clipped.push(p);
+        }
+        if (s < 0.0) != (t < 0.0) {
+            clipped.push(p + (s / (s - t)) * (q - p));
+        }
+    }
+    clipped
+}
+
+/// Writes the scene as a 3D model, for printing the tiles as the pieces of a puzzle. Every tile is
+/// extruded into a prism, shrunk so that there is a gap between the pieces, and every matching arc
+/// that the scene draws into a ridge on top of its tile, cut where the tile is shrunk. The colors,
+/// outlines and markers are ignored. The model is flipped with `flip_y`, so that the pieces look the
+/// same from above as the tiles in the SVG, and each view box unit is a millimeter.
+///
+/// The triangles are kept in memory until the whole scene is drawn, since STL files start with
+/// their number, and OBJ files group them by object. In OBJ files, there is one object for each
+/// type of tile, with the ridges of its pieces.
+pub struct MeshBackend<W> {
+    format: MeshFormat,
+    config: MeshConfig,
+    out: W,
+    view_box_height: f64,
+    ridge_width: f64,
+
+    // Whether the first and the second arcs of the tiles are drawn
+    ridges: [bool; 2],
+    objects: Vec<(String, Mesh)>,
+}
+
+impl<W: Write> MeshBackend<W> {
+    pub fn new(format: MeshFormat, config: MeshConfig, out: W) -> Self {
+        MeshBackend {
+            format,
+            config,
+            out,
+            view_box_height: 0.0,
+            ridge_width: 0.0,
+            ridges: [false; 2],
+            objects: Vec::new(),
+        }
+    }
+
+    pub fn into_inner(self) -> W {
+        self.out
+    }
+
+    fn object(&mut self, name: &str) -> usize {
+        match self.objects.iter().position(|(n, _)| n == name) {
+            Some(index) => index,
+            None => {
+                self.objects.push((name.to_string(), Mesh::default()));
+                self.objects.len() - 1
+            }
+        }
+    }
+
+    // The outline of the ridge over an arc of a tile, which is the band between two arcs around it,
+    // one on each side, cut by the sides of the shrunk tile that the arc ends on
+    fn ridge(&self, arc: Arc, polygon: &[Point], piece: &[Point]) -> Option<Vec<Point>> {
+        let (start, center, end, _) = arc;
+        let [start, center, end] = [start, center, end].map(|p| flip_y(p, self.view_box_height));
+        let radius = center.distance_to(start);
+        let half_width = self.ridge_width / 2.0;
+        if radius <= half_width {
+            return None;
+        }
+
+        // The band is built counterclockwise, so the ends are swapped if the arc goes the other way
+        let (start, end) = if arc_is_counterclockwise(arc, false) {
+            (start, end)
+        } else {
+            (end, start)
+        };
+
+        // The sides of the tile that the ends of the arc are on, if they are on one
+        let n = polygon.len();
+        let side = |p: Point| {
+            (0..n).find(|&i| {
+                let (a, b) = (polygon[i], polygon[(i + 1) % n]);
+                let t = (p - a).dot(b - a) / (b - a).dot(b - a);
+                (0.0..=1.0).contains(&t) && close(p, a + t * (b - a))
+            })
+        };
+        let (start_side, end_side) = (side(start), side(end));
+
+        // The band goes a little past the sides, so that it is cut along them at every width
+        let angle = |p: Point| (p.1 - center.1).atan2(p.0 - center.0);
+        let overlap = half_width / radius;
+        let mut start_angle = angle(start);
+        let mut sweep = angle(end) - start_angle;
+        if sweep <= 0.0 {
+            sweep += 2.0 * std::f64::consts::PI;
+        }
+        if start_side.is_some() {
+            start_angle -= overlap;
+            sweep += overlap;
+        }
+        if end_side.is_some() {
+            sweep += overlap;
+        }
+        let steps = (sweep.to_degrees() / ARC_STEP).ceil().max(1.0) as usize;
+        let point = |r: f64, i: usize| {
+            let t = start_angle + sweep * i as f64 / steps as f64;
+            center + r * Point(t.cos(), t.sin())
+        };
+        let mut band: Vec<_> = (0..=steps).map(|i| point(radius + half_width, i)).collect();
+        band.extend((0..=steps).rev().map(|i| point(radius - half_width, i)));
+
+        let sign = signed_area(piece).signum();
+        for i in [start_side, end_side].iter().flatten() {
+            band = clip(&band, piece[*i], piece[(*i + 1) % n], sign);
+        }
+        if band.len() < 3 {
+            None
+        } else {
+            Some(band)
+        }
+    }
+
+    fn write_stl(&mut self) -> io::Result<()> {
+        let mut header = [b' '; 80];
+        let title = b"Penrose tiling generated by rose";
+        header[..title.len()].copy_from_slice(title);
+        self.out.write_all(&header)?;
+        let num_triangles: usize = self.objects.iter().map(|(_, m)| m.triangles.len()).sum();
+        self.out.write_all(&(num_triangles as u32).to_le_bytes())?;
+        for (_, mesh) in &self.objects {
+            for triangle in &mesh.triangles {
+                let [a, b, c] = triangle.map(|i| mesh.vertices[i]);
+                let (u, v) = (
+                    [0, 1, 2].map(|k| b[k] - a[k]),
+                    [0, 1, 2].map(|k| c[k] - a[k]),
+                );
+                let normal = [
+                    u[1] * v[2] - u[2] * v[1],
+                    u[2] * v[0] - u[0] * v[2],
+                    u[0] * v[1] - u[1] * v[0],
+                ];
+                let length = normal.iter().map(|x| x * x).sum::<f64>().sqrt();
+                let normal = normal.map(|x| if length > 0.0 { x / length } else { 0.0 });
+                for vector in &[normal, a, b, c] {
+                    for x in vector {
+                        self.out.write_all(&(*x as f32).to_le_bytes())?;
+                    }
+                }
+                // The attribute byte count, which is unused
+                self.out.write_all(&[0, 0])?;
+            }
+        }
+        Ok(())
+    }
+
+    fn write_obj(&mut self) -> io::Result<()> {
+        writeln!(self.out, "# Penrose tiling generated by rose")?;
+
+        // The indices of the vertices are shared by every object, and start at 1
+        let mut offset = 1;
+        for (name, mesh) in &self.objects {
+            writeln!(self.out, "o {}", name)?;
+            for [x, y, z] in &mesh.vertices {
+                writeln!(self.out, "v {:.4} {:.4} {:.4}", x, y, z)?;
+            }
+            for [a, b, c] in &mesh.triangles {
+                writeln!(self.out, "f {} {} {}", a + offset, b + offset, c + offset)?;
+            }
+            offset += mesh.vertices.len();
+        }
+        Ok(())
+    }
+}
+
+impl<W: Write> Backend for MeshBackend<W> {
+    fn begin(&mut self, scene: &Scene) -> io::Result<()> {
+        self.view_box_height = scene.view_box_height as f64;
+        self.ridge_width = scene.stroke_width;
+        for group in &scene.groups {
+            match group.kind {
+                GroupKind::FirstArcs => self.ridges[0] = true,
+                GroupKind::SecondArcs => self.ridges[1] = true,
+                _ => (),
+            }
+        }
+        Ok(())
+    }
+
+    fn draw_polygon(&mut self, tile: &Tile) -> io::Result<()> {
+        let object = self.object(tile.tile_type.name());
+        let polygon: Vec<_> = tile
+            .vertices
+            .iter()
+            .map(|&p| flip_y(p, self.view_box_height))
+            .collect();
+        let piece = inset(&polygon, self.config.gap / 2.0);
+        let top = self.config.thickness;
+        self.objects[object].1.add_prism(&piece, 0.0, top)?;
+
+        // The ridges are part of the pieces, so they are in the object of their tile
+        let arcs = tile
+            .arcs
+            .map_or(vec![], |(first, second)| vec![first, second]);
+        for (arc, _) in arcs
+            .into_iter()
+            .zip(self.ridges)
+            .filter(|(_, drawn)| *drawn)
+        {
+            if let Some(band) = self.ridge(arc, &polygon, &piece) {
+                self.objects[object]
+                    .1
+                    .add_prism(&band, top, top + self.config.ridge_height)?;
+            }
+        }
+        Ok(())
+    }
+
+    // The ridges are added with their tiles
+    fn draw_arc(&mut self, _: Arc) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn draw_marker(&mut self, _: Point, _: f64) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn draw_line(&mut self, _: Point, _: Point) -> io::Result<()> {
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        match self.format {
+            MeshFormat::Stl => self.write_stl()?,
+            MeshFormat::Obj => self.write_obj()?,
+        }
+        self.out.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn export(format: MeshFormat) -> (Vec<u8>, MeshBackend<Vec<u8>>, usize) {
+        let config = MeshConfig {
+            thickness: 3.0,
+            gap: 0.4,
+            ridge_height: 1.0,
+        };
+        let mut backend = MeshBackend::new(format, config, Vec::new());
+        let num_tiles = crate::render::render_seed(
+            crate::seeds::tile(TileType::Kite),
+            3,
+            Some(("green", "yellow")),
+            &mut backend,
+        );
+        let out = std::mem::take(&mut backend.out);
+        (out, backend, num_tiles)
+    }
+
+    // The signed volume of a mesh, which is positive if its triangles face outwards
+    fn volume(mesh: &Mesh) -> f64 {
+        mesh.triangles
+            .iter()
+            .map(|t| {
+                let [a, b, c] = t.map(|i| mesh.vertices[i]);
+                (a[0] * (b[1] * c[2] - b[2] * c[1]) - a[1] * (b[0] * c[2] - b[2] * c[0])
+                    + a[2] * (b[0] * c[1] - b[1] * c[0]))
+                    / 6.0
+            })
+            .sum()
+    }
+
+    #[test]
+    fn test_prism() {
+        // A dart, which isn't convex, so its faces can't be split from a single corner
+        let dart = [
+            Point(0.0, 0.0),
+            Point(2.0, -1.0),
+            Point(0.5, 0.0),
+            Point(2.0, 1.0),
+        ];
+        let area = signed_area(&dart).abs() / 2.0;
+        let mut mesh = Mesh::default();
+        mesh.add_prism(&dart, 0.0, 2.0).unwrap();
+        assert_eq!(mesh.triangles.len(), 2 * 2 + 2 * 4);
+        assert_close!(volume(&mesh), 2.0 * area);
+
+        // Every edge is shared by two triangles, which go through it in opposite directions
+        let mut edges: Vec<_> = mesh
+            .triangles
+            .iter()
+            .flat_map(|&[a, b, c]| vec![(a, b), (b, c), (c, a)])
+            .collect();
+        edges.sort_unstable();
+        let mut reversed: Vec<_> = edges.iter().map(|&(a, b)| (b, a)).collect();
+        reversed.sort_unstable();
+        assert_eq!(edges, reversed);
+
+        // The corners of this dart are too sharp to move all the way, so they move by the miter
+        // limit instead
+        let shrunk = inset(&dart, 0.1);
+        for (&p, &q) in dart.iter().zip(&shrunk) {
+            assert!(p.distance_to(q) <= MITER_LIMIT * 0.1 + 1e-9);
+        }
+
+        // Every side of the inset Penrose dart is at the inset distance from the original side
+        let (x, y) = (
+            PHI * (36.0 * DEG_TO_RAD).cos(),
+            PHI * (36.0 * DEG_TO_RAD).sin(),
+        );
+        let dart = [Point(0.0, 0.0), Point(x, -y), Point(1.0, 0.0), Point(x, y)];
+        let shrunk = inset(&dart, 0.1);
+        for i in 0..4 {
+            let (a, b) = (dart[i], dart[(i + 1) % 4]);
+            let direction = (b - a).normalized();
+            for &p in &[shrunk[i], shrunk[(i + 1) % 4]] {
+                assert_close!(direction.cross(p - a).abs(), 0.1);
+            }
+        }
+    }
+
+    #[test]
+    fn test_ridge() {
+        let config = MeshConfig {
+            thickness: 3.0,
+            gap: 0.4,
+            ridge_height: 1.0,
+        };
+        let mut backend = MeshBackend::new(MeshFormat::Stl, config, Vec::new());
+        backend.view_box_height = 10.0;
+        backend.ridge_width = 1.0;
+
+        // A quarter circle around a corner of a square, which ends on the two sides of the corner
+        let square = [
+            Point(0.0, 0.0),
+            Point(10.0, 0.0),
+            Point(10.0, 10.0),
+            Point(0.0, 10.0),
+        ];
+        let piece = inset(&square, 0.2);
+        let arc = (Point(4.0, 10.0), Point(0.0, 10.0), Point(0.0, 6.0), false);
+        let band = backend.ridge(arc, &square, &piece).unwrap();
+
+        // The band is cut along the sides of the piece, instead of sticking out into the gap
+        let (xs, ys): (Vec<_>, Vec<_>) = band.iter().map(|&Point(x, y)| (x, y)).unzip();
+        let min = |v: &[f64]| v.iter().cloned().fold(f64::INFINITY, f64::min);
+        assert_close!(min(&xs), 0.2);
+        assert_close!(min(&ys), 0.2);
+        let on_side = |v: &[f64]| v.iter().filter(|&&x| close(x, 0.2)).count();
+        assert_eq!((on_side(&xs), on_side(&ys)), (2, 2));
+        assert!(triangulate(&band).is_some());
+    }
+
+    #[test]
+    fn test_mesh_export() {
+        let (stl, backend, num_tiles) = export(MeshFormat::Stl);
+        let names: Vec<_> = backend.objects.iter().map(|(n, _)| n.as_str()).collect();
+        assert_eq!(names, ["dart", "kite"]);
+        let num_triangles: usize = backend.objects.iter().map(|(_, m)| m.triangles.len()).sum();
+        assert_eq!(
+            u32::from_le_bytes([stl[80], stl[81], stl[82], stl[83]]) as usize,
+            num_triangles
+        );
+        assert_eq!(stl.len(), 84 + 50 * num_triangles);
+
+        // Every piece is a whole prism, shrunk by the gap, with the ridges of its arcs on top
+        for (_, mesh) in &backend.objects {
+            assert!(volume(mesh) > 0.0);
+            assert!(mesh
+                .vertices
+                .iter()
+                .all(|v| [0.0, 3.0, 4.0].contains(&v[2])));
+            let ridge_vertices = mesh.vertices.iter().filter(|v| v[2] == 4.0).count();
+            assert!(ridge_vertices > 0);
+        }
+        let num_ridge_triangles = (backend.objects.iter())
+            .flat_map(|(_, m)| m.triangles.iter().map(move |t| t.map(|i| m.vertices[i][2])))
+            .filter(|t| t.contains(&4.0))
+            .count();
+        assert!(num_ridge_triangles > 2 * num_tiles);
+
+        let (obj, backend, _) = export(MeshFormat::Obj);
+        let obj = String::from_utf8(obj).unwrap();
+        assert_eq!(obj.matches("\no ").count(), 2);
+        assert_eq!(obj.matches("\nf ").count(), num_triangles);
+        let num_vertices: usize = backend.objects.iter().map(|(_, m)| m.vertices.len()).sum();
+        assert_eq!(obj.matches("\nv ").count(), num_vertices);
+    }
+}
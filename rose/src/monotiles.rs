@@ -0,0 +1,689 @@
+use crate::geometry::*;
+use crate::render::Polygon;
+use crate::rules::Affine;
+
+use std::rc::Rc;
+
+// The hat and the spectre are aperiodic monotiles: single shapes that only tile the plane without
+// repeating. Their tilings are generated by substitution too, but not of the tiles themselves,
+// which can't be decomposed into smaller copies of themselves. Instead, the tiles are grouped into
+// metatiles, and the metatiles into larger metatiles of the same kinds, as described by Smith,
+// Myers, Kaplan and Goodman-Strauss. This follows the constructions in Craig Kaplan's reference
+// implementations, which build the hierarchy upwards, from the tiles to the largest metatile, and
+// then place every tile by composing the transforms of its ancestors.
+//
+// Unlike the other substitutions, the metatiles aren't exactly similar to their parents, since
+// their shapes change slightly from one level to the next, so the transforms are computed from the
+// geometry of each level. The tiles keep their size, and the metatiles grow with each level.
+
+/// The aperiodic monotiles that can be generated.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum Monotile {
+    Hat,
+    Spectre,
+}
+
+impl Monotile {
+    pub fn tile_type(self) -> TileType {
+        match self {
+            Monotile::Hat => TileType::Hat,
+            Monotile::Spectre => TileType::Spectre,
+        }
+    }
+
+    /// The vertices of the tile, in the coordinates its metatiles are built in.
+    pub fn shape(self) -> Vec<Point> {
+        match self {
+            Monotile::Hat => HAT_OUTLINE.iter().map(|&(x, y)| hex_point(x, y)).collect(),
+            Monotile::Spectre => spectre_outline().to_vec(),
+        }
+    }
+}
+
+/// The orientation of a tile, as a rotation by a multiple of 30 degrees, which can be preceded by a
+/// reflection. The hats are only ever rotated by multiples of 60 degrees, but the spectres can be
+/// rotated by any multiple of 30 degrees. All the spectres in a tiling have the same handedness,
+/// so they are either all reflected or none of them is.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct Orientation {
+    pub rotation: u32,
+    pub reflected: bool,
+}
+
+impl Orientation {
+    /// Every possible orientation, with the unreflected ones first.
+    pub fn all() -> impl Iterator<Item = Orientation> {
+        [false, true].iter().flat_map(|&reflected| {
+            (0..12).map(move |rotation| Orientation {
+                rotation,
+                reflected,
+            })
+        })
+    }
+
+    fn of(transform: &Affine) -> Self {
+        let [a, b, _, _, _, _] = transform.0;
+        let steps = (b.atan2(a).to_degrees() / 30.0).round() as i64;
+        Orientation {
+            rotation: steps.rem_euclid(12) as u32,
+            reflected: transform.determinant() < 0.0,
+        }
+    }
+}
+
+/// A tile of a monotile tiling, placed in the tiling coordinates.
+#[derive(Debug, Clone)]
+pub struct MonotileTile {
+    pub tile_type: TileType,
+    pub orientation: Orientation,
+    pub vertices: Vec<Point>,
+}
+
+impl Polygon for MonotileTile {
+    type Vertex = Point;
+
+    fn polygon_type(&self) -> TileType {
+        self.tile_type
+    }
+
+    fn vertices(&self) -> Vec<Point> {
+        self.vertices.clone()
+    }
+
+    // The matching rules of the monotiles are enforced by their shapes alone
+    fn arcs(&self) -> Option<(Arc, Arc)> {
+        None
+    }
+
+    fn orientation(&self) -> Option<Orientation> {
+        Some(self.orientation)
+    }
+}
+
+// A metatile, or a single tile if it has no children. The outline is the shape that the rules
+// that build the next level refer to, which is only approximately the shape covered by the tiles
+// in the metatile. The bounding circle, however, contains all of them
+#[derive(Debug)]
+struct Node {
+    outline: Vec<Point>,
+    children: Vec<(Affine, Rc<Node>)>,
+    center: Point,
+    radius: f64,
+}
+
+impl Node {
+    fn leaf(outline: Vec<Point>, shape: &[Point]) -> Self {
+        let bounds = Rectangle::bounding(shape);
+        let center = (bounds.min + bounds.max) / 2.0;
+        Node {
+            outline,
+            children: Vec::new(),
+            center,
+            radius: center.distance_to(bounds.max),
+        }
+    }
+
+    fn new(outline: Vec<Point>, children: Vec<(Affine, Rc<Node>)>) -> Self {
+        // The transforms are similarities, so they scale the radius of the bounding circle by the
+        // square root of the factor by which they scale areas
+        let centers: Vec<_> = children.iter().map(|(t, c)| t.apply(c.center)).collect();
+        let center = centers.iter().fold(Point::ZERO, |sum, &p| sum + p) / centers.len() as f64;
+        let radius = children
+            .iter()
+            .zip(&centers)
+            .map(|((t, c), &p)| center.distance_to(p) + t.determinant().abs().sqrt() * c.radius)
+            .fold(0.0, f64::max);
+        Node {
+            outline,
+            children,
+            center,
+            radius,
+        }
+    }
+
+    // The position of vertex `i` of the outline of child `n`
+    fn eval_child(&self, n: usize, i: usize) -> Point {
+        let (transform, child) = &self.children[n];
+        transform.apply(child.outline[i])
+    }
+
+    // Returns the same metatile, translated so that the centroid of its outline is on the origin
+    fn recentred(self) -> Self {
+        let n = self.outline.len() as f64;
+        let centroid = self.outline.iter().fold(Point::ZERO, |sum, &p| sum + p) / n;
+        let translation = Affine::translate(-centroid);
+        Node::new(
+            self.outline.iter().map(|&p| p - centroid).collect(),
+            self.children
+                .into_iter()
+                .map(|(t, c)| (translation.compose(&t), c))
+                .collect(),
+        )
+    }
+}
+
+/// Builds the metatiles of the given monotile up to the given level, and returns the tiles of the
+/// largest one, centered on the origin. Each level multiplies the number of tiles by about 6.9 for
+/// the hat, and by about 7.9 for the spectre.
+pub fn generate_tiling(monotile: Monotile, num_generations: u64) -> Vec<MonotileTile> {
+    let (top, transform) = top_metatile(monotile, num_generations);
+    let mut tiles = Vec::new();
+    collect_tiles(monotile, &top, transform, None, &mut tiles);
+    tiles
+}
+
+/// Like `generate_tiling`, but only returns the tiles in the metatiles that may intersect
+/// `bounds`, which is much faster if the bounds are small compared to the largest metatile.
+pub fn generate_tiling_clipped(
+    monotile: Monotile,
+    num_generations: u64,
+    bounds: Rectangle,
+) -> Vec<MonotileTile> {
+    let (top, transform) = top_metatile(monotile, num_generations);
+    let mut tiles = Vec::new();
+    collect_tiles(monotile, &top, transform, Some(bounds), &mut tiles);
+    tiles
+}
+
+/// The distance from the center of the largest metatile of the given level to its farthest
+/// vertex. This is used to scale the tiling to the view box, in the same way as the side length of
+/// a seed.
+pub fn radius(monotile: Monotile, num_generations: u64) -> f64 {
+    let (top, transform) = top_metatile(monotile, num_generations);
+    top.outline
+        .iter()
+        .map(|&p| Point::ZERO.distance_to(transform.apply(p)))
+        .fold(0.0, f64::max)
+}
+
+fn top_metatile(monotile: Monotile, num_generations: u64) -> (Rc<Node>, Affine) {
+    let top = match monotile {
+        Monotile::Hat => {
+            let levels = (0..num_generations).fold(hat_metatiles(), |metatiles, _| {
+                build_hat_metatiles(&hat_patch(&metatiles))
+            });
+            levels[H].clone()
+        }
+        Monotile::Spectre => {
+            let levels = (0..num_generations).fold(spectre_metatiles(), |metatiles, _| {
+                build_spectre_metatiles(&metatiles)
+            });
+            levels[SPECTRE_DELTA].clone()
+        }
+    };
+    let n = top.outline.len() as f64;
+    let centroid = top.outline.iter().fold(Point::ZERO, |sum, &p| sum + p) / n;
+    (top, Affine::translate(-centroid))
+}
+
+fn collect_tiles(
+    monotile: Monotile,
+    node: &Node,
+    transform: Affine,
+    bounds: Option<Rectangle>,
+    tiles: &mut Vec<MonotileTile>,
+) {
+    if let Some(bounds) = bounds {
+        let radius = transform.determinant().abs().sqrt() * node.radius;
+        if !bounds.expand(radius).contains(transform.apply(node.center)) {
+            return;
+        }
+    }
+    if node.children.is_empty() {
+        tiles.push(MonotileTile {
+            tile_type: monotile.tile_type(),
+            orientation: Orientation::of(&transform),
+            vertices: monotile
+                .shape()
+                .into_iter()
+                .map(|p| transform.apply(p))
+                .collect(),
+        });
+    }
+    for (child_transform, child) in &node.children {
+        let transform = transform.compose(child_transform);
+        collect_tiles(monotile, child, transform, bounds, tiles);
+    }
+}
+
+// The square root of 3, divided by 2
+const HR3: f64 = 0.866_025_403_784_438_6;
+
+// The hats and their metatiles are built on a grid of hexagons, whose centers have the coordinates
+// `x + y / 2` and `y * sqrt(3) / 2`
+fn hex_point(x: f64, y: f64) -> Point {
+    Point(x + 0.5 * y, HR3 * y)
+}
+
+const HAT_OUTLINE: [(f64, f64); 13] = [
+    (0.0, 0.0),
+    (-1.0, -1.0),
+    (0.0, -2.0),
+    (2.0, -2.0),
+    (2.0, -1.0),
+    (4.0, -2.0),
+    (5.0, -1.0),
+    (4.0, 0.0),
+    (3.0, 0.0),
+    (2.0, 2.0),
+    (0.0, 3.0),
+    (0.0, 2.0),
+    (-1.0, 2.0),
+];
+
+// The indices of the four hat metatiles, which have the shapes of a hexagon, a triangle, a
+// parallelogram and a pentagon, and are named after the Greek letters they approximate
+const H: usize = 0;
+const T: usize = 1;
+const P: usize = 2;
+const F: usize = 3;
+
+// The first level of metatiles, which are made of hats. The hats are half the size of the
+// metatiles' sides, and the one at the center of the H metatile is the only reflected one
+fn hat_metatiles() -> [Rc<Node>; 4] {
+    let hat = Monotile::Hat.shape();
+    let leaf = Rc::new(Node::leaf(hat.clone(), &hat));
+    let half = Affine::scale(0.5);
+    let translate = |x, y| Affine::translate(Point(x, y));
+
+    let h_outline = vec![
+        Point(0.0, 0.0),
+        Point(4.0, 0.0),
+        Point(4.5, HR3),
+        Point(2.5, 5.0 * HR3),
+        Point(1.5, 5.0 * HR3),
+        Point(-0.5, HR3),
+    ];
+    let on_edge = |i: usize, j: usize, p: Point, q: Point| {
+        (
+            Affine::match_segments((hat[i], hat[j]), (p, q)),
+            leaf.clone(),
+        )
+    };
+    let h = Node::new(
+        h_outline.clone(),
+        vec![
+            on_edge(5, 7, h_outline[5], h_outline[0]),
+            on_edge(9, 11, h_outline[1], h_outline[2]),
+            on_edge(5, 7, h_outline[3], h_outline[4]),
+            (
+                translate(2.5, HR3)
+                    .compose(&Affine::rotation(120.0))
+                    .compose(&Affine([0.5, 0.0, 0.0, -0.5, 0.0, 0.0])),
+                leaf.clone(),
+            ),
+        ],
+    );
+
+    let t = Node::new(
+        vec![Point(0.0, 0.0), Point(3.0, 0.0), Point(1.5, 3.0 * HR3)],
+        vec![(translate(0.5, HR3).compose(&half), leaf.clone())],
+    );
+
+    // The P and F metatiles both have the same two hats
+    let pair = || {
+        vec![
+            (translate(1.5, HR3).compose(&half), leaf.clone()),
+            (
+                translate(0.0, 2.0 * HR3)
+                    .compose(&Affine::rotation(-60.0))
+                    .compose(&half),
+                leaf.clone(),
+            ),
+        ]
+    };
+    let p = Node::new(
+        vec![
+            Point(0.0, 0.0),
+            Point(4.0, 0.0),
+            Point(3.0, 2.0 * HR3),
+            Point(-1.0, 2.0 * HR3),
+        ],
+        pair(),
+    );
+    let f = Node::new(
+        vec![
+            Point(0.0, 0.0),
+            Point(3.0, 0.0),
+            Point(3.5, HR3),
+            Point(3.0, 2.0 * HR3),
+            Point(-1.0, 2.0 * HR3),
+        ],
+        pair(),
+    );
+    [Rc::new(h), Rc::new(t), Rc::new(p), Rc::new(f)]
+}
+
+// A rule for placing a metatile in the patch that the next level is cut from
+enum PatchRule {
+    // The first metatile, placed as it is
+    First(usize),
+
+    // A metatile whose edge `j` is glued to edge `i` of an earlier metatile `n`, in the opposite
+    // direction
+    Edge(usize, usize, usize, usize),
+
+    // A metatile whose edge `j` is glued to the segment from vertex `k` of metatile `m` to vertex
+    // `i` of metatile `n`
+    Corner(usize, usize, usize, usize, usize, usize),
+}
+
+const PATCH_RULES: [PatchRule; 29] = {
+    use PatchRule::*;
+    [
+        First(H),
+        Edge(0, 0, P, 2),
+        Edge(1, 0, H, 2),
+        Edge(2, 0, P, 2),
+        Edge(3, 0, H, 2),
+        Edge(4, 4, P, 2),
+        Edge(0, 4, F, 3),
+        Edge(2, 4, F, 3),
+        Corner(4, 1, 3, 2, F, 0),
+        Edge(8, 3, H, 0),
+        Edge(9, 2, P, 0),
+        Edge(10, 2, H, 0),
+        Edge(11, 4, P, 2),
+        Edge(12, 0, H, 2),
+        Edge(13, 0, F, 3),
+        Edge(14, 2, F, 1),
+        Edge(15, 3, H, 4),
+        Edge(8, 2, F, 1),
+        Edge(17, 3, H, 0),
+        Edge(18, 2, P, 0),
+        Edge(19, 2, H, 2),
+        Edge(20, 4, F, 3),
+        Edge(20, 0, P, 2),
+        Edge(22, 0, H, 2),
+        Edge(23, 4, F, 3),
+        Edge(23, 0, F, 3),
+        Edge(16, 0, P, 2),
+        Corner(9, 4, 0, 2, T, 2),
+        Edge(4, 0, F, 3),
+    ]
+};
+
+// Glues copies of the metatiles together into a patch that contains every metatile of the next
+// level
+fn hat_patch(metatiles: &[Rc<Node>; 4]) -> Node {
+    let mut patch = Node {
+        outline: Vec::new(),
+        children: Vec::new(),
+        center: Point::ZERO,
+        radius: 0.0,
+    };
+    for rule in PATCH_RULES.iter() {
+        let (p, q, shape, j) = match *rule {
+            PatchRule::First(shape) => {
+                patch
+                    .children
+                    .push((Affine::IDENTITY, metatiles[shape].clone()));
+                continue;
+            }
+            PatchRule::Edge(n, i, shape, j) => {
+                let len = patch.children[n].1.outline.len();
+                let p = patch.eval_child(n, (i + 1) % len);
+                (p, patch.eval_child(n, i), shape, j)
+            }
+            PatchRule::Corner(n, i, m, k, shape, j) => {
+                (patch.eval_child(m, k), patch.eval_child(n, i), shape, j)
+            }
+        };
+        let outline = &metatiles[shape].outline;
+        let edge = (outline[j], outline[(j + 1) % outline.len()]);
+        let transform = Affine::match_segments(edge, (p, q));
+        patch.children.push((transform, metatiles[shape].clone()));
+    }
+    patch
+}
+
+// The point where the line through `p1` and `q1` crosses the line through `p2` and `q2`
+fn intersect(p1: Point, q1: Point, p2: Point, q2: Point) -> Point {
+    let (d1, d2) = (q1 - p1, q2 - p2);
+    let t = d2.cross(p2 - p1) / d2.cross(d1);
+    p1 + t * d1
+}
+
+fn rotate_about(center: Point, angle: f64) -> Affine {
+    Affine::translate(center)
+        .compose(&Affine::rotation(angle))
+        .compose(&Affine::translate(-center))
+}
+
+// Cuts the metatiles of the next level out of the patch
+fn build_hat_metatiles(patch: &Node) -> [Rc<Node>; 4] {
+    let bps1 = patch.eval_child(8, 2);
+    let bps2 = patch.eval_child(21, 2);
+    let rbps = rotate_about(bps1, -120.0).apply(bps2);
+    let p72 = patch.eval_child(7, 2);
+    let p252 = patch.eval_child(25, 2);
+    let llc = intersect(bps1, rbps, patch.eval_child(6, 2), p72);
+    let mut w = patch.eval_child(6, 2) - llc;
+
+    let mut h_outline = vec![llc, bps1];
+    w = w.rotate(-60.0);
+    h_outline.push(h_outline[1] + w);
+    h_outline.push(patch.eval_child(14, 2));
+    w = w.rotate(-60.0);
+    h_outline.push(h_outline[3] - w);
+    h_outline.push(patch.eval_child(6, 2));
+
+    let metatile = |outline: Vec<Point>, children: &[usize]| {
+        let children = children
+            .iter()
+            .map(|&i| patch.children[i].clone())
+            .collect();
+        Rc::new(Node::new(outline, children).recentred())
+    };
+    let p = metatile(vec![p72, p72 + (bps1 - llc), bps1, llc], &[7, 2, 3, 4, 28]);
+    let f = metatile(
+        vec![
+            bps2,
+            patch.eval_child(24, 2),
+            patch.eval_child(25, 0),
+            p252,
+            p252 + (llc - bps1),
+        ],
+        &[21, 20, 22, 23, 24, 25],
+    );
+    let a = h_outline[2];
+    let b = h_outline[1] + (h_outline[4] - h_outline[5]);
+    let c = rotate_about(b, -60.0).apply(a);
+    let t = metatile(vec![b, c, a], &[11]);
+    let h = metatile(h_outline, &[0, 9, 16, 27, 26, 6, 1, 8, 10, 15]);
+    [h, t, p, f]
+}
+
+fn spectre_outline() -> [Point; 14] {
+    [
+        Point(0.0, 0.0),
+        Point(1.0, 0.0),
+        Point(1.5, -HR3),
+        Point(1.5 + HR3, 0.5 - HR3),
+        Point(1.5 + HR3, 1.5 - HR3),
+        Point(2.5 + HR3, 1.5 - HR3),
+        Point(3.0 + HR3, 1.5),
+        Point(3.0, 2.0),
+        Point(3.0 - HR3, 1.5),
+        Point(2.5 - HR3, 1.5 + HR3),
+        Point(1.5 - HR3, 1.5 + HR3),
+        Point(0.5 - HR3, 1.5 + HR3),
+        Point(-HR3, 1.5),
+        Point(0.0, 1.0),
+    ]
+}
+
+// The indices of the nine spectre metatiles. All of them are single spectres, except for the
+// Gamma, which is a pair of spectres, called the mystic
+const SPECTRE_GAMMA: usize = 0;
+const SPECTRE_DELTA: usize = 1;
+const SPECTRE_THETA: usize = 2;
+const SPECTRE_LAMBDA: usize = 3;
+const SPECTRE_XI: usize = 4;
+const SPECTRE_PI: usize = 5;
+const SPECTRE_SIGMA: usize = 6;
+const SPECTRE_PHI: usize = 7;
+const SPECTRE_PSI: usize = 8;
+
+// The children of each metatile, in the order of the transforms computed by
+// `build_spectre_metatiles`. The Gamma metatile has one less child than the others
+const SPECTRE_RULES: [[Option<usize>; 8]; 9] = {
+    const N: Option<usize> = None;
+    const GA: Option<usize> = Some(SPECTRE_GAMMA);
+    const DE: Option<usize> = Some(SPECTRE_DELTA);
+    const TH: Option<usize> = Some(SPECTRE_THETA);
+    const LA: Option<usize> = Some(SPECTRE_LAMBDA);
+    const X: Option<usize> = Some(SPECTRE_XI);
+    const P: Option<usize> = Some(SPECTRE_PI);
+    const S: Option<usize> = Some(SPECTRE_SIGMA);
+    const PH: Option<usize> = Some(SPECTRE_PHI);
+    const PS: Option<usize> = Some(SPECTRE_PSI);
+    [
+        [P, DE, N, TH, S, X, PH, GA],
+        [X, DE, X, PH, S, P, PH, GA],
+        [PS, DE, P, PH, S, P, PH, GA],
+        [PS, DE, X, PH, S, P, PH, GA],
+        [PS, DE, P, PH, S, PS, PH, GA],
+        [PS, DE, X, PH, S, PS, PH, GA],
+        [X, DE, X, PH, S, P, LA, GA],
+        [PS, DE, PS, PH, S, P, PH, GA],
+        [PS, DE, PS, PH, S, PS, PH, GA],
+    ]
+};
+
+// The first level of metatiles. Their outline is a quadrilateral with four of the spectre's
+// vertices, which the transforms of the next level are computed from
+fn spectre_metatiles() -> [Rc<Node>; 9] {
+    let spectre = spectre_outline();
+    let quad = vec![spectre[3], spectre[5], spectre[7], spectre[11]];
+    let leaf = Rc::new(Node::leaf(quad.clone(), &spectre));
+    let mystic = Rc::new(Node::new(
+        quad,
+        vec![
+            (Affine::IDENTITY, leaf.clone()),
+            (
+                Affine::translate(spectre[8]).compose(&Affine::rotation(30.0)),
+                leaf.clone(),
+            ),
+        ],
+    ));
+    std::array::from_fn(|i| {
+        if i == SPECTRE_GAMMA {
+            mystic.clone()
+        } else {
+            leaf.clone()
+        }
+    })
+}
+
+fn build_spectre_metatiles(metatiles: &[Rc<Node>; 9]) -> [Rc<Node>; 9] {
+    // Each metatile is placed by rotating it and then gluing two vertices of its quad to the
+    // previous one, and the whole metatile is reflected, so the levels alternate between spectres
+    // and their mirror images
+    let quad = &metatiles[SPECTRE_DELTA].outline;
+    let mut transforms = vec![Affine::IDENTITY];
+    let mut total_angle = 0.0;
+    for &(angle, from, to) in &[
+        (60.0, 3, 1),
+        (0.0, 2, 0),
+        (60.0, 3, 1),
+        (60.0, 3, 1),
+        (0.0, 2, 0),
+        (60.0, 3, 1),
+        (-120.0, 3, 3),
+    ] {
+        total_angle += angle;
+        let rotation = Affine::rotation(total_angle);
+        let previous = transforms.last().unwrap().apply(quad[from]);
+        let translation = Affine::translate(previous - rotation.apply(quad[to]));
+        transforms.push(translation.compose(&rotation));
+    }
+    let reflection = Affine([-1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+    let transforms: Vec<_> = transforms.iter().map(|t| reflection.compose(t)).collect();
+
+    let super_quad = vec![
+        transforms[6].apply(quad[2]),
+        transforms[5].apply(quad[1]),
+        transforms[3].apply(quad[2]),
+        transforms[0].apply(quad[1]),
+    ];
+    std::array::from_fn(|i| {
+        let children = SPECTRE_RULES[i]
+            .iter()
+            .zip(&transforms)
+            .filter_map(|(child, &t)| child.map(|c| (t, metatiles[c].clone())))
+            .collect();
+        Rc::new(Node::new(super_quad.clone(), children))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn contains(vertices: &[Point], p: Point) -> bool {
+        // Counts how many edges cross the horizontal ray from the point to the right
+        let n = vertices.len();
+        (0..n)
+            .filter(|&i| {
+                let (a, b) = (vertices[i], vertices[(i + 1) % n]);
+                (a.1 > p.1) != (b.1 > p.1) && p.0 < a.0 + (p.1 - a.1) / (b.1 - a.1) * (b.0 - a.0)
+            })
+            .count()
+            % 2
+            == 1
+    }
+
+    #[test]
+    fn test_tiles_fit() {
+        // If the tiles don't overlap or leave gaps, every point near the center of the tiling is
+        // in exactly one of them. The largest metatiles aren't convex, so only the points well
+        // inside them are tested
+        let mut rng = rand::thread_rng();
+        for &monotile in &[Monotile::Hat, Monotile::Spectre] {
+            let tiles = generate_tiling(monotile, 2);
+            let radius = radius(monotile, 2) / 5.0;
+            for _ in 0..2000 {
+                let p = Point(
+                    rng.gen_range(-radius..radius),
+                    rng.gen_range(-radius..radius),
+                );
+                let count = tiles.iter().filter(|t| contains(&t.vertices, p)).count();
+                assert_eq!(count, 1, "{:?} at {:?}", monotile, p);
+            }
+        }
+    }
+
+    #[test]
+    fn test_orientations() {
+        // About one in seven hats is reflected, and the spectres of a level are either all
+        // reflected or not reflected at all
+        let hats = generate_tiling(Monotile::Hat, 3);
+        let reflected = hats.iter().filter(|t| t.orientation.reflected).count();
+        assert!(reflected * 5 < hats.len() && reflected * 10 > hats.len());
+        assert!(hats.iter().all(|t| t.orientation.rotation % 2 == 0));
+
+        for n in 0..3 {
+            let spectres = generate_tiling(Monotile::Spectre, n);
+            let reflected = |t: &MonotileTile| t.orientation.reflected;
+            assert!(spectres.iter().all(reflected) || !spectres.iter().any(reflected));
+        }
+    }
+
+    #[test]
+    fn test_clipping() {
+        let bounds = Rectangle {
+            min: Point(-3.0, -3.0),
+            max: Point(4.0, 2.0),
+        };
+        let all = generate_tiling(Monotile::Hat, 3);
+        let clipped = generate_tiling_clipped(Monotile::Hat, 3, bounds);
+        assert!(clipped.len() < all.len() / 4);
+        let visible = |t: &&MonotileTile| bounds.intersects(&Rectangle::bounding(&t.vertices));
+        assert_eq!(
+            all.iter().filter(visible).count(),
+            clipped.iter().filter(visible).count()
+        );
+    }
+}
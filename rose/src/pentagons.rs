@@ -0,0 +1,486 @@
+use crate::geometry::*;
+use crate::golden::*;
+use crate::render::Polygon;
+
+use std::collections::{HashMap, HashSet};
+
+// The tiles of Penrose's first tiling, P1, all have sides of the same length, and angles that are
+// multiples of 36 degrees. Decomposing a tile scales it down by phi^2, so each side of the parent is
+// split into a side of a child, a gap, and the side of another child. The gap is a notch shaped
+// like half of a diamond, that points into the parent.
+//
+// A pentagon is decomposed into a pentagon at each of its corners, and an upside down pentagon in
+// the middle, which leaves a notch in each of its sides. The other tiles are decomposed into
+// children that poke out of the parent into the notches of the pentagons around it: a boat at each
+// tip, with its side tips in the notches next to that tip, and pentagons and a star in the middle.
+// So every notch of a pentagon is filled either by the tip of one of these children, or, if the
+// pentagon next to it is another pentagon, by a new diamond made of their two facing notches.
+//
+// The diamonds are decomposed into a star at one end, a boat at the other end, and a pentagon, so
+// they are the only tiles that need to be told apart from their mirror image. The star goes to the
+// end that is inside the pentagon with the same orientation as the one in the pentagon seed. Since
+// the two pentagons on either side of a diamond always have opposite orientations, this chooses
+// exactly one end, while keeping the symmetry of the seeds
+//
+// Penrose told the pentagons apart by the way they fit with the tiles around them. A side of a
+// pentagon is either shared with another pentagon, and then their two notches make a diamond when
+// they are decomposed, or it's shared with a star, a boat or a diamond, whose children fill the
+// notch with a tip. Away from the boundary of the tiling, every pentagon shares either two, three
+// or all five of its sides with other pentagons, always in the same arrangement, which gives the
+// three classes of pentagons. The classes depend on the tiles around the pentagon, so they are
+// only found once the whole tiling is generated
+
+/// A tile of Penrose's original tiling, P1, which is made of pentagons, stars, boats and diamonds.
+/// Unlike the tiles of the other two tilings, these can't be split into Robinson triangles.
+#[derive(Debug, PartialEq, Eq, Hash, Clone)]
+pub struct PentagonTile {
+    pub tile_type: TileType,
+
+    /// The vertices of the tile, going counterclockwise in the tiling coordinates. In the stars,
+    /// boats and diamonds, the first vertex is one of the tips. In boats, it's the middle tip, and
+    /// in diamonds, it's the end that becomes a star when the diamond is decomposed. In pentagons,
+    /// it's the one that puts the sides they share with other pentagons where `shared_sides`
+    /// expects them.
+    pub vertices: Vec<GoldenPoint>,
+}
+
+impl PentagonTile {
+    /// Creates the tile of the given type whose first vertex is `start`, and whose first side goes
+    /// from `start` to `start + side`.
+    pub fn new(tile_type: TileType, start: GoldenPoint, side: GoldenPoint) -> Self {
+        let angles = corner_angles(tile_type);
+        let mut vertices = Vec::with_capacity(angles.len());
+        let (mut vertex, mut side) = (start, side);
+        vertices.push(vertex);
+        for &angle in &angles[1..] {
+            vertex = vertex + side;
+            vertices.push(vertex);
+            side = side.rotate_steps(5 - angle);
+        }
+        PentagonTile {
+            tile_type,
+            vertices,
+        }
+    }
+
+    /// The indices of the vertices that are tips, that is, that have an angle of 36 degrees.
+    pub fn tips(&self) -> impl Iterator<Item = usize> {
+        let angles = corner_angles(self.tile_type);
+        (0..angles.len()).filter(move |&i| angles[i] == 1)
+    }
+
+    /// The length of the sides of the tile, which is the same for all of them.
+    pub fn side_length(&self) -> f64 {
+        (self.vertices[1] - self.vertices[0])
+            .norm_squared()
+            .to_f64()
+            .sqrt()
+    }
+}
+
+impl Polygon for PentagonTile {
+    type Vertex = GoldenPoint;
+
+    fn polygon_type(&self) -> TileType {
+        self.tile_type
+    }
+
+    fn vertices(&self) -> Vec<GoldenPoint> {
+        self.vertices.clone()
+    }
+
+    fn arcs(&self) -> Option<(Arc, Arc)> {
+        let vertices: Vec<_> = self.vertices.iter().map(|v| v.to_point()).collect();
+        match self.tile_type {
+            // The diamonds are decomposed differently from their mirror images, so their marks
+            // tell their star end from their boat end
+            TileType::Diamond => {
+                let [a, b, c, d] = [0, 1, 2, 3].map(|i| vertices[i]);
+                let first_arc = (Line(a, b).median(), a, Line(a, d).median(), false);
+                let second_arc = (Line(c, b).median(), c, Line(c, d).median(), false);
+                Some((first_arc, second_arc))
+            }
+
+            // The marks of the pentagons follow a circle around their center, along every side
+            // they share with another pentagon. The first arc goes along the first side, and the
+            // last one if it's shared, and the second arc along the third side, and the second and
+            // fourth ones if they are shared
+            TileType::Pentagon2 | TileType::Pentagon3 | TileType::Pentagon5 => {
+                let shared = shared_sides(self.tile_type);
+                let center = vertices.iter().fold(Point::ZERO, |sum, &v| sum + v) / 5.0;
+                let radius = 0.7 * center.distance_to(Line(vertices[0], vertices[1]).median());
+                let corner = |i: usize| center + radius * (vertices[i] - center).normalized();
+                let first_start = if shared[4] { 4 } else { 0 };
+                let (second_start, second_end) = if shared[1] { (1, 4) } else { (2, 3) };
+                let first_arc = (corner(first_start), center, corner(1), false);
+                let second_arc = (corner(second_start), center, corner(second_end), shared[1]);
+                Some((first_arc, second_arc))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Returns which sides a pentagon of the given class shares with other pentagons, starting from the
+/// side between its first two vertices. These are the sides where diamonds appear when the
+/// pentagon is decomposed.
+pub fn shared_sides(tile_type: TileType) -> [bool; 5] {
+    match tile_type {
+        TileType::Pentagon2 => [true, false, true, false, false],
+        TileType::Pentagon3 => [true, false, true, false, true],
+        TileType::Pentagon5 => [true; 5],
+        _ => panic!("{:?} is not a P1 pentagon", tile_type),
+    }
+}
+
+// The classes of pentagons, from the one that shares the fewest sides with other pentagons
+const PENTAGONS: [TileType; 3] = [
+    TileType::Pentagon2,
+    TileType::Pentagon3,
+    TileType::Pentagon5,
+];
+
+fn is_pentagon(tile_type: TileType) -> bool {
+    PENTAGONS.contains(&tile_type)
+}
+
+// The angle at each vertex of a tile, in multiples of 36 degrees, starting from the first one
+fn corner_angles(tile_type: TileType) -> &'static [i64] {
+    match tile_type {
+        TileType::Pentagon2 | TileType::Pentagon3 | TileType::Pentagon5 => &[3, 3, 3, 3, 3],
+        TileType::Star => &[1, 7, 1, 7, 1, 7, 1, 7, 1, 7],
+        TileType::Boat => &[1, 7, 1, 4, 4, 1, 7],
+        TileType::Diamond => &[1, 4, 1, 4],
+        _ => panic!("{:?} is not a P1 tile", tile_type),
+    }
+}
+
+// The gap between the children of a pentagon in the middle of one of its sides. The base goes in
+// the same direction as the side of the pentagon
+struct Notch {
+    apex: GoldenPoint,
+    base: (GoldenPoint, GoldenPoint),
+}
+
+// The classes of the children aren't known yet, so their pentagons are all given the same one
+fn decompose(tile: &PentagonTile) -> (Vec<PentagonTile>, Vec<Notch>) {
+    let PentagonTile {
+        tile_type,
+        vertices,
+    } = tile;
+    let n = vertices.len();
+    let sides: Vec<_> = (0..n)
+        .map(|i| (vertices[(i + 1) % n] - vertices[i]).div_phi().div_phi())
+        .collect();
+    let mut children = Vec::new();
+    let mut notches = Vec::new();
+    if is_pentagon(*tile_type) {
+        // The apex of each notch is a vertex of the middle pentagon
+        let mut middle = Vec::with_capacity(5);
+        for (&vertex, &side) in vertices.iter().zip(&sides) {
+            children.push(PentagonTile::new(TileType::Pentagon5, vertex, side));
+            let near = vertex + side;
+            let apex = near + side.rotate_steps(2);
+            notches.push(Notch {
+                apex,
+                base: (near, near + side.div_phi()),
+            });
+            middle.push(apex);
+        }
+        children.push(PentagonTile {
+            tile_type: TileType::Pentagon5,
+            vertices: middle,
+        });
+    } else {
+        for tip in tile.tips() {
+            let previous = (tip + n - 1) % n;
+            if *tile_type == TileType::Diamond && tip == 0 {
+                children.push(PentagonTile::new(TileType::Star, vertices[0], sides[0]));
+            } else {
+                children.push(PentagonTile::new(TileType::Boat, vertices[tip], sides[tip]));
+                children.push(PentagonTile::new(
+                    TileType::Pentagon5,
+                    vertices[previous],
+                    sides[previous],
+                ));
+            }
+        }
+
+        // The star in the middle of a star or a boat has its tips at the inner corners of the
+        // parent, and one more in the notch below a boat
+        if *tile_type != TileType::Diamond {
+            children.push(PentagonTile::new(
+                TileType::Star,
+                vertices[1],
+                sides[1].rotate_steps(3),
+            ));
+        }
+    }
+    (children, notches)
+}
+
+// Returns the direction of the vector, in multiples of 36 degrees. The vector must be parallel to
+// one of the sides of the tiles
+fn direction(v: GoldenPoint) -> i64 {
+    (0..10)
+        .find(|&steps| {
+            let GoldenPoint(x, y) = v.rotate_steps(-steps);
+            y == Golden::ZERO && x > Golden::ZERO
+        })
+        .expect("Vector is not parallel to a side of the tiles")
+}
+
+// Decomposes the tiles, keeping only the children that are close enough to the bounds, if given
+fn decompose_generation(tiles: &[PentagonTile], bounds: Option<Rectangle>) -> Vec<PentagonTile> {
+    let mut children = Vec::new();
+    let mut notches = Vec::new();
+    for tile in tiles {
+        let (c, n) = decompose(tile);
+        children.extend(c);
+        notches.extend(n);
+    }
+
+    // A notch can only be filled by a tip of a star or a boat, since any other tile would overlap
+    // the pentagons around it. The ones that aren't are paired with the notch on the other side of
+    // their base, which belongs to another pentagon
+    let tips: HashSet<_> = children
+        .iter()
+        .flat_map(|t| t.tips().map(move |i| t.vertices[i]))
+        .collect();
+    let mut unpaired = HashMap::new();
+    for Notch { apex, base } in notches {
+        if tips.contains(&apex) {
+            continue;
+        }
+        let (near, far) = base;
+        let key = if near < far { (near, far) } else { (far, near) };
+        let other_apex = match unpaired.remove(&key) {
+            Some(other_apex) => other_apex,
+            None => {
+                unpaired.insert(key, apex);
+                continue;
+            }
+        };
+
+        // The sides of the pentagon seed are at odd multiples of 36 degrees
+        let vertices = if direction(far - near) % 2 == 1 {
+            vec![apex, near, other_apex, far]
+        } else {
+            vec![other_apex, far, apex, near]
+        };
+        children.push(PentagonTile {
+            tile_type: TileType::Diamond,
+            vertices,
+        });
+    }
+    match bounds {
+        Some(bounds) => children
+            .into_iter()
+            .filter(|t| is_near(&bounds, t))
+            .collect(),
+        None => children,
+    }
+}
+
+// Returns true if the tile is within two side lengths of the bounds. The tiles next to the ones
+// that intersect the bounds must be kept, since their children may fill the notches of the
+// children of those, and they give the classes of the pentagons
+fn is_near(bounds: &Rectangle, tile: &PentagonTile) -> bool {
+    let vertices: Vec<_> = tile.vertices.iter().map(|v| v.to_point()).collect();
+    bounds
+        .expand(2.0 * tile.side_length())
+        .intersects(&Rectangle::bounding(&vertices))
+}
+
+// Sets the class of every pentagon from the tiles it shares its sides with, and rotates its
+// vertices to match it. Whether a side on the boundary of the tiling is shared with a pentagon
+// isn't known, so the pentagons there are given the class with the fewest shared sides that agrees
+// with the other sides
+fn classify_pentagons(tiles: &mut [PentagonTile]) {
+    // As in `validate`, the sides are found by twice their median. For each of them, this counts
+    // the tiles, and the pentagons, that have it
+    let mut sides: HashMap<GoldenPoint, (usize, usize)> = HashMap::new();
+    for t in tiles.iter() {
+        let n = t.vertices.len();
+        for i in 0..n {
+            let count = sides
+                .entry(t.vertices[i] + t.vertices[(i + 1) % n])
+                .or_default();
+            count.0 += 1;
+            if is_pentagon(t.tile_type) {
+                count.1 += 1;
+            }
+        }
+    }
+
+    for t in tiles.iter_mut().filter(|t| is_pentagon(t.tile_type)) {
+        let shared: Vec<_> = (0..5)
+            .map(
+                |i| match sides[&(t.vertices[i] + t.vertices[(i + 1) % 5])] {
+                    (_, 2) => Some(true),
+                    (2, _) => Some(false),
+                    _ => None,
+                },
+            )
+            .collect();
+        let (tile_type, rotation) = PENTAGONS
+            .iter()
+            .flat_map(|&tile_type| (0..5).map(move |rotation| (tile_type, rotation)))
+            .find(|&(tile_type, rotation)| {
+                let expected = shared_sides(tile_type);
+                (0..5).all(|i| shared[(i + rotation) % 5].map_or(true, |s| s == expected[i]))
+            })
+            .expect("Pentagon doesn't belong to any class");
+        t.tile_type = tile_type;
+        t.vertices.rotate_left(rotation);
+    }
+}
+
+pub fn generate_tiling(seed: Vec<PentagonTile>, num_generations: u64) -> Vec<PentagonTile> {
+    let mut tiles = (0..num_generations).fold(seed, |tiles, _| decompose_generation(&tiles, None));
+    classify_pentagons(&mut tiles);
+    tiles
+}
+
+/// Like `generate_tiling`, but discards the tiles that are far from `bounds` as the tiling is
+/// generated. Some of the tiles just outside the bounds are kept.
+pub fn generate_tiling_clipped(
+    seed: Vec<PentagonTile>,
+    num_generations: u64,
+    bounds: Rectangle,
+) -> Vec<PentagonTile> {
+    let seed: Vec<_> = seed.into_iter().filter(|t| is_near(&bounds, t)).collect();
+    let mut tiles =
+        (0..num_generations).fold(seed, |tiles, _| decompose_generation(&tiles, Some(bounds)));
+    classify_pentagons(&mut tiles);
+    tiles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::seeds;
+
+    fn count(tiles: &[PentagonTile], tile_types: &[TileType]) -> usize {
+        tiles
+            .iter()
+            .filter(|t| tile_types.contains(&t.tile_type))
+            .count()
+    }
+
+    #[test]
+    fn test_generation_counts() {
+        // The numbers of pentagons, stars, boats and diamonds after each generation
+        let expected = [
+            (
+                seeds::pentagon(),
+                [(6, 0, 0, 0), (36, 0, 0, 5), (221, 5, 5, 40)],
+            ),
+            (
+                seeds::star(),
+                [(5, 1, 5, 0), (50, 6, 20, 0), (390, 26, 90, 35)],
+            ),
+        ];
+        for (seed, counts) in expected.iter() {
+            let mut tiles = seed.clone().into_pentagon_tiles();
+            for &(pentagons, stars, boats, diamonds) in counts {
+                tiles = generate_tiling(tiles, 1);
+                assert_eq!(count(&tiles, &PENTAGONS), pentagons);
+                assert_eq!(count(&tiles, &[TileType::Star]), stars);
+                assert_eq!(count(&tiles, &[TileType::Boat]), boats);
+                assert_eq!(count(&tiles, &[TileType::Diamond]), diamonds);
+            }
+        }
+    }
+
+    #[test]
+    fn test_tiles_fit() {
+        for seed in [seeds::pentagon(), seeds::star()].iter() {
+            let tiles = generate_tiling(seed.clone().into_pentagon_tiles(), 4);
+
+            // All tiles must have the shape given by their type, with sides phi^8 times shorter
+            // than the seed's, so the squares of their lengths are phi^16 times smaller
+            let side = (0..16).fold(Golden::from(25), |side, _| side.div_phi());
+            for t in &tiles {
+                let n = t.vertices.len();
+                assert_eq!(
+                    t,
+                    &PentagonTile::new(t.tile_type, t.vertices[0], t.vertices[1] - t.vertices[0])
+                );
+                for i in 0..n {
+                    assert_eq!(
+                        (t.vertices[(i + 1) % n] - t.vertices[i]).norm_squared(),
+                        side
+                    );
+                }
+            }
+
+            // If the tiles don't overlap or leave gaps, the angles around every vertex far from the
+            // boundary add up to 360 degrees
+            let mut angles = HashMap::new();
+            for t in &tiles {
+                let corners = corner_angles(t.tile_type);
+                for (v, angle) in t.vertices.iter().zip(corners) {
+                    *angles.entry(*v).or_insert(0) += angle;
+                }
+            }
+            let interior: Vec<_> = angles
+                .iter()
+                .filter(|(v, _)| v.to_point().distance_to(Point::ZERO) < 1.0)
+                .collect();
+            assert!(interior.len() > 100);
+            assert!(interior.iter().all(|(_, &angle)| angle == 10));
+        }
+    }
+
+    #[test]
+    fn test_pentagon_classes() {
+        let tiles = generate_tiling(seeds::star().into_pentagon_tiles(), 4);
+        let mut pentagon_sides = HashMap::new();
+        for t in tiles.iter().filter(|t| is_pentagon(t.tile_type)) {
+            for i in 0..5 {
+                let side = t.vertices[i] + t.vertices[(i + 1) % 5];
+                *pentagon_sides.entry(side).or_insert(0) += 1;
+            }
+        }
+
+        // Away from the boundary, the sides that every pentagon shares with other pentagons are
+        // exactly the ones its class gives, and every class is found
+        let interior: Vec<_> = tiles
+            .iter()
+            .filter(|t| is_pentagon(t.tile_type))
+            .filter(|t| t.vertices[0].to_point().distance_to(Point::ZERO) < 2.0)
+            .collect();
+        for t in &interior {
+            for (i, &shared) in shared_sides(t.tile_type).iter().enumerate() {
+                let side = t.vertices[i] + t.vertices[(i + 1) % 5];
+                assert_eq!(pentagon_sides[&side] == 2, shared);
+            }
+        }
+        for &tile_type in &PENTAGONS {
+            assert!(interior.iter().any(|t| t.tile_type == tile_type));
+        }
+    }
+
+    #[test]
+    fn test_clipping() {
+        let bounds = Rectangle {
+            min: Point(-1.0, 0.5),
+            max: Point(0.5, 2.0),
+        };
+        let seed = seeds::star().into_pentagon_tiles();
+        let all = generate_tiling(seed.clone(), 4);
+        let clipped: HashSet<_> = generate_tiling_clipped(seed, 4, bounds)
+            .into_iter()
+            .collect();
+        assert!(clipped.len() < all.len());
+
+        // Every tile that intersects the bounds must be in the clipped tiling, with the same class
+        for t in all {
+            let vertices: Vec<_> = t.vertices.iter().map(|v| v.to_point()).collect();
+            if bounds.intersects(&Rectangle::bounding(&vertices)) {
+                assert!(clipped.contains(&t));
+            }
+        }
+    }
+}
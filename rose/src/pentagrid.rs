@@ -0,0 +1,192 @@
+use crate::geometry::*;
+use crate::golden::*;
+use std::ops::RangeInclusive;
+
+// A pentagrid is singular if three or more of its lines meet at a single point. In that case, the
+// region around that point can be filled with rhombuses in more than one way, and the basic
+// algorithm would produce overlapping rhombuses. To avoid that, we always shift the grid offsets by
+// a tiny amount, which makes the grid regular while keeping the tiling the same everywhere except
+// at the singular points, where it picks one of the valid fillings. The perturbation must have
+// every component nonzero, and must add up to zero so it doesn't change the sum of the offsets.
+const PERTURBATION: [f64; 5] = [1.0, -2.0, 3.0, -4.0, 2.0];
+const PERTURBATION_SCALE: f64 = 1e-9;
+
+// How far a rhombus vertex can be from the point its grid intersection maps to. The vertex is at
+// most the sum of a subset of the grid directions away from it, plus the rhombus diagonal, so this
+// is a comfortable upper bound.
+const MARGIN: f64 = 3.0;
+
+/// The unit vector normal to the lines in the `i`-th family of the pentagrid.
+pub fn grid_direction(i: usize) -> Point {
+    Point(1.0, 0.0).rotate(72.0 * i as f64)
+}
+
+/// The same as `grid_direction`, but represented exactly.
+pub fn exact_grid_direction(i: usize) -> GoldenPoint {
+    GoldenPoint::ONE.rotate_steps(2 * i as i64)
+}
+
+/// Shifts the offsets by a tiny amount, so that the pentagrid they describe is never singular.
+pub fn regularize(offsets: [f64; 5]) -> [f64; 5] {
+    let mut offsets = offsets;
+    for (o, p) in offsets.iter_mut().zip(PERTURBATION.iter()) {
+        *o += p * PERTURBATION_SCALE;
+    }
+    offsets
+}
+
+/// Finds the region of the pentagrid whose rhombuses may intersect `bounds` in the tiling. Returns
+/// that region, together with the range of grid indices that each family of lines takes in it.
+pub fn grid_region(offsets: &[f64; 5], bounds: Rectangle) -> (Rectangle, Vec<RangeInclusive<i64>>) {
+    // A point `x` in the grid maps to a point in the tiling that is roughly `2.5 * x + shift`. We
+    // use that to find the region of the grid that maps to the bounds
+    let shift = (0..5).fold(Point::ZERO, |acc, i| acc + offsets[i] * grid_direction(i));
+    let region = {
+        let Rectangle { min, max } = bounds.expand(MARGIN);
+        Rectangle {
+            min: (min - shift) / 2.5,
+            max: (max - shift) / 2.5,
+        }
+    };
+
+    // For each family, find the range of lines that cross the grid region
+    let ranges = (0..5)
+        .map(|i| {
+            let values = region
+                .corners()
+                .iter()
+                .map(|p| p.dot(grid_direction(i)) + offsets[i])
+                .collect::<Vec<_>>();
+            let min = values.iter().cloned().fold(f64::INFINITY, f64::min);
+            let max = values.iter().cloned().fold(f64::NEG_INFINITY, f64::max);
+            min.floor() as i64..=max.ceil() as i64
+        })
+        .collect();
+    (region, ranges)
+}
+
+/// Generates a Penrose rhombus tiling using de Bruijn's pentagrid method, and returns every
+/// rhombus whose bounding box intersects `bounds`. The rhombuses have unit side length.
+///
+/// The pentagrid is made of five families of parallel lines, where the `i`-th family is the set of
+/// points `x` such that `x · e_i + offsets[i]` is an integer, and `e_i` is the unit vector at
+/// `72 * i` degrees. Every intersection of two lines becomes a rhombus in the tiling. The result is
+/// only an actual Penrose tiling if the offsets add up to an integer, usually zero. Offsets that
+/// make three or more lines meet at a single point, like all zeros, result in singular tilings,
+/// such as the "cartwheel" tiling.
+pub fn generate_tiling(offsets: [f64; 5], bounds: Rectangle) -> Vec<Quadrilateral> {
+    let sum = offsets.iter().sum::<f64>().round() as i64;
+    let offsets = regularize(offsets);
+    let directions: Vec<_> = (0..5).map(grid_direction).collect();
+    let exact_directions: Vec<_> = (0..5).map(exact_grid_direction).collect();
+    let (grid_bounds, line_ranges) = grid_region(&offsets, bounds);
+
+    let mut result = Vec::new();
+    for j in 0..5 {
+        for k in j + 1..5 {
+            let (e_j, e_k) = (directions[j], directions[k]);
+            let det = e_j.cross(e_k);
+            for n_j in line_ranges[j].clone() {
+                for n_k in line_ranges[k].clone() {
+                    // Find the intersection between the two lines by solving the linear system
+                    //   x · e_j == n_j - offsets[j]
+                    //   x · e_k == n_k - offsets[k]
+                    let (r_j, r_k) = (n_j as f64 - offsets[j], n_k as f64 - offsets[k]);
+                    let x = Point(
+                        (r_j * e_k.1 - r_k * e_j.1) / det,
+                        (e_j.0 * r_k - e_k.0 * r_j) / det,
+                    );
+                    if !grid_bounds.contains(x) {
+                        continue;
+                    }
+
+                    // Each point in the grid is mapped to the tiling by taking the index of the
+                    // grid cell it's in, that is, the ceiling of `x · e_i + offsets[i]` for each
+                    // family. The intersection is on a line of family j and a line of family k, so
+                    // the four cells around it give the four vertices of the rhombus.
+                    let indices: Vec<_> = (0..5)
+                        .map(|i| match i {
+                            _ if i == j => n_j,
+                            _ if i == k => n_k,
+                            _ => (x.dot(directions[i]) + offsets[i]).ceil() as i64,
+                        })
+                        .collect();
+                    let vertex = indices
+                        .iter()
+                        .zip(exact_directions.iter())
+                        .fold(GoldenPoint::ZERO, |acc, (&n, &e)| acc + Golden::from(n) * e);
+                    let quad = make_rhombus(
+                        vertex,
+                        exact_directions[j],
+                        exact_directions[k],
+                        indices.iter().sum::<i64>() - sum,
+                    );
+
+                    let points = [quad.a, quad.b, quad.c, quad.d].map(GoldenPoint::to_point);
+                    if Rectangle::bounding(&points).intersects(&bounds) {
+                        result.push(quad);
+                    }
+                }
+            }
+        }
+    }
+    result
+}
+
+/// Creates the rhombus with vertices `v`, `v + e_j`, `v + e_k` and `v + e_j + e_k`, where `index` is
+/// the sum of the grid indices of `v`, minus the sum of the offsets.
+pub fn make_rhombus(
+    v: GoldenPoint,
+    e_j: GoldenPoint,
+    e_k: GoldenPoint,
+    index: i64,
+) -> Quadrilateral {
+    // The vertices `v` and `v + e_j + e_k` are on the rhombus' long diagonal if it's a large
+    // rhombus, or on its short diagonal if it's a small rhombus. Either way, these are the vertices
+    // that correspond to `a` and `c` in the Robinson triangles. When the offsets add up to zero,
+    // every vertex index is between 1 and 4, so `index` is either 1 or 2. It is the index that
+    // determines which of the two vertices gets which matching arc.
+    let (near, far) = (v, v + e_j + e_k);
+    let (a, c) = if index == 2 { (near, far) } else { (far, near) };
+    Quadrilateral {
+        a,
+        b: v + e_j,
+        c,
+        d: v + e_k,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::validate::validate;
+
+    fn test_bounds() -> Rectangle {
+        Rectangle {
+            min: Point(-15.0, -10.0),
+            max: Point(15.0, 10.0),
+        }
+    }
+
+    #[test]
+    fn test_matching_rules() {
+        let offsets = [
+            [0.1, 0.2, -0.3, 0.15, -0.15],
+            [0.2, 0.2, 0.2, 0.2, -0.8],
+            // All zeros is a singular pentagrid, that results in a cartwheel tiling
+            [0.0, 0.0, 0.0, 0.0, 0.0],
+        ];
+        for &o in &offsets {
+            let quads = generate_tiling(o, test_bounds());
+            assert!(!quads.is_empty());
+
+            // Make sure no rhombus was generated twice
+            let mut centers: Vec<_> = quads.iter().map(|q| q.a + q.c).collect();
+            centers.sort();
+            centers.dedup();
+            assert_eq!(centers.len(), quads.len());
+
+            assert_eq!(validate(&quads), vec![]);
+        }
+    }
+}
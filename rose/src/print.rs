@@ -0,0 +1,491 @@
+use crate::geometry::*;
+use crate::render::{Backend, Color, Group, GroupKind, Scene, Tile};
+
+use std::io::{self, Write};
+
+// Like in the SVG, the coordinates are written as integers, in thousandths of a view box unit. The
+// conversion to the page coordinates is done by the transformation matrix of the document
+const SCALING_FACTOR: f64 = 1000.0;
+
+pub const POINTS_PER_INCH: f64 = 72.0;
+pub const POINTS_PER_MM: f64 = POINTS_PER_INCH / 25.4;
+
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum PrintFormat {
+    Pdf,
+    Eps,
+}
+
+/// The physical dimensions of the page. All lengths are in PostScript points, that is, 1/72 of an
+/// inch.
+#[derive(Debug, Copy, Clone)]
+pub struct PageConfig {
+    pub width: f64,
+    pub height: f64,
+    pub margin: f64,
+}
+
+/// Parses a length with a unit, like "10mm", "2.5cm", "8.5in" or "12pt", and returns it in points.
+pub fn parse_length(s: &str) -> Result<f64, String> {
+    let s = s.trim();
+    let units = [
+        ("mm", POINTS_PER_MM),
+        ("cm", 10.0 * POINTS_PER_MM),
+        ("in", POINTS_PER_INCH),
+        ("pt", 1.0),
+    ];
+    let (number, factor) = units
+        .iter()
+        .find_map(|&(unit, factor)| s.strip_suffix(unit).map(|n| (n, factor)))
+        .ok_or_else(|| format!("length \"{}\" must have a unit (mm, cm, in or pt)", s))?;
+    let value: f64 = number
+        .trim()
+        .parse()
+        .map_err(|_| format!("invalid length: \"{}\"", s))?;
+    if value < 0.0 || !value.is_finite() {
+        return Err(format!("invalid length: \"{}\"", s));
+    }
+    Ok(value * factor)
+}
+
+/// Parses a page size, and returns its width and height in points. The size can be the name of a
+/// common paper size, like "a4" or "letter", or the width and height separated by an "x", like
+/// "210mm x 297mm". If the unit is only given once, like in "8.5x11in", it applies to both.
+pub fn parse_page_size(s: &str) -> Result<(f64, f64), String> {
+    let named = match s.to_ascii_lowercase().as_str() {
+        "a3" => Some(("297mm", "420mm")),
+        "a4" => Some(("210mm", "297mm")),
+        "a5" => Some(("148mm", "210mm")),
+        "letter" => Some(("8.5in", "11in")),
+        "legal" => Some(("8.5in", "14in")),
+        "tabloid" => Some(("11in", "17in")),
+        _ => None,
+    };
+    if let Some((width, height)) = named {
+        return Ok((parse_length(width)?, parse_length(height)?));
+    }
+
+    let mut parts = s.splitn(2, 'x');
+    let (width, height) = match (parts.next(), parts.next()) {
+        (Some(w), Some(h)) => (w.trim(), h.trim()),
+        _ => return Err(format!("invalid page size: \"{}\"", s)),
+    };
+    // If the width has no unit, use the same unit as the height
+    let unit = height.trim_start_matches(|c: char| !c.is_ascii_alphabetic());
+    let width = parse_length(width)
+        .or_else(|e| parse_length(&format!("{}{}", width, unit)).map_err(|_| e))?;
+    Ok((width, parse_length(height)?))
+}
+
+/// Writes the scene as a PDF or EPS document, with the same appearance as the SVG. The view box is
+/// scaled to cover the area of the page inside the margins, and the excess is cropped, in the same
+/// way as the SVG is displayed. Since neither format supports transparency in a simple way, the
+/// alpha component of the colors is ignored. Like `SvgBackend`, every element is written as soon
+/// as it's drawn.
+pub struct PrintBackend<W> {
+    format: PrintFormat,
+    page: PageConfig,
+    out: CountingWriter<W>,
+
+    // Where the content stream starts in a PDF document
+    stream_start: usize,
+
+    // The arcs and outlines change the stroke color, and the outlines change the line width, so
+    // they must be restored before drawing the markers and after drawing the outlines
+    stroke_color: String,
+    line_width: i64,
+}
+
+impl<W: io::Write> PrintBackend<W> {
+    /// Returns an error if the margins don't leave any space in the page.
+    pub fn new(page: PageConfig, format: PrintFormat, out: W) -> io::Result<Self> {
+        if page.width <= 2.0 * page.margin || page.height <= 2.0 * page.margin {
+            return Err(io::Error::new(
+                io::ErrorKind::InvalidInput,
+                "the margins are larger than the page",
+            ));
+        }
+        Ok(PrintBackend {
+            format,
+            page,
+            out: CountingWriter::new(out),
+            stream_start: 0,
+            stroke_color: String::new(),
+            line_width: 0,
+        })
+    }
+
+    fn write_point(&mut self, Point(x, y): Point, operator: &str) -> io::Result<()> {
+        write!(
+            self.out,
+            "{} {} {} ",
+            scale_float(x),
+            scale_float(y),
+            operator
+        )
+    }
+
+    // Continues the current path with the arc, which must start at the current point
+    fn write_arc(&mut self, arc: Arc) -> io::Result<()> {
+        for [c1, c2, end] in arc_to_cubics(arc) {
+            for &Point(x, y) in &[c1, c2] {
+                write!(self.out, "{} {} ", scale_float(x), scale_float(y))?;
+            }
+            self.write_point(end, "c")?;
+        }
+        Ok(())
+    }
+}
+
+impl<W: io::Write> Backend for PrintBackend<W> {
+    fn begin(&mut self, scene: &Scene) -> io::Result<()> {
+        let PageConfig {
+            width,
+            height,
+            margin,
+        } = self.page;
+        let (inner_width, inner_height) = (width - 2.0 * margin, height - 2.0 * margin);
+        let (view_box_width, view_box_height) =
+            (scene.view_box_width as f64, scene.view_box_height as f64);
+        let scale = f64::max(inner_width / view_box_width, inner_height / view_box_height);
+
+        // The y axis points down in the view box, but up in the page
+        let clip = format!("{} {} {} {}", margin, margin, inner_width, inner_height);
+        let matrix = format!(
+            "{} 0 0 {} {} {}",
+            scale / SCALING_FACTOR,
+            -scale / SCALING_FACTOR,
+            margin + (inner_width - view_box_width * scale) / 2.0,
+            height - margin - (inner_height - view_box_height * scale) / 2.0,
+        );
+        let line_width = scale_float(scene.stroke_width);
+        let stroke_color = print_color(scene.stroke_color);
+        self.stroke_color = stroke_color.clone();
+        self.line_width = line_width;
+        let out = &mut self.out;
+        match self.format {
+            PrintFormat::Pdf => {
+                // The page dictionary refers to the content stream, which refers to its length,
+                // which is only known after the content is written. Because of that, the length is
+                // written as a separate object at the end. The second line of the header marks the
+                // file as binary, with four bytes above 127
+                out.write_all(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n")?;
+                out.mark_object();
+                write!(out, "1 0 obj\n<< /Type /Catalog /Pages 2 0 R >>\nendobj\n")?;
+                out.mark_object();
+                write!(
+                    out,
+                    "2 0 obj\n<< /Type /Pages /Kids [3 0 R] /Count 1 >>\nendobj\n"
+                )?;
+                out.mark_object();
+                write!(
+                    out,
+                    "3 0 obj\n<< /Type /Page /Parent 2 0 R /MediaBox [0 0 {} {}] /Contents 4 0 R \
+                    /Resources << >> >>\nendobj\n",
+                    width, height,
+                )?;
+                out.mark_object();
+                write!(out, "4 0 obj\n<< /Length 5 0 R >>\nstream\n")?;
+                self.stream_start = out.count;
+                write!(
+                    out,
+                    "q\n{} re W n\n{} cm\n{} w 1 J 1 j\n{} RG\n",
+                    clip, matrix, line_width, stroke_color,
+                )
+            }
+            PrintFormat::Eps => {
+                write!(
+                    out,
+                    "%!PS-Adobe-3.0 EPSF-3.0\n%%BoundingBox: 0 0 {} {}\n\
+                    %%HiResBoundingBox: 0 0 {} {}\n%%Creator: rose\n%%EndComments\n",
+                    width.ceil(),
+                    height.ceil(),
+                    width,
+                    height,
+                )?;
+
+                // Short names for the operators, so that they are the same as in PDF
+                write!(
+                    out,
+                    "/m {{ moveto }} bind def\n/l {{ lineto }} bind def\n\
+                    /c {{ curveto }} bind def\n/h {{ closepath }} bind def\n\
+                    /fs {{ gsave fc setrgbcolor fill grestore sc setrgbcolor stroke }} bind def\n\
+                    /st {{ sc setrgbcolor stroke }} bind def\n/sc {{ {} }} def\n",
+                    stroke_color,
+                )?;
+                write!(
+                    out,
+                    "gsave\nnewpath {} rectclip\n[{}] concat\n\
+                    {} setlinewidth 1 setlinecap 1 setlinejoin\n",
+                    clip, matrix, line_width,
+                )
+            }
+        }
+    }
+
+    fn begin_group(&mut self, group: &Group) -> io::Result<()> {
+        // Since there are no groups in these formats, we only set the color used by the elements
+        // that follow
+        let color = print_color(group.color);
+        match (self.format, &group.kind) {
+            (PrintFormat::Pdf, GroupKind::Polygons(_)) => writeln!(self.out, "{} rg", color),
+            (PrintFormat::Pdf, GroupKind::Markers { .. }) => {
+                writeln!(self.out, "{} rg {} RG", color, self.stroke_color)
+            }
+            (PrintFormat::Pdf, GroupKind::Outlines { width, .. }) => {
+                writeln!(self.out, "{} RG {} w", color, scale_float(*width))
+            }
+            (PrintFormat::Pdf, _) => writeln!(self.out, "{} RG", color),
+            (PrintFormat::Eps, GroupKind::Polygons(_)) => {
+                writeln!(self.out, "/fc {{ {} }} def", color)
+            }
+            (PrintFormat::Eps, GroupKind::Markers { .. }) => writeln!(
+                self.out,
+                "/fc {{ {} }} def /sc {{ {} }} def",
+                color, self.stroke_color
+            ),
+            (PrintFormat::Eps, GroupKind::Outlines { width, .. }) => writeln!(
+                self.out,
+                "/sc {{ {} }} def {} setlinewidth",
+                color,
+                scale_float(*width)
+            ),
+            (PrintFormat::Eps, _) => writeln!(self.out, "/sc {{ {} }} def", color),
+        }
+    }
+
+    fn draw_polygon(&mut self, tile: &Tile) -> io::Result<()> {
+        for (i, &p) in tile.vertices.iter().enumerate() {
+            self.write_point(p, if i == 0 { "m" } else { "l" })?;
+        }
+        match self.format {
+            PrintFormat::Pdf => writeln!(self.out, "h B"),
+            PrintFormat::Eps => writeln!(self.out, "h fs"),
+        }
+    }
+
+    fn draw_arc(&mut self, arc: Arc) -> io::Result<()> {
+        self.write_point(arc.0, "m")?;
+        self.write_arc(arc)?;
+        match self.format {
+            PrintFormat::Pdf => writeln!(self.out, "S"),
+            PrintFormat::Eps => writeln!(self.out, "st"),
+        }
+    }
+
+    fn draw_marker(&mut self, center: Point, radius: f64) -> io::Result<()> {
+        // The circle is drawn as four quarter arcs, since a single arc can't describe it
+        let corners = [
+            Point(radius, 0.0),
+            Point(0.0, radius),
+            Point(-radius, 0.0),
+            Point(0.0, -radius),
+        ];
+        self.write_point(center + corners[0], "m")?;
+        for i in 0..4 {
+            let (start, end) = (center + corners[i], center + corners[(i + 1) % 4]);
+            self.write_arc((start, center, end, false))?;
+        }
+        match self.format {
+            PrintFormat::Pdf => writeln!(self.out, "h B"),
+            PrintFormat::Eps => writeln!(self.out, "h fs"),
+        }
+    }
+
+    fn draw_line(&mut self, start: Point, end: Point) -> io::Result<()> {
+        self.write_point(start, "m")?;
+        self.write_point(end, "l")?;
+        match self.format {
+            PrintFormat::Pdf => writeln!(self.out, "S"),
+            PrintFormat::Eps => writeln!(self.out, "st"),
+        }
+    }
+
+    fn end_group(&mut self, group: &Group) -> io::Result<()> {
+        match (self.format, &group.kind) {
+            (PrintFormat::Pdf, GroupKind::Outlines { .. }) => {
+                writeln!(self.out, "{} w", self.line_width)
+            }
+            (PrintFormat::Eps, GroupKind::Outlines { .. }) => {
+                writeln!(self.out, "{} setlinewidth", self.line_width)
+            }
+            _ => Ok(()),
+        }
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let out = &mut self.out;
+        match self.format {
+            PrintFormat::Pdf => {
+                writeln!(out, "Q")?;
+                let stream_length = out.count - self.stream_start;
+                write!(out, "endstream\nendobj\n")?;
+                out.mark_object();
+                write!(out, "5 0 obj\n{}\nendobj\n", stream_length)?;
+
+                let xref_start = out.count;
+                write!(out, "xref\n0 6\n0000000000 65535 f \n")?;
+
+                // The offsets are borrowed from the writer, so the rest is written directly to the
+                // inner writer, since nothing else needs to be counted
+                for offset in &out.objects {
+                    writeln!(out.inner, "{:010} 00000 n ", offset)?;
+                }
+                write!(
+                    out.inner,
+                    "trailer\n<< /Size 6 /Root 1 0 R >>\nstartxref\n{}\n%%EOF\n",
+                    xref_start,
+                )?;
+            }
+            PrintFormat::Eps => write!(out, "grestore\nshowpage\n%%EOF\n")?,
+        }
+        out.flush()
+    }
+}
+
+fn scale_float(x: f64) -> i64 {
+    (x * SCALING_FACTOR) as i64
+}
+
+// Writes the color as three numbers between 0 and 1, which is how both formats expect them
+fn print_color(color: Color) -> String {
+    let [r, g, b, _] = color.rgba;
+    let component = |c: u8| format!("{:.3}", c as f64 / 255.0);
+    format!("{} {} {}", component(r), component(g), component(b))
+}
+
+// Keeps track of how many bytes were written, and of where each PDF object starts, which is needed
+// for the PDF cross-reference table
+struct CountingWriter<W> {
+    inner: W,
+    count: usize,
+    objects: Vec<usize>,
+}
+
+impl<W: io::Write> CountingWriter<W> {
+    fn new(inner: W) -> Self {
+        CountingWriter {
+            inner,
+            count: 0,
+            objects: Vec::new(),
+        }
+    }
+
+    fn mark_object(&mut self) {
+        self.objects.push(self.count);
+    }
+}
+
+impl<W: io::Write> io::Write for CountingWriter<W> {
+    fn write(&mut self, buf: &[u8]) -> io::Result<usize> {
+        let written = self.inner.write(buf)?;
+        self.count += written;
+        Ok(written)
+    }
+
+    fn flush(&mut self) -> io::Result<()> {
+        self.inner.flush()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::RenderConfig;
+
+    fn test_document(format: PrintFormat) -> Vec<u8> {
+        let seed = crate::seeds::rose();
+        let scene = Scene::new(&RenderConfig {
+            placement: seed.placement(Point(50.0, 50.0), 50.0),
+            view_box_width: 100,
+            view_box_height: 100,
+            stroke_width: 1.0,
+            stroke_color: "white",
+            quad_colors: ("red", "#0000ff"),
+            arc_colors: Some(("green", "yellow")),
+        })
+        .unwrap();
+        let page = PageConfig {
+            width: 200.0,
+            height: 300.0,
+            margin: 10.0,
+        };
+        let quads =
+            crate::tiling::merge_pairs(crate::tiling::generate_tiling(seed.into_triangles(), 3));
+        let mut backend = PrintBackend::new(page, format, Vec::new()).unwrap();
+        scene.render(|| quads.iter(), &mut backend).unwrap();
+        backend.out.inner
+    }
+
+    #[test]
+    fn test_parse_page_size() {
+        let assert_size = |s, (width, height): (f64, f64)| {
+            let (w, h) = parse_page_size(s).unwrap();
+            assert!((w - width).abs() < 1e-9 && (h - height).abs() < 1e-9);
+        };
+        assert_size("a4", (210.0 * POINTS_PER_MM, 297.0 * POINTS_PER_MM));
+        assert_size("Letter", (612.0, 792.0));
+        assert_size("8.5x11in", (612.0, 792.0));
+        assert_size("10cm x 50mm", (100.0 * POINTS_PER_MM, 50.0 * POINTS_PER_MM));
+        assert_size("100 x 200pt", (100.0, 200.0));
+        for &invalid in &["", "x", "a4x", "10", "10x20", "-10x20mm", "10mmx20ft"] {
+            assert!(parse_page_size(invalid).is_err());
+        }
+    }
+
+    #[test]
+    fn test_pdf_structure() {
+        let pdf = test_document(PrintFormat::Pdf);
+        assert!(pdf.starts_with(b"%PDF-1.4\n%\xe2\xe3\xcf\xd3\n"));
+
+        // The bytes that aren't ASCII are replaced one by one, so that the offsets are kept
+        let text: String = pdf
+            .iter()
+            .map(|&b| if b.is_ascii() { b as char } else { '?' })
+            .collect();
+        let position = |pattern: &str| text.find(pattern).unwrap();
+
+        // Every entry in the cross-reference table must point to the start of its object
+        let xref = position("xref\n");
+        let offsets: Vec<usize> = text[xref..]
+            .lines()
+            .filter(|line| line.ends_with(" 00000 n "))
+            .map(|line| line[..10].parse().unwrap())
+            .collect();
+        assert_eq!(offsets.len(), 5);
+        for (i, &offset) in offsets.iter().enumerate() {
+            assert!(text[offset..].starts_with(&format!("{} 0 obj\n", i + 1)));
+        }
+        assert!(text.ends_with(&format!("startxref\n{}\n%%EOF\n", xref)));
+
+        // The length of the content stream is given by the last object
+        let stream_start = position("stream\n") + "stream\n".len();
+        let stream_length = position("endstream") - stream_start;
+        assert!(text.contains(&format!("5 0 obj\n{}\nendobj", stream_length)));
+        assert!(text.contains("1.000 0.000 0.000 rg"));
+        assert!(text.contains("1.000 1.000 0.000 RG"));
+    }
+
+    #[test]
+    fn test_eps_structure() {
+        let eps = String::from_utf8(test_document(PrintFormat::Eps)).unwrap();
+        assert!(eps.starts_with("%!PS-Adobe-3.0 EPSF-3.0\n%%BoundingBox: 0 0 200 300\n"));
+        assert_eq!(
+            eps.matches("gsave").count(),
+            eps.matches("grestore").count()
+        );
+        assert!(eps.contains(" h fs\n"));
+        assert!(eps.contains(" c st\n"));
+        assert!(eps.ends_with("%%EOF\n"));
+    }
+
+    #[test]
+    fn test_invalid_page() {
+        let page = PageConfig {
+            width: 100.0,
+            height: 100.0,
+            margin: 50.0,
+        };
+        assert!(PrintBackend::new(page, PrintFormat::Pdf, Vec::new()).is_err());
+    }
+}
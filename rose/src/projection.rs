@@ -0,0 +1,249 @@
+use crate::geometry::*;
+use crate::golden::*;
+use crate::pentagrid::{
+    exact_grid_direction, grid_direction, grid_region, make_rhombus, regularize,
+};
+use std::collections::HashSet;
+
+/// A vertex of a tiling generated by the cut-and-project method.
+#[derive(Debug, Clone)]
+pub struct Vertex {
+    /// The point in the five-dimensional integer lattice that was projected to this vertex.
+    pub lattice_point: [i64; 5],
+
+    /// The index of the vertex, which is the sum of the coordinates of the lattice point minus the
+    /// sum of the offsets. It is always between 1 and 4, and determines which acceptance window
+    /// the vertex was tested against.
+    pub index: i64,
+
+    /// The projection of the lattice point onto the physical plane, that is, the position of the
+    /// vertex in the tiling.
+    pub position: GoldenPoint,
+
+    /// The projection of the lattice point onto the internal space, shifted by the projection of
+    /// the offsets. This is always inside the acceptance window for the vertex index.
+    pub internal: Point,
+}
+
+pub struct ProjectedTiling {
+    pub vertices: Vec<Vertex>,
+    pub rhombuses: Vec<Quadrilateral>,
+}
+
+/// Returns true if `internal` is in the acceptance window for vertices of the given index.
+///
+/// The window for index `k` is the projection onto the internal space of the slice of the unit
+/// hypercube where the coordinates add up to `k`. For indices 1 and 4, that is a regular pentagon
+/// with unit circumradius, and for indices 2 and 3, a pentagon with circumradius φ. The pentagons
+/// for indices 3 and 4 are the ones for 2 and 1 rotated by 180 degrees.
+pub fn in_window(internal: Point, index: i64) -> bool {
+    // The circumradius of the pentagon, and the angle of its first vertex
+    let (radius, angle) = match index {
+        1 => (1.0, 0.0),
+        2 => (PHI, 36.0),
+        3 => (PHI, 0.0),
+        4 => (1.0, 36.0),
+        _ => return false,
+    };
+
+    // A point is inside a regular pentagon if its projection on the normal of each side is at most
+    // the pentagon's inradius. The normals point to the middle of each side, halfway between two
+    // vertices
+    let inradius = radius * f64::cos(36.0 * DEG_TO_RAD);
+    (0..5).all(|i| {
+        let normal = Point(1.0, 0.0).rotate(angle + 36.0 + 72.0 * i as f64);
+        internal.dot(normal) <= inradius
+    })
+}
+
+/// Generates a Penrose rhombus tiling using the cut-and-project method, and returns every vertex
+/// inside `bounds`, together with every rhombus whose bounding box intersects `bounds`. The
+/// rhombuses have unit side length.
+///
+/// Each point `n` in the five-dimensional integer lattice is projected onto the physical plane as
+/// `n_0 e_0 + ... + n_4 e_4`, where `e_i` is the unit vector at `72 * i` degrees, and onto the
+/// internal space by replacing each `e_i` with `e_2i`. The point is a vertex of the tiling if its
+/// internal projection, shifted by the projection of the offsets, lies inside the acceptance
+/// window for its index. Two vertices are connected by an edge if their lattice points differ by a
+/// unit vector.
+///
+/// The offsets have the same meaning as in `pentagrid::generate_tiling`, and both methods result
+/// in the same tiling for the same offsets.
+pub fn generate_tiling(offsets: [f64; 5], bounds: Rectangle) -> ProjectedTiling {
+    let sum = offsets.iter().sum::<f64>().round() as i64;
+    let offsets = regularize(offsets);
+    let internal_shift = (0..5).fold(Point::ZERO, |acc, i| {
+        acc + offsets[i] * grid_direction(2 * i % 5)
+    });
+
+    // Every vertex whose rhombuses could intersect the bounds has coordinates in these ranges
+    let (_, ranges) = grid_region(&offsets, bounds);
+
+    let mut vertices = Vec::new();
+    let mut lattice_points = HashSet::new();
+    for n_0 in ranges[0].clone() {
+        for n_1 in ranges[1].clone() {
+            for n_2 in ranges[2].clone() {
+                for n_3 in ranges[3].clone() {
+                    // Since the index must be between 1 and 4, the last coordinate can only take
+                    // four values
+                    for index in 1..=4 {
+                        let n_4 = sum + index - (n_0 + n_1 + n_2 + n_3);
+                        if !ranges[4].contains(&n_4) {
+                            continue;
+                        }
+                        let lattice_point = [n_0, n_1, n_2, n_3, n_4];
+                        let position = project(lattice_point);
+                        let internal = position.star().to_point() - internal_shift;
+                        if in_window(internal, index) {
+                            lattice_points.insert(lattice_point);
+                            vertices.push(Vertex {
+                                lattice_point,
+                                index,
+                                position,
+                                internal,
+                            });
+                        }
+                    }
+                }
+            }
+        }
+    }
+
+    // Every rhombus is the projection of a face of the lattice, that is, a set of four lattice
+    // points of the form `n`, `n + u_j`, `n + u_k` and `n + u_j + u_k`, where `u_j` and `u_k` are
+    // unit vectors. A face becomes a rhombus when all of its vertices are in the tiling
+    let mut rhombuses = Vec::new();
+    for v in &vertices {
+        for j in 0..5 {
+            for k in j + 1..5 {
+                let step = |mut n: [i64; 5], i: usize| {
+                    n[i] += 1;
+                    n
+                };
+                let n = v.lattice_point;
+                let face = [step(n, j), step(n, k), step(step(n, j), k)];
+                if face.iter().all(|n| lattice_points.contains(n)) {
+                    let quad = make_rhombus(
+                        v.position,
+                        exact_grid_direction(j),
+                        exact_grid_direction(k),
+                        v.index,
+                    );
+                    let points = [quad.a, quad.b, quad.c, quad.d].map(GoldenPoint::to_point);
+                    if Rectangle::bounding(&points).intersects(&bounds) {
+                        rhombuses.push(quad);
+                    }
+                }
+            }
+        }
+    }
+
+    vertices.retain(|v| bounds.contains(v.position.to_point()));
+    ProjectedTiling {
+        vertices,
+        rhombuses,
+    }
+}
+
+/// Projects a point in the five-dimensional integer lattice onto the physical plane.
+pub fn project(lattice_point: [i64; 5]) -> GoldenPoint {
+    (0..5).fold(GoldenPoint::ZERO, |acc, i| {
+        acc + Golden::from(lattice_point[i]) * exact_grid_direction(i)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sorted_centers(quads: &[Quadrilateral]) -> Vec<GoldenPoint> {
+        // The sum of the vertices is four times the center of the quadrilateral
+        let mut centers: Vec<_> = quads.iter().map(|q| q.a + q.b + q.c + q.d).collect();
+        centers.sort();
+        centers
+    }
+
+    #[test]
+    fn test_pentagrid_equivalence() {
+        let bounds = Rectangle {
+            min: Point(-12.0, -8.0),
+            max: Point(12.0, 8.0),
+        };
+        let offsets = [
+            [0.1, 0.2, -0.3, 0.15, -0.15],
+            [0.2, 0.2, 0.2, 0.2, -0.8],
+            [0.0, 0.0, 0.0, 0.0, 0.0],
+        ];
+        for &o in &offsets {
+            let projected = generate_tiling(o, bounds);
+            let pentagrid = crate::pentagrid::generate_tiling(o, bounds);
+            assert_eq!(
+                sorted_centers(&projected.rhombuses),
+                sorted_centers(&pentagrid)
+            );
+
+            // The tiles must also have the same orientation, so that their matching arcs agree
+            let mut projected_arcs: Vec<_> =
+                projected.rhombuses.iter().map(|q| (q.a, q.c)).collect();
+            let mut pentagrid_arcs: Vec<_> = pentagrid.iter().map(|q| (q.a, q.c)).collect();
+            projected_arcs.sort();
+            pentagrid_arcs.sort();
+            assert_eq!(projected_arcs, pentagrid_arcs);
+
+            for v in &projected.vertices {
+                assert!(in_window(v.internal, v.index));
+                assert_eq!(v.position, project(v.lattice_point));
+            }
+        }
+    }
+
+    #[test]
+    fn test_substitution_equivalence() {
+        // Decomposing the rose seed for six generations, or the pizza seed for seven, results in a
+        // patch of the tiling with five-fold symmetry that is generated by these offsets
+        let cases = [(crate::seeds::rose(), 6), (crate::seeds::pizza(), 7)];
+        let offsets = [0.2; 5];
+        let bounds = Rectangle {
+            min: Point(-15.0, -15.0),
+            max: Point(15.0, 15.0),
+        };
+
+        // Only compare the rhombuses that are completely inside the bounds
+        let inside = |quads: Vec<Quadrilateral>| {
+            quads
+                .into_iter()
+                .filter(|q| {
+                    [q.a, q.b, q.c, q.d]
+                        .iter()
+                        .all(|p| bounds.contains(p.to_point()))
+                })
+                .collect::<Vec<_>>()
+        };
+        for (seed, num_generations) in cases.iter().cloned() {
+            let triangles = crate::tiling::generate_tiling(seed.into_triangles(), num_generations);
+
+            // Scale the rhombuses so they have unit side length
+            let scale = |p: GoldenPoint| (0..num_generations).fold(p, |p, _| p.mul_phi());
+            let substitution = crate::tiling::merge_pairs_hashing(triangles)
+                .into_iter()
+                .map(|q| Quadrilateral {
+                    a: scale(q.a),
+                    b: scale(q.b),
+                    c: scale(q.c),
+                    d: scale(q.d),
+                })
+                .collect();
+            let substitution = inside(substitution);
+            let projected = inside(generate_tiling(offsets, bounds).rhombuses);
+            assert!(!substitution.is_empty());
+            assert_eq!(sorted_centers(&substitution), sorted_centers(&projected));
+
+            let mut substitution_arcs: Vec<_> = substitution.iter().map(|q| (q.a, q.c)).collect();
+            let mut projected_arcs: Vec<_> = projected.iter().map(|q| (q.a, q.c)).collect();
+            substitution_arcs.sort();
+            projected_arcs.sort();
+            assert_eq!(substitution_arcs, projected_arcs);
+        }
+    }
+}
@@ -0,0 +1,222 @@
+use crate::geometry::*;
+use crate::render::{Backend, Color, Group, GroupKind, Scene, Tile};
+
+use std::io;
+use tiny_skia::{
+    FillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, Stroke, Transform as PixelTransform,
+};
+
+/// Renders the scene to an image, with the same appearance as the SVG, and writes it to `out` as a
+/// PNG once everything is drawn.
+pub struct RasterBackend<W> {
+    pixmap: Pixmap,
+    out: W,
+
+    // Maps the view box coordinates to pixel coordinates
+    transform: PixelTransform,
+    stroke: Stroke,
+    stroke_paint: Paint<'static>,
+
+    // The paint for the current group, and the stroke for its outlines, if it has any
+    paint: Paint<'static>,
+    outline_stroke: Stroke,
+}
+
+impl<W: io::Write> RasterBackend<W> {
+    /// Creates a backend for an image of `width` by `height` pixels. The view box of the scene is
+    /// scaled to cover the whole image, in the same way as the SVG is displayed: if their aspect
+    /// ratios are different, the view box is centered and the excess is cropped. Returns an error
+    /// if the image size is invalid.
+    pub fn new(width: u32, height: u32, out: W) -> io::Result<Self> {
+        let pixmap = Pixmap::new(width, height).ok_or_else(|| {
+            io::Error::new(
+                io::ErrorKind::InvalidInput,
+                format!("invalid image size: {}x{}", width, height),
+            )
+        })?;
+        Ok(RasterBackend {
+            pixmap,
+            out,
+            transform: PixelTransform::identity(),
+            stroke: Stroke::default(),
+            stroke_paint: Paint::default(),
+            paint: Paint::default(),
+            outline_stroke: Stroke::default(),
+        })
+    }
+}
+
+impl<W: io::Write> Backend for RasterBackend<W> {
+    fn begin(&mut self, scene: &Scene) -> io::Result<()> {
+        let (width, height) = (self.pixmap.width() as f64, self.pixmap.height() as f64);
+        let (view_box_width, view_box_height) =
+            (scene.view_box_width as f64, scene.view_box_height as f64);
+        let scale = f64::max(width / view_box_width, height / view_box_height);
+        self.transform = PixelTransform::from_row(
+            scale as f32,
+            0.0,
+            0.0,
+            scale as f32,
+            ((width - view_box_width * scale) / 2.0) as f32,
+            ((height - view_box_height * scale) / 2.0) as f32,
+        );
+        self.stroke = Stroke {
+            width: scene.stroke_width as f32,
+            line_cap: LineCap::Round,
+            line_join: LineJoin::Round,
+            ..Stroke::default()
+        };
+        self.stroke_paint = paint(scene.stroke_color);
+        Ok(())
+    }
+
+    fn begin_group(&mut self, group: &Group) -> io::Result<()> {
+        self.paint = paint(group.color);
+        if let GroupKind::Outlines { width, .. } = group.kind {
+            self.outline_stroke = Stroke {
+                width: width as f32,
+                ..self.stroke.clone()
+            };
+        }
+        Ok(())
+    }
+
+    fn draw_polygon(&mut self, tile: &Tile) -> io::Result<()> {
+        let mut builder = PathBuilder::new();
+        for (i, &Point(x, y)) in tile.vertices.iter().enumerate() {
+            if i == 0 {
+                builder.move_to(x as f32, y as f32);
+            } else {
+                builder.line_to(x as f32, y as f32);
+            }
+        }
+        builder.close();
+        if let Some(path) = builder.finish() {
+            self.pixmap
+                .fill_path(&path, &self.paint, FillRule::Winding, self.transform, None);
+            self.pixmap.stroke_path(
+                &path,
+                &self.stroke_paint,
+                &self.stroke,
+                self.transform,
+                None,
+            );
+        }
+        Ok(())
+    }
+
+    fn draw_arc(&mut self, arc: Arc) -> io::Result<()> {
+        let (Point(x, y), ..) = arc;
+        let mut builder = PathBuilder::new();
+        builder.move_to(x as f32, y as f32);
+        for [c1, c2, end] in arc_to_cubics(arc) {
+            builder.cubic_to(
+                c1.0 as f32,
+                c1.1 as f32,
+                c2.0 as f32,
+                c2.1 as f32,
+                end.0 as f32,
+                end.1 as f32,
+            );
+        }
+        if let Some(path) = builder.finish() {
+            self.pixmap
+                .stroke_path(&path, &self.paint, &self.stroke, self.transform, None);
+        }
+        Ok(())
+    }
+
+    fn draw_marker(&mut self, Point(x, y): Point, radius: f64) -> io::Result<()> {
+        if let Some(path) = PathBuilder::from_circle(x as f32, y as f32, radius as f32) {
+            self.pixmap
+                .fill_path(&path, &self.paint, FillRule::Winding, self.transform, None);
+            self.pixmap.stroke_path(
+                &path,
+                &self.stroke_paint,
+                &self.stroke,
+                self.transform,
+                None,
+            );
+        }
+        Ok(())
+    }
+
+    fn draw_line(&mut self, Point(x0, y0): Point, Point(x1, y1): Point) -> io::Result<()> {
+        let mut builder = PathBuilder::new();
+        builder.move_to(x0 as f32, y0 as f32);
+        builder.line_to(x1 as f32, y1 as f32);
+        if let Some(path) = builder.finish() {
+            self.pixmap.stroke_path(
+                &path,
+                &self.paint,
+                &self.outline_stroke,
+                self.transform,
+                None,
+            );
+        }
+        Ok(())
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        let png = self.pixmap.encode_png().map_err(io::Error::other)?;
+        self.out.write_all(&png)?;
+        self.out.flush()
+    }
+}
+
+fn paint(color: Color) -> Paint<'static> {
+    let [r, g, b, a] = color.rgba;
+    let mut paint = Paint::default();
+    paint.set_color_rgba8(r, g, b, a);
+    paint.anti_alias = true;
+    paint
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::render::RenderConfig;
+
+    #[test]
+    fn test_raster_colors() {
+        // A large rhombus with side length 40, centered in the view box. Its long diagonal is
+        // horizontal, from (17.6, 50) to (82.4, 50)
+        let seed = crate::seeds::tile(TileType::LargeRhombus);
+        let scene = Scene::new(&RenderConfig {
+            placement: seed.placement(Point(50.0, 50.0), 40.0),
+            view_box_width: 100,
+            view_box_height: 100,
+            stroke_width: 1.0,
+            stroke_color: "white",
+            quad_colors: ("#ff0000", "rgb(0, 0, 255)"),
+            arc_colors: None,
+        })
+        .unwrap();
+        let quads = crate::tiling::merge_pairs(seed.into_triangles());
+
+        // Render at twice the view box size, so each view box unit is two pixels wide
+        let mut backend = RasterBackend::new(200, 200, Vec::new()).unwrap();
+        scene.render(|| quads.iter(), &mut backend).unwrap();
+        let pixel = |x, y| backend.pixmap.pixel(x, y).unwrap();
+        let center = pixel(100, 100);
+        assert_eq!(
+            (center.red(), center.green(), center.blue(), center.alpha()),
+            (0, 0, 255, 255)
+        );
+        assert_eq!(pixel(5, 5).alpha(), 0);
+        assert_eq!(pixel(100, 40).alpha(), 0);
+
+        // The middle of each side is covered by the stroke
+        for &(x, y) in &[(132, 123), (132, 76), (67, 123), (67, 76)] {
+            let side = pixel(x, y);
+            assert!(side.red() > 240 && side.green() > 240 && side.blue() > 240);
+        }
+        assert_eq!(&backend.out[1..4], b"PNG");
+    }
+
+    #[test]
+    fn test_invalid_size() {
+        assert!(RasterBackend::new(0, 100, Vec::new()).is_err());
+        assert!(RasterBackend::new(100, 0, Vec::new()).is_err());
+    }
+}
@@ -0,0 +1,577 @@
+use crate::config::parse_color;
+use crate::geometry::*;
+use crate::golden::*;
+use crate::monotiles::Orientation;
+
+use std::io;
+
+/// A polygon that can be drawn, like a Robinson triangle or a whole tile.
+pub trait Polygon {
+    type Vertex: IntoTilingVertex;
+
+    fn polygon_type(&self) -> TileType;
+
+    /// The vertices of the polygon, in order, in the tiling coordinates.
+    fn vertices(&self) -> Vec<Self::Vertex>;
+
+    /// The two matching arcs of the polygon, in the tiling coordinates, or `None` if it has no
+    /// matching rules to show.
+    fn arcs(&self) -> Option<(Arc, Arc)>;
+
+    /// The orientation of the polygon, if it's one of the monotiles, which can be colored by their
+    /// orientation instead of their type.
+    fn orientation(&self) -> Option<Orientation> {
+        None
+    }
+}
+
+impl<T: Polygon + ?Sized> Polygon for &T {
+    type Vertex = T::Vertex;
+
+    fn polygon_type(&self) -> TileType {
+        (**self).polygon_type()
+    }
+
+    fn vertices(&self) -> Vec<T::Vertex> {
+        (**self).vertices()
+    }
+
+    fn arcs(&self) -> Option<(Arc, Arc)> {
+        (**self).arcs()
+    }
+
+    fn orientation(&self) -> Option<Orientation> {
+        (**self).orientation()
+    }
+}
+
+impl Polygon for RobinsonTriangle {
+    type Vertex = GoldenPoint;
+
+    fn polygon_type(&self) -> TileType {
+        self.triangle_type
+    }
+
+    fn vertices(&self) -> Vec<GoldenPoint> {
+        vec![self.a, self.b, self.c]
+    }
+
+    fn arcs(&self) -> Option<(Arc, Arc)> {
+        let [a, b, c] = [self.a, self.b, self.c].map(GoldenPoint::to_point);
+        let first_radius = Line(Line(a, b).median(), a).length();
+        let first_arc = (
+            Line(a, b).median(),
+            a,
+            a + first_radius * (c - a).normalized(),
+            false,
+        );
+        let second_radius = Line(Line(c, b).median(), c).length();
+        let second_arc = (
+            Line(c, b).median(),
+            c,
+            c + second_radius * (a - c).normalized(),
+            false,
+        );
+        Some((first_arc, second_arc))
+    }
+}
+
+impl Polygon for Quadrilateral {
+    type Vertex = GoldenPoint;
+
+    fn polygon_type(&self) -> TileType {
+        RobinsonTriangle::infer_triangle_type(self.a, self.b, self.c)
+    }
+
+    fn vertices(&self) -> Vec<GoldenPoint> {
+        vec![self.a, self.b, self.c, self.d]
+    }
+
+    fn arcs(&self) -> Option<(Arc, Arc)> {
+        let [a, b, c, d] = [self.a, self.b, self.c, self.d].map(GoldenPoint::to_point);
+        let first_arc = (Line(a, b).median(), a, Line(a, d).median(), false);
+        let second_arc = (
+            Line(c, b).median(),
+            c,
+            Line(c, d).median(),
+            // Only in the dart tile, the second arc has an angle of more than 180 degrees
+            self.polygon_type() == TileType::Dart,
+        );
+        Some((first_arc, second_arc))
+    }
+}
+
+/// The appearance of the rendered tiling. This is the same for every output format.
+pub struct RenderConfig<'a> {
+    pub placement: Placement,
+    pub view_box_width: u64,
+    pub view_box_height: u64,
+    pub stroke_width: f64,
+    pub stroke_color: &'a str,
+    pub quad_colors: (&'a str, &'a str),
+    pub arc_colors: Option<(&'a str, &'a str)>,
+}
+
+/// A CSS color, together with its red, green, blue and alpha components, so that each backend can
+/// use whichever is more convenient.
+#[derive(Debug, Copy, Clone)]
+pub struct Color<'a> {
+    pub css: &'a str,
+    pub rgba: [u8; 4],
+}
+
+impl<'a> Color<'a> {
+    pub fn parse(css: &'a str) -> io::Result<Self> {
+        Ok(Color {
+            css,
+            rgba: parse_color(css)?,
+        })
+    }
+}
+
+/// The polygons that are drawn in a group.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PolygonFilter {
+    /// The polygons of the given types.
+    Types(Vec<TileType>),
+
+    /// The polygons whose type is filled with the given tile color.
+    QuadColor(QuadColor),
+
+    /// The polygons with the given orientation, which only the monotiles have.
+    Orientation(Orientation),
+}
+
+impl PolygonFilter {
+    pub fn includes<T: Polygon>(&self, polygon: &T) -> bool {
+        match self {
+            PolygonFilter::Types(types) => types.contains(&polygon.polygon_type()),
+            PolygonFilter::QuadColor(color) => polygon.polygon_type().quad_color() == *color,
+            PolygonFilter::Orientation(orientation) => polygon.orientation() == Some(*orientation),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub enum GroupKind {
+    /// The polygons that pass the filter, filled with the group color.
+    Polygons(PolygonFilter),
+
+    /// The first or second matching arc of every polygon that has them, stroked with the group
+    /// color.
+    FirstArcs,
+    SecondArcs,
+
+    /// Circles of the given radius, in view box units, marking points of the tiling, filled with
+    /// the group color. The name identifies the group in formats that support it.
+    Markers {
+        name: String,
+        radius: f64,
+        positions: Vec<GoldenPoint>,
+    },
+
+    /// Line segments of the given width, in view box units, stroked with the group color. The name
+    /// identifies the group in formats that support it.
+    Outlines {
+        name: String,
+        width: f64,
+        lines: Vec<(GoldenPoint, GoldenPoint)>,
+    },
+}
+
+/// A group of elements that are drawn together, with the same color.
+#[derive(Debug, Clone)]
+pub struct Group<'a> {
+    pub kind: GroupKind,
+    pub color: Color<'a>,
+}
+
+/// A vertex of a polygon in the tiling coordinates, both as floating point numbers and as the
+/// coefficients of its exact coordinates, as given by `ExactPoint::coefficients`, if it has them.
+#[derive(Debug, Copy, Clone)]
+pub struct TilingVertex {
+    pub point: Point,
+    pub exact: Option<[[i64; 2]; 2]>,
+}
+
+/// A point that can be a vertex of a polygon. These are usually exact points, but the tiles
+/// generated from user-defined rules only have floating point coordinates.
+pub trait IntoTilingVertex: Copy {
+    fn into_tiling_vertex(self) -> TilingVertex;
+}
+
+impl<P: ExactPoint> IntoTilingVertex for P {
+    fn into_tiling_vertex(self) -> TilingVertex {
+        TilingVertex {
+            point: self.to_point(),
+            exact: Some(self.coefficients()),
+        }
+    }
+}
+
+impl IntoTilingVertex for Point {
+    fn into_tiling_vertex(self) -> TilingVertex {
+        TilingVertex {
+            point: self,
+            exact: None,
+        }
+    }
+}
+
+/// A polygon, as it is passed to the backend.
+#[derive(Debug, Clone)]
+pub struct Tile<'a> {
+    /// The position of the polygon in the sequence of polygons passed to `Scene::render`. Since
+    /// that sequence must be the same for every group, this identifies the polygon across groups.
+    pub id: usize,
+    pub tile_type: TileType,
+
+    /// The vertices, in the view box coordinates.
+    pub vertices: &'a [Point],
+
+    /// The vertices, in the tiling coordinates.
+    pub tiling_vertices: &'a [TilingVertex],
+
+    /// The matching arcs of the polygon, in the view box coordinates, if it has any.
+    pub arcs: Option<(Arc, Arc)>,
+}
+
+/// A description of everything that is drawn, independent of the output format. The groups are
+/// drawn in order, so later groups are drawn over earlier ones.
+#[derive(Debug, Clone)]
+pub struct Scene<'a> {
+    pub placement: Placement,
+    pub view_box_width: u64,
+    pub view_box_height: u64,
+    pub stroke_width: f64,
+
+    /// The color of the outline of every polygon.
+    pub stroke_color: Color<'a>,
+    pub groups: Vec<Group<'a>>,
+}
+
+impl<'a> Scene<'a> {
+    /// Creates the scene described by the config: the polygons, grouped by color, followed by
+    /// their arcs, if the config has arc colors. Returns an error if any of the colors is not a
+    /// valid CSS color.
+    pub fn new(config: &RenderConfig<'a>) -> io::Result<Self> {
+        let mut groups = vec![
+            Group {
+                kind: GroupKind::Polygons(PolygonFilter::QuadColor(QuadColor::First)),
+                color: Color::parse(config.quad_colors.0)?,
+            },
+            Group {
+                kind: GroupKind::Polygons(PolygonFilter::QuadColor(QuadColor::Second)),
+                color: Color::parse(config.quad_colors.1)?,
+            },
+        ];
+        if let Some((first, second)) = config.arc_colors {
+            groups.push(Group {
+                kind: GroupKind::FirstArcs,
+                color: Color::parse(first)?,
+            });
+            groups.push(Group {
+                kind: GroupKind::SecondArcs,
+                color: Color::parse(second)?,
+            });
+        }
+        Ok(Scene {
+            placement: config.placement,
+            view_box_width: config.view_box_width,
+            view_box_height: config.view_box_height,
+            stroke_width: config.stroke_width,
+            stroke_color: Color::parse(config.stroke_color)?,
+            groups,
+        })
+    }
+
+    /// Replaces the groups of polygons with one group for each of the given orientations, so that
+    /// the monotiles are colored by their orientation instead of their type. Polygons with other
+    /// orientations, or without one, aren't drawn.
+    pub fn color_by_orientation(&mut self, colors: &[(Orientation, &'a str)]) -> io::Result<()> {
+        let mut groups = Vec::with_capacity(colors.len() + self.groups.len());
+        for &(orientation, color) in colors {
+            groups.push(Group {
+                kind: GroupKind::Polygons(PolygonFilter::Orientation(orientation)),
+                color: Color::parse(color)?,
+            });
+        }
+        groups.extend(
+            self.groups
+                .drain(..)
+                .filter(|g| !matches!(g.kind, GroupKind::Polygons(_))),
+        );
+        self.groups = groups;
+        Ok(())
+    }
+
+    /// Adds a group of markers, drawn over everything that was added before.
+    pub fn add_markers(
+        &mut self,
+        name: &str,
+        color: &'a str,
+        radius: f64,
+        positions: Vec<GoldenPoint>,
+    ) -> io::Result<()> {
+        self.groups.push(Group {
+            kind: GroupKind::Markers {
+                name: name.to_string(),
+                radius,
+                positions,
+            },
+            color: Color::parse(color)?,
+        });
+        Ok(())
+    }
+
+    /// Adds a group of line segments, drawn over everything that was added before.
+    pub fn add_outlines(
+        &mut self,
+        name: &str,
+        color: &'a str,
+        width: f64,
+        lines: Vec<(GoldenPoint, GoldenPoint)>,
+    ) -> io::Result<()> {
+        self.groups.push(Group {
+            kind: GroupKind::Outlines {
+                name: name.to_string(),
+                width,
+                lines,
+            },
+            color: Color::parse(color)?,
+        });
+        Ok(())
+    }
+
+    /// Draws the polygons generated by `polygons` with the given backend. The polygons are never
+    /// kept in memory, so they can be generated lazily. Since they are drawn one group at a time,
+    /// `polygons` is called once for each group of polygons or arcs, and must return the same
+    /// polygons every time. When the polygons are expensive to generate, as in a deep tiling, the
+    /// cost of drawing the arcs is that of generating the tiling twice more.
+    pub fn render<T, I, B>(&self, polygons: impl Fn() -> I, backend: &mut B) -> io::Result<()>
+    where
+        T: Polygon,
+        I: Iterator<Item = T>,
+        B: Backend + ?Sized,
+    {
+        // Everything is mapped to the view box coordinates before being passed to the backend
+        let placement = self.placement;
+        let map_arc = |(start, center, end, large_angle_flag): Arc| {
+            let [start, center, end] = [start, center, end].map(|p| placement.apply_point(p));
+            (start, center, end, large_angle_flag)
+        };
+
+        backend.begin(self)?;
+        let mut vertices = Vec::with_capacity(4);
+        let mut tiling_vertices = Vec::with_capacity(4);
+        for group in &self.groups {
+            backend.begin_group(group)?;
+            match &group.kind {
+                GroupKind::Polygons(filter) => {
+                    for (id, p) in polygons().enumerate() {
+                        if filter.includes(&p) {
+                            tiling_vertices.clear();
+                            tiling_vertices.extend(
+                                p.vertices()
+                                    .into_iter()
+                                    .map(IntoTilingVertex::into_tiling_vertex),
+                            );
+                            vertices.clear();
+                            vertices.extend(
+                                tiling_vertices
+                                    .iter()
+                                    .map(|v| placement.apply_point(v.point)),
+                            );
+                            backend.draw_polygon(&Tile {
+                                id,
+                                tile_type: p.polygon_type(),
+                                vertices: &vertices,
+                                tiling_vertices: &tiling_vertices,
+                                arcs: p
+                                    .arcs()
+                                    .map(|(first, second)| (map_arc(first), map_arc(second))),
+                            })?;
+                        }
+                    }
+                }
+                GroupKind::FirstArcs => {
+                    for (first, _) in polygons().filter_map(|p| p.arcs()) {
+                        backend.draw_arc(map_arc(first))?;
+                    }
+                }
+                GroupKind::SecondArcs => {
+                    for (_, second) in polygons().filter_map(|p| p.arcs()) {
+                        backend.draw_arc(map_arc(second))?;
+                    }
+                }
+                GroupKind::Markers {
+                    radius, positions, ..
+                } => {
+                    for &p in positions {
+                        backend.draw_marker(placement.apply(p), *radius)?;
+                    }
+                }
+                GroupKind::Outlines { lines, .. } => {
+                    for &(start, end) in lines {
+                        backend.draw_line(placement.apply(start), placement.apply(end))?;
+                    }
+                }
+            }
+            backend.end_group(group)?;
+        }
+        backend.finish()
+    }
+}
+
+/// An output format. The backend receives the elements of the scene one at a time, already in the
+/// view box coordinates, and is responsible for writing them to its output.
+pub trait Backend {
+    /// Called once before anything is drawn.
+    fn begin(&mut self, scene: &Scene) -> io::Result<()>;
+
+    fn begin_group(&mut self, _group: &Group) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Draws a polygon, filled with the color of the current group, and outlined with the stroke
+    /// color of the scene.
+    fn draw_polygon(&mut self, tile: &Tile) -> io::Result<()>;
+
+    /// Draws an arc, stroked with the color of the current group.
+    fn draw_arc(&mut self, arc: Arc) -> io::Result<()>;
+
+    /// Draws a circle marking a point, filled with the color of the current group, and outlined
+    /// with the stroke color of the scene.
+    fn draw_marker(&mut self, center: Point, radius: f64) -> io::Result<()>;
+
+    /// Draws a line segment, stroked with the color and width of the current group.
+    fn draw_line(&mut self, start: Point, end: Point) -> io::Result<()>;
+
+    fn end_group(&mut self, _group: &Group) -> io::Result<()> {
+        Ok(())
+    }
+
+    /// Called once after everything is drawn.
+    fn finish(&mut self) -> io::Result<()>;
+}
+
+/// Renders the rhombuses or kites and darts decomposed from the seed into the backend, with the
+/// seed centered in a 100 by 100 view box. Returns the number of tiles.
+#[cfg(test)]
+pub fn render_seed<B: Backend>(
+    seed: crate::seeds::Seed,
+    num_generations: u64,
+    arc_colors: Option<(&str, &str)>,
+    backend: &mut B,
+) -> usize {
+    let scene = Scene::new(&RenderConfig {
+        placement: seed.placement(Point(50.0, 50.0), 50.0),
+        view_box_width: 100,
+        view_box_height: 100,
+        stroke_width: 1.0,
+        stroke_color: "white",
+        quad_colors: ("red", "blue"),
+        arc_colors,
+    })
+    .unwrap();
+    let quads = crate::tiling::merge_pairs(crate::tiling::generate_tiling(
+        seed.into_triangles(),
+        num_generations,
+    ));
+    scene.render(|| quads.iter(), backend).unwrap();
+    quads.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // A backend that only records what was drawn in each group
+    #[derive(Default)]
+    struct CountingBackend {
+        groups: Vec<(String, usize)>,
+        finished: bool,
+    }
+
+    impl Backend for CountingBackend {
+        fn begin(&mut self, _: &Scene) -> io::Result<()> {
+            Ok(())
+        }
+
+        fn begin_group(&mut self, group: &Group) -> io::Result<()> {
+            self.groups.push((group.color.css.to_string(), 0));
+            Ok(())
+        }
+
+        fn draw_polygon(&mut self, _: &Tile) -> io::Result<()> {
+            self.groups.last_mut().unwrap().1 += 1;
+            Ok(())
+        }
+
+        fn draw_arc(&mut self, _: Arc) -> io::Result<()> {
+            self.groups.last_mut().unwrap().1 += 1;
+            Ok(())
+        }
+
+        fn draw_marker(&mut self, _: Point, _: f64) -> io::Result<()> {
+            self.groups.last_mut().unwrap().1 += 1;
+            Ok(())
+        }
+
+        fn draw_line(&mut self, _: Point, _: Point) -> io::Result<()> {
+            self.groups.last_mut().unwrap().1 += 1;
+            Ok(())
+        }
+
+        fn finish(&mut self) -> io::Result<()> {
+            self.finished = true;
+            Ok(())
+        }
+    }
+
+    fn config(arc_colors: Option<(&'static str, &'static str)>) -> RenderConfig<'static> {
+        RenderConfig {
+            placement: Placement {
+                origin: Point::ZERO,
+                scale: 1.0,
+            },
+            view_box_width: 100,
+            view_box_height: 100,
+            stroke_width: 1.0,
+            stroke_color: "black",
+            quad_colors: ("red", "blue"),
+            arc_colors,
+        }
+    }
+
+    #[test]
+    fn test_scene_groups() {
+        let quads = crate::tiling::merge_pairs(crate::tiling::generate_tiling(
+            crate::seeds::rose().into_triangles(),
+            3,
+        ));
+        let thin = quads
+            .iter()
+            .filter(|q| q.polygon_type() == TileType::SmallRhombus)
+            .count();
+
+        let mut scene = Scene::new(&config(Some(("green", "yellow")))).unwrap();
+        let lines = vec![(GoldenPoint::ZERO, GoldenPoint::ONE); 3];
+        scene.add_outlines("lines", "white", 2.0, lines).unwrap();
+        let mut backend = CountingBackend::default();
+        scene.render(|| quads.iter(), &mut backend).unwrap();
+        assert!(backend.finished);
+        let expected = [
+            ("red", thin),
+            ("blue", quads.len() - thin),
+            ("green", quads.len()),
+            ("yellow", quads.len()),
+            ("white", 3),
+        ];
+        let expected: Vec<_> = expected.iter().map(|&(c, n)| (c.to_string(), n)).collect();
+        assert_eq!(backend.groups, expected);
+
+        assert_eq!(Scene::new(&config(None)).unwrap().groups.len(), 2);
+        assert!(Scene::new(&config(Some(("green", "nope")))).is_err());
+    }
+}
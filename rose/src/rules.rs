@@ -0,0 +1,617 @@
+use crate::geometry::*;
+use crate::golden::*;
+use crate::render::Polygon;
+use crate::seeds::{self, Seed};
+use crate::tiling::SubstitutionTriangle;
+
+use serde::Deserialize;
+use std::collections::HashMap;
+use std::fs;
+use std::io;
+
+// A ruleset describes a substitution tiling by its prototiles, the shapes that every tile is a copy
+// of, and by how each prototile is decomposed. To decompose a tile, it is first inflated, scaling it
+// up by the inflation factor, and then replaced by copies of the prototiles, each placed by an
+// affine transform. So the tiles of every generation have the same size as the prototiles, and the
+// tiling is scaled down by the inflation factor after each generation to keep its size.
+//
+// Unlike the Robinson triangles, the tiles generated from a ruleset only have floating point
+// coordinates, since the transforms may be arbitrary. Because of that, they are always drawn as
+// they are, and never merged in pairs.
+
+/// An affine transform, given by the matrix `[a, b, c, d, e, f]`, in the same order as the
+/// `matrix` transform in SVG. It maps the point `(x, y)` to `(ax + cy + e, bx + dy + f)`.
+#[derive(Debug, Copy, Clone, PartialEq)]
+pub struct Affine(pub [f64; 6]);
+
+impl Affine {
+    pub const IDENTITY: Affine = Affine([1.0, 0.0, 0.0, 1.0, 0.0, 0.0]);
+
+    pub fn scale(factor: f64) -> Self {
+        Affine([factor, 0.0, 0.0, factor, 0.0, 0.0])
+    }
+
+    pub fn translate(Point(x, y): Point) -> Self {
+        Affine([1.0, 0.0, 0.0, 1.0, x, y])
+    }
+
+    /// Rotates around the origin by the given angle, in degrees, in the same direction as
+    /// `Point::rotate`.
+    pub fn rotation(angle: f64) -> Self {
+        let (sin, cos) = angle.to_radians().sin_cos();
+        Affine([cos, sin, -sin, cos, 0.0, 0.0])
+    }
+
+    /// Returns the similarity that maps `from.0` to `to.0` and `from.1` to `to.1`, without
+    /// reflecting anything.
+    pub fn match_segments(from: (Point, Point), to: (Point, Point)) -> Affine {
+        let third = |(p, q): (Point, Point)| p + (q - p).rotate(90.0);
+        Affine::mapping([from.0, from.1, third(from)], [to.0, to.1, third(to)]).unwrap()
+    }
+
+    pub fn apply(&self, Point(x, y): Point) -> Point {
+        let [a, b, c, d, e, f] = self.0;
+        Point(a * x + c * y + e, b * x + d * y + f)
+    }
+
+    /// Returns the transform that applies `other` first, and then `self`.
+    pub fn compose(&self, other: &Affine) -> Affine {
+        let [a1, b1, c1, d1, e1, f1] = self.0;
+        let [a2, b2, c2, d2, e2, f2] = other.0;
+        Affine([
+            a1 * a2 + c1 * b2,
+            b1 * a2 + d1 * b2,
+            a1 * c2 + c1 * d2,
+            b1 * c2 + d1 * d2,
+            a1 * e2 + c1 * f2 + e1,
+            b1 * e2 + d1 * f2 + f1,
+        ])
+    }
+
+    /// Whether all the entries of the matrix are finite, that is, neither infinite nor NaN.
+    pub fn is_finite(&self) -> bool {
+        self.0.iter().all(|x| x.is_finite())
+    }
+
+    /// The factor by which the transform scales areas. It's negative if the transform is a
+    /// reflection.
+    pub fn determinant(&self) -> f64 {
+        let [a, b, c, d, _, _] = self.0;
+        a * d - b * c
+    }
+
+    /// Returns the transform that maps each of the points in `from` to the point in `to` with the
+    /// same index, or `None` if the points in `from` are collinear.
+    pub fn mapping(from: [Point; 3], to: [Point; 3]) -> Option<Affine> {
+        let (u1, u2) = (from[1] - from[0], from[2] - from[0]);
+        let (v1, v2) = (to[1] - to[0], to[2] - to[0]);
+        let det = u1.cross(u2);
+        if det.abs() < f64::EPSILON {
+            return None;
+        }
+
+        // The linear part maps u1 to v1 and u2 to v2, so it's V * U^-1, where U and V are the
+        // matrices whose columns are those vectors
+        let linear = Affine([
+            (v1.0 * u2.1 - v2.0 * u1.1) / det,
+            (v1.1 * u2.1 - v2.1 * u1.1) / det,
+            (v2.0 * u1.0 - v1.0 * u2.0) / det,
+            (v2.1 * u1.0 - v1.1 * u2.0) / det,
+            0.0,
+            0.0,
+        ]);
+        Some(Affine::translate(to[0] - linear.apply(from[0])).compose(&linear))
+    }
+}
+
+/// A copy of a prototile, placed by a transform. For the children of a prototile, the transform
+/// maps the child prototile to its place in the inflated parent prototile.
+#[derive(Debug, Clone)]
+pub struct Instance {
+    pub prototile: usize,
+    pub transform: Affine,
+}
+
+#[derive(Debug, Clone)]
+pub struct Prototile {
+    pub name: String,
+
+    /// The type of the prototile, which sets its color and its name in the exported data.
+    pub tile_type: TileType,
+    pub vertices: Vec<Point>,
+    pub children: Vec<Instance>,
+}
+
+#[derive(Debug, Clone)]
+pub struct Ruleset {
+    pub name: String,
+
+    /// The factor by which the tiles are scaled up before being replaced by their children.
+    pub inflation: f64,
+    pub prototiles: Vec<Prototile>,
+
+    /// The tiles that the tiling is generated from.
+    pub seed: Vec<Instance>,
+}
+
+/// A tile generated from a ruleset, with its vertices in the tiling coordinates.
+#[derive(Debug, Clone)]
+pub struct RuleTile {
+    pub prototile: usize,
+    pub tile_type: TileType,
+    pub transform: Affine,
+    pub vertices: Vec<Point>,
+}
+
+impl Polygon for RuleTile {
+    type Vertex = Point;
+
+    fn polygon_type(&self) -> TileType {
+        self.tile_type
+    }
+
+    fn vertices(&self) -> Vec<Point> {
+        self.vertices.clone()
+    }
+
+    // Rulesets don't describe the matching rules of their tiles
+    fn arcs(&self) -> Option<(Arc, Arc)> {
+        None
+    }
+}
+
+// The names of the built-in rulesets, which can be used instead of a file name
+pub const BUILT_IN_RULESETS: [&str; 2] = ["penrose-rhombus", "penrose-kite-dart"];
+
+impl Ruleset {
+    /// Returns the built-in ruleset with the given name, if there is one.
+    pub fn built_in(name: &str) -> Option<Ruleset> {
+        match name {
+            "penrose-rhombus" => Some(penrose(
+                "Penrose rhombus tiling (P3)",
+                [TileType::SmallRhombus, TileType::LargeRhombus],
+                seeds::rose(),
+            )),
+            "penrose-kite-dart" => Some(penrose(
+                "Penrose kite and dart tiling (P2)",
+                [TileType::Kite, TileType::Dart],
+                seeds::tile(TileType::Kite),
+            )),
+            _ => None,
+        }
+    }
+
+    /// Returns the built-in ruleset with the given name, or loads it from the file with that name.
+    /// Files whose names end in ".json" are parsed as JSON, and every other file as TOML.
+    pub fn load(name: &str) -> io::Result<Ruleset> {
+        if let Some(ruleset) = Ruleset::built_in(name) {
+            return Ok(ruleset);
+        }
+        let contents = fs::read_to_string(name)?;
+        if name.to_lowercase().ends_with(".json") {
+            Ruleset::from_json(&contents)
+        } else {
+            Ruleset::from_toml(&contents)
+        }
+    }
+
+    pub fn from_toml(s: &str) -> io::Result<Ruleset> {
+        toml::from_str::<RulesetFile>(s)
+            .map_err(|e| invalid_data(e.to_string()))?
+            .into_ruleset()
+    }
+
+    pub fn from_json(s: &str) -> io::Result<Ruleset> {
+        serde_json::from_str::<RulesetFile>(s)
+            .map_err(|e| invalid_data(e.to_string()))?
+            .into_ruleset()
+    }
+
+    /// Creates a ruleset, checking that it's valid. Every prototile must have at least three
+    /// vertices, and the area of its children must add up to the area of the inflated prototile,
+    /// which catches most mistakes in the transforms. If no seed is given, the seed is a single
+    /// copy of the first prototile, centered on the origin.
+    pub fn new(
+        name: String,
+        inflation: f64,
+        prototiles: Vec<Prototile>,
+        seed: Vec<Instance>,
+    ) -> io::Result<Ruleset> {
+        if !inflation.is_finite() || inflation <= 1.0 {
+            return Err(invalid_data(format!(
+                "the inflation factor must be a finite number larger than 1, but it's {}",
+                inflation
+            )));
+        }
+        let first = prototiles
+            .first()
+            .ok_or_else(|| invalid_data("the ruleset has no prototiles".to_string()))?;
+        for p in &prototiles {
+            if p.vertices.len() < 3 {
+                return Err(invalid_data(format!(
+                    "prototile \"{}\" has fewer than three vertices",
+                    p.name
+                )));
+            }
+            if p.vertices
+                .iter()
+                .any(|v| !v.0.is_finite() || !v.1.is_finite())
+            {
+                return Err(invalid_data(format!(
+                    "prototile \"{}\" has a vertex with a coordinate that isn't finite",
+                    p.name
+                )));
+            }
+            if p.children.iter().any(|c| !c.transform.is_finite()) {
+                return Err(invalid_data(format!(
+                    "prototile \"{}\" has a child with a transform that isn't finite",
+                    p.name
+                )));
+            }
+            let children_area: f64 = p
+                .children
+                .iter()
+                .map(|c| c.transform.determinant().abs() * area(&prototiles[c.prototile].vertices))
+                .sum();
+            let expected = inflation * inflation * area(&p.vertices);
+            if (children_area - expected).abs() > 1e-6 * expected {
+                return Err(invalid_data(format!(
+                    "the children of prototile \"{}\" have a total area of {}, but the inflated \
+                    prototile has an area of {}",
+                    p.name, children_area, expected
+                )));
+            }
+        }
+        if seed.iter().any(|s| !s.transform.is_finite()) {
+            return Err(invalid_data(
+                "the seed has a tile with a transform that isn't finite".to_string(),
+            ));
+        }
+        let seed = if seed.is_empty() {
+            let center = first.vertices.iter().fold(Point::ZERO, |sum, &v| sum + v)
+                / first.vertices.len() as f64;
+            vec![Instance {
+                prototile: 0,
+                transform: Affine::translate(-center),
+            }]
+        } else {
+            seed
+        };
+        Ok(Ruleset {
+            name,
+            inflation,
+            prototiles,
+            seed,
+        })
+    }
+
+    fn tile(&self, instance: &Instance) -> RuleTile {
+        let prototile = &self.prototiles[instance.prototile];
+        RuleTile {
+            prototile: instance.prototile,
+            tile_type: prototile.tile_type,
+            transform: instance.transform,
+            vertices: prototile
+                .vertices
+                .iter()
+                .map(|&v| instance.transform.apply(v))
+                .collect(),
+        }
+    }
+
+    /// Inflates the tile around the origin, and replaces it by its children. So the children have
+    /// the size of the prototiles, and the tiling grows by the inflation factor.
+    pub fn decompose(&self, tile: &RuleTile) -> Vec<RuleTile> {
+        // The inflated tile is the prototile placed by the tile's transform and then scaled up,
+        // but the children are placed in the inflated prototile, which is scaled before the
+        // transform
+        let parent = Affine::scale(self.inflation)
+            .compose(&tile.transform)
+            .compose(&Affine::scale(1.0 / self.inflation));
+        self.prototiles[tile.prototile]
+            .children
+            .iter()
+            .map(|child| {
+                self.tile(&Instance {
+                    prototile: child.prototile,
+                    transform: parent.compose(&child.transform),
+                })
+            })
+            .collect()
+    }
+
+    /// Decomposes the seed `num_generations` times. The tiles keep the size of the prototiles, so
+    /// the tiling grows by the inflation factor in each generation.
+    pub fn generate_tiling(&self, num_generations: u64) -> Vec<RuleTile> {
+        let seed = self.seed.iter().map(|p| self.tile(p)).collect();
+        (0..num_generations).fold(seed, |tiles: Vec<_>, _| {
+            tiles.iter().flat_map(|t| self.decompose(t)).collect()
+        })
+    }
+
+    /// Like `generate_tiling`, but discards the tiles that are far from `bounds` as the tiling is
+    /// generated. The children of a tile may stick out of it, so a tile is only discarded if it's
+    /// farther from the bounds than its own size. Rulesets whose children stick out even farther
+    /// than that may lose some tiles near the edges of the bounds.
+    pub fn generate_tiling_clipped(
+        &self,
+        num_generations: u64,
+        bounds: Rectangle,
+    ) -> Vec<RuleTile> {
+        let is_near = |t: &RuleTile| {
+            let bounding = Rectangle::bounding(&t.vertices);
+            let size = bounding.min.distance_to(bounding.max);
+            bounds.expand(size).intersects(&bounding)
+        };
+        let seed = self
+            .seed
+            .iter()
+            .map(|p| self.tile(p))
+            .filter(is_near)
+            .collect();
+        (0..num_generations).fold(seed, |tiles: Vec<_>, _| {
+            tiles
+                .iter()
+                .flat_map(|t| self.decompose(t))
+                .filter(is_near)
+                .collect()
+        })
+    }
+}
+
+// The area of a simple polygon, using the shoelace formula
+fn area(vertices: &[Point]) -> f64 {
+    let n = vertices.len();
+    let twice_area: f64 = (0..n)
+        .map(|i| vertices[i].cross(vertices[(i + 1) % n]))
+        .sum();
+    twice_area.abs() / 2.0
+}
+
+fn invalid_data(message: String) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message)
+}
+
+// Builds the ruleset of a Penrose tiling from the decomposition of its Robinson triangles. There is
+// one prototile for each type of triangle, and the transform of each child is the one that maps the
+// prototile of its type to it. The mirror images of the triangles are given by reflections, so they
+// don't need prototiles of their own
+fn penrose(name: &str, tile_types: [TileType; 2], seed: Seed) -> Ruleset {
+    let prototile_triangle = |tile_type: TileType| {
+        let right = GoldenPoint::real(tile_type.base_to_side_ratio());
+        RobinsonTriangle::from_base(-right, right, tile_type, false)
+    };
+    let index = |tile_type: TileType| tile_types.iter().position(|&t| t == tile_type).unwrap();
+    let to_points =
+        |t: &RobinsonTriangle, scale: f64| [t.a, t.b, t.c].map(|p| scale * p.to_point());
+    let instance = |t: &RobinsonTriangle, scale: f64| Instance {
+        prototile: index(t.triangle_type),
+        transform: Affine::mapping(
+            to_points(&prototile_triangle(t.triangle_type), 1.0),
+            to_points(t, scale),
+        )
+        .unwrap(),
+    };
+
+    // The children are placed in the inflated prototile, so they are scaled up by phi
+    let prototiles = tile_types
+        .iter()
+        .map(|&tile_type| {
+            let triangle = prototile_triangle(tile_type);
+            Prototile {
+                name: tile_type.name().to_string(),
+                tile_type,
+                vertices: to_points(&triangle, 1.0).to_vec(),
+                children: triangle
+                    .decompose()
+                    .iter()
+                    .map(|child| instance(child, PHI))
+                    .collect(),
+            }
+        })
+        .collect();
+    let seed = seed
+        .into_triangles()
+        .iter()
+        .map(|t| instance(t, 1.0))
+        .collect();
+    Ruleset::new(name.to_string(), PHI, prototiles, seed).unwrap()
+}
+
+// The format of the rule files. The prototiles are referred to by their names, and their types by
+// the names given by `TileType::name`
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RulesetFile {
+    #[serde(default)]
+    name: String,
+    inflation: f64,
+    prototiles: Vec<PrototileFile>,
+    #[serde(default)]
+    seed: Vec<InstanceFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct PrototileFile {
+    name: String,
+    #[serde(rename = "type")]
+    tile_type: String,
+    vertices: Vec<[f64; 2]>,
+    #[serde(default)]
+    children: Vec<InstanceFile>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct InstanceFile {
+    tile: String,
+    #[serde(default = "identity")]
+    transform: [f64; 6],
+}
+
+fn identity() -> [f64; 6] {
+    Affine::IDENTITY.0
+}
+
+impl RulesetFile {
+    fn into_ruleset(self) -> io::Result<Ruleset> {
+        let mut indices = HashMap::new();
+        for (i, p) in self.prototiles.iter().enumerate() {
+            if indices.insert(p.name.clone(), i).is_some() {
+                return Err(invalid_data(format!(
+                    "there is more than one prototile named \"{}\"",
+                    p.name
+                )));
+            }
+        }
+        let instance = |p: InstanceFile| match indices.get(&p.tile) {
+            Some(&prototile) => Ok(Instance {
+                prototile,
+                transform: Affine(p.transform),
+            }),
+            None => Err(invalid_data(format!(
+                "there is no prototile named \"{}\"",
+                p.tile
+            ))),
+        };
+        let mut prototiles = Vec::with_capacity(self.prototiles.len());
+        for p in self.prototiles {
+            let tile_type = TileType::from_name(&p.tile_type).ok_or_else(|| {
+                let names: Vec<_> = TileType::ALL.iter().map(|t| t.name()).collect();
+                invalid_data(format!(
+                    "unknown tile type \"{}\", expected one of: {}",
+                    p.tile_type,
+                    names.join(", ")
+                ))
+            })?;
+            prototiles.push(Prototile {
+                name: p.name,
+                tile_type,
+                vertices: p.vertices.iter().map(|&[x, y]| Point(x, y)).collect(),
+                children: p
+                    .children
+                    .into_iter()
+                    .map(instance)
+                    .collect::<io::Result<_>>()?,
+            });
+        }
+        let seed = self
+            .seed
+            .into_iter()
+            .map(instance)
+            .collect::<io::Result<_>>()?;
+        Ruleset::new(self.name, self.inflation, prototiles, seed)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiling::generate_tiling;
+
+    const CHAIR: &str = include_str!("../rules/chair.toml");
+
+    #[test]
+    fn test_affine_mapping() {
+        let from = [Point(0.0, 0.0), Point(1.0, 0.0), Point(0.0, 1.0)];
+        let to = [Point(2.0, 1.0), Point(2.0, 3.0), Point(0.0, 1.0)];
+        let transform = Affine::mapping(from, to).unwrap();
+        for (&p, &q) in from.iter().zip(to.iter()) {
+            assert_close!(transform.apply(p), q);
+        }
+        assert_close!(transform.determinant(), 4.0);
+        let inverse = Affine::mapping(to, from).unwrap();
+        let p = Point(0.3, -1.7);
+        assert_close!(transform.compose(&inverse).apply(p), p);
+        assert!(Affine::mapping(from, [Point::ZERO; 3]).is_some());
+        assert!(Affine::mapping([Point::ZERO; 3], to).is_none());
+    }
+
+    #[test]
+    fn test_penrose_ruleset() {
+        // The built-in rulesets must generate the same triangles as the exact decomposition, scaled
+        // up by phi for each generation
+        let expected = [
+            (BUILT_IN_RULESETS[0], seeds::rose()),
+            (BUILT_IN_RULESETS[1], seeds::tile(TileType::Kite)),
+        ];
+        for (name, seed) in expected.iter() {
+            let ruleset = Ruleset::built_in(name).unwrap();
+            let triangles = generate_tiling(seed.clone().into_triangles(), 4);
+            let tiles = ruleset.generate_tiling(4);
+            assert_eq!(tiles.len(), triangles.len());
+            for (tile, t) in tiles.iter().zip(&triangles) {
+                assert_eq!(tile.tile_type, t.triangle_type);
+                let scale = PHI.powi(4);
+                for (&v, &p) in tile.vertices.iter().zip(&[t.a, t.b, t.c]) {
+                    assert_close!(v, scale * p.to_point());
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_load_rules() {
+        let chair = Ruleset::from_toml(CHAIR).unwrap();
+        assert_eq!(chair.prototiles.len(), 1);
+        let tiles = chair.generate_tiling(3);
+        assert_eq!(tiles.len(), 64);
+        let total: f64 = tiles.iter().map(|t| area(&t.vertices)).sum();
+        assert_close!(total, 64.0 * 3.0);
+
+        let json = r#"{
+            "inflation": 2,
+            "prototiles": [{
+                "name": "square",
+                "type": "square",
+                "vertices": [[0, 0], [1, 0], [1, 1], [0, 1]],
+                "children": [
+                    {"tile": "square"},
+                    {"tile": "square", "transform": [1, 0, 0, 1, 1, 0]},
+                    {"tile": "square", "transform": [1, 0, 0, 1, 0, 1]},
+                    {"tile": "square", "transform": [1, 0, 0, 1, 1, 1]}
+                ]
+            }]
+        }"#;
+        let squares = Ruleset::from_json(json).unwrap();
+        assert_eq!(squares.generate_tiling(2).len(), 16);
+
+        // Mistakes in the transforms change the area of the children
+        let wrong = json.replace("[1, 0, 0, 1, 1, 1]", "[2, 0, 0, 1, 1, 1]");
+        assert!(Ruleset::from_json(&wrong).is_err());
+        let unknown = json.replace("{\"tile\": \"square\"}", "{\"tile\": \"circle\"}");
+        assert!(Ruleset::from_json(&unknown).is_err());
+        let bad_type = json.replace("\"type\": \"square\"", "\"type\": \"circle\"");
+        assert!(Ruleset::from_json(&bad_type).is_err());
+
+        // TOML can express infinite and NaN numbers, which no ruleset can use
+        for (from, to) in &[
+            ("inflation = 2", "inflation = inf"),
+            ("inflation = 2", "inflation = nan"),
+            ("[[0, 0], [2, 0]", "[[0, 0], [nan, 0]"),
+            ("[1, 0, 0, 1, 1, 1]", "[1, 0, 0, 1, 1, inf]"),
+        ] {
+            assert!(Ruleset::from_toml(&CHAIR.replace(from, to)).is_err());
+        }
+    }
+
+    #[test]
+    fn test_clipping() {
+        let bounds = Rectangle {
+            min: Point(-2.0, -2.0),
+            max: Point(0.0, 1.0),
+        };
+        let chair = Ruleset::from_toml(CHAIR).unwrap();
+        let all = chair.generate_tiling(4);
+        let clipped = chair.generate_tiling_clipped(4, bounds);
+        assert!(clipped.len() < all.len());
+        for t in all {
+            if bounds.intersects(&Rectangle::bounding(&t.vertices)) {
+                assert!(clipped
+                    .iter()
+                    .any(|c| c.vertices[0].is_close(&t.vertices[0]) && c.transform == t.transform));
+            }
+        }
+    }
+}
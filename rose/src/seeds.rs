@@ -1,32 +1,95 @@
+use crate::ammann_beenker::AmmannBeenkerTriangle;
 use crate::geometry::*;
+use crate::golden::*;
+use crate::monotiles::Monotile;
+use crate::pentagons::PentagonTile;
+use crate::silver::*;
 
+/// The tiles that a seed starts from. Each kind of tiles is generated with its own functions.
 #[derive(Clone)]
-pub struct Seed(Vec<RobinsonTriangle>);
+pub enum SeedTiles {
+    /// Robinson triangles, which are decomposed with the functions in `tiling`.
+    Robinson(Vec<RobinsonTriangle>),
+
+    /// The tiles of the P1 seeds, which can't be split into triangles, and are decomposed with the
+    /// functions in `pentagons`.
+    Pentagons(Vec<PentagonTile>),
+
+    /// Ammann-Beenker triangles. These are decomposed with the same functions in `tiling` as the
+    /// Robinson triangles, but their vertices are `SilverPoint`s.
+    AmmannBeenker(Vec<AmmannBeenkerTriangle>),
+
+    /// The aperiodic monotile that the seed grows into, with the functions in `monotiles`. Its
+    /// tiles aren't decomposed, but grouped into larger and larger metatiles, so the seed has no
+    /// tiles of its own.
+    Monotile(Monotile),
+}
+
+#[derive(Clone)]
+pub struct Seed {
+    tiles: SeedTiles,
+
+    // The side length of the seed tiles, in exact coordinates. The seeds are centered on the
+    // origin, so most of them have unit side length, but the single tile seeds must be scaled by
+    // two for their center to be representable exactly
+    side_length: i64,
+}
 
 impl Seed {
-    pub fn transform(self, center: Point, scale: f64) -> Vec<RobinsonTriangle> {
-        self.0
-            .iter()
-            .map(|t| {
-                let RobinsonTriangle { a, b, c, .. } = *t;
-                RobinsonTriangle::new(scale * a + center, scale * b + center, scale * c + center)
-            })
-            .collect()
+    /// Returns the placement that maps the seed's center to `center`, and that scales it so that
+    /// the side length of the seed tiles is `scale`.
+    pub fn placement(&self, center: Point, scale: f64) -> Placement {
+        Placement {
+            origin: center,
+            scale: scale / self.side_length as f64,
+        }
+    }
+
+    pub fn tiles(&self) -> &SeedTiles {
+        &self.tiles
+    }
+
+    pub fn into_tiles(self) -> SeedTiles {
+        self.tiles
+    }
+
+    /// Returns the Robinson triangles of the seed. Panics if the seed is made of other tiles.
+    pub fn into_triangles(self) -> Vec<RobinsonTriangle> {
+        match self.tiles {
+            SeedTiles::Robinson(triangles) => triangles,
+            _ => panic!("The seed isn't made of Robinson triangles"),
+        }
+    }
+
+    /// Returns the tiles of a P1 seed. Panics if the seed is made of other tiles.
+    pub fn into_pentagon_tiles(self) -> Vec<PentagonTile> {
+        match self.tiles {
+            SeedTiles::Pentagons(tiles) => tiles,
+            _ => panic!("The seed isn't made of P1 tiles"),
+        }
+    }
+
+    /// Returns the triangles of an Ammann-Beenker seed. Panics if the seed is made of other tiles.
+    pub fn into_ammann_beenker_triangles(self) -> Vec<AmmannBeenkerTriangle> {
+        match self.tiles {
+            SeedTiles::AmmannBeenker(triangles) => triangles,
+            _ => panic!("The seed isn't made of Ammann-Beenker triangles"),
+        }
     }
 }
 
 pub fn rose() -> Seed {
-    let p1 = Point(1.0, 0.0);
-    let p2 = Point(PHI, 0.0);
+    let p1 = GoldenPoint::ONE;
+    let p2 = GoldenPoint::real(Golden::PHI);
     let p3 = p2 + p1.rotate(36.0);
     let p4 = p2.rotate(36.0);
     let p5 = (p2 + p1).rotate(36.0);
 
     let top_half = [
-        RobinsonTriangle::new(p4, p1, Point::ZERO), // Inner petal
-        RobinsonTriangle::new(p1, p4, p2),          // Outer petal
-        RobinsonTriangle::new(p5, p4, p2),          // Leaf
-        RobinsonTriangle::new(p5, p3, p2),          // Leaf
+        RobinsonTriangle::new(p4, p1, GoldenPoint::ZERO), // Inner petal
+        RobinsonTriangle::new(p1, p4, p2),                // Outer petal
+        RobinsonTriangle::new(p5, p4, p2),                // Leaf
+        RobinsonTriangle::new(p5, p3, p2),                // Leaf
     ];
 
     // The rose can be divided in five equal sectors. Here we create the first one of these
@@ -44,27 +107,34 @@ pub fn rose() -> Seed {
         first_sector.extend(sector_clone.iter().map(|t| t.rotate(angle)));
     }
 
-    Seed(first_sector)
+    Seed {
+        tiles: SeedTiles::Robinson(first_sector),
+        side_length: 1,
+    }
 }
 
 pub fn tile(tile_type: TileType) -> Seed {
-    let right = Point(tile_type.base_to_side_ratio() / 2.0, 0.0);
+    // The base goes from -ratio to ratio, so the tile sides have length 2
+    let right = GoldenPoint::real(tile_type.base_to_side_ratio());
     let left = -right;
-    Seed(vec![
-        RobinsonTriangle::from_base(left, right, tile_type, true),
-        RobinsonTriangle::from_base(left, right, tile_type, false),
-    ])
+    Seed {
+        tiles: SeedTiles::Robinson(vec![
+            RobinsonTriangle::from_base(left, right, tile_type, true),
+            RobinsonTriangle::from_base(left, right, tile_type, false),
+        ]),
+        side_length: 2,
+    }
 }
 
 pub fn pizza() -> Seed {
-    let p1 = Point(1.0, 0.0);
+    let p1 = GoldenPoint::ONE;
     let p2 = p1.rotate(36.0);
     let p3 = p1.rotate(72.0);
 
     // First we create the first two triangles
     let mut triangles = vec![
-        RobinsonTriangle::new(p1, Point::ZERO, p2),
-        RobinsonTriangle::new(p3, Point::ZERO, p2),
+        RobinsonTriangle::new(p1, GoldenPoint::ZERO, p2),
+        RobinsonTriangle::new(p3, GoldenPoint::ZERO, p2),
     ];
 
     // And then create the remaining eight by rotating the first two
@@ -73,7 +143,122 @@ pub fn pizza() -> Seed {
         triangles.push(triangles[0].rotate(angle));
         triangles.push(triangles[1].rotate(angle));
     }
-    Seed(triangles)
+    Seed {
+        tiles: SeedTiles::Robinson(triangles),
+        side_length: 1,
+    }
+}
+
+pub fn pentagon() -> Seed {
+    pentagon_tile(TileType::Pentagon5)
+}
+
+pub fn star() -> Seed {
+    pentagon_tile(TileType::Star)
+}
+
+// Creates a seed with a single P1 tile, with a corner pointing up. Its center is the average of its
+// five outer corners, so the tile must be scaled by five for the center to be representable exactly
+fn pentagon_tile(tile_type: TileType) -> Seed {
+    // The first vertex is the top corner, and the first side goes down and to the right from it, at
+    // 36 degrees for the pentagon, and 72 degrees for the star
+    let side = match tile_type {
+        TileType::Pentagon5 => GoldenPoint::ONE.rotate_steps(1),
+        _ => GoldenPoint::ONE.rotate_steps(2),
+    };
+    let tile = PentagonTile::new(tile_type, GoldenPoint::ZERO, side);
+    let step = tile.vertices.len() / 5;
+    let sum = tile
+        .vertices
+        .iter()
+        .step_by(step)
+        .fold(GoldenPoint::ZERO, |sum, &v| sum + v);
+    let vertices = tile
+        .vertices
+        .iter()
+        .map(|&v| Golden::from(5) * v - sum)
+        .collect();
+    Seed {
+        tiles: SeedTiles::Pentagons(vec![PentagonTile {
+            tile_type,
+            vertices,
+        }]),
+        side_length: 5,
+    }
+}
+
+/// Eight rhombs around the origin, with their acute corners at the center, which grows into an
+/// Ammann-Beenker tiling with eightfold symmetry.
+pub fn octagram() -> Seed {
+    let triangles = (0..8)
+        .flat_map(|i| {
+            let (a, b) = (
+                SilverPoint::ONE.rotate_steps(i),
+                SilverPoint::ONE.rotate_steps(i + 1),
+            );
+            vec![
+                AmmannBeenkerTriangle::rhomb(SilverPoint::ZERO, a, a + b),
+                AmmannBeenkerTriangle::rhomb(SilverPoint::ZERO, b, a + b),
+            ]
+        })
+        .collect();
+    ammann_beenker_seed(triangles, 1)
+}
+
+pub fn square() -> Seed {
+    // The corners are at (±1, ±1), and (1, 1) is sqrt(2)ξ
+    let corner = SilverPoint(Silver::ZERO, Silver::SQRT_2);
+    let [a, b, c, d] = [4, 6, 0, 2].map(|i| corner.rotate_steps(i));
+    ammann_beenker_seed(
+        vec![
+            AmmannBeenkerTriangle::square(c, b, a),
+            AmmannBeenkerTriangle::square(c, d, a),
+        ],
+        2,
+    )
+}
+
+pub fn rhomb() -> Seed {
+    // The long diagonal goes from -(1 + ξ) to 1 + ξ, so the sides have length 2
+    let xi = SilverPoint::ONE.rotate_steps(1);
+    let c = SilverPoint::ONE + xi;
+    let a = -c;
+    ammann_beenker_seed(
+        vec![
+            AmmannBeenkerTriangle::rhomb(a, a + SilverPoint::real(Silver::from(2)), c),
+            AmmannBeenkerTriangle::rhomb(a, a + Silver::from(2) * xi, c),
+        ],
+        2,
+    )
+}
+
+fn ammann_beenker_seed(triangles: Vec<AmmannBeenkerTriangle>, side_length: i64) -> Seed {
+    Seed {
+        tiles: SeedTiles::AmmannBeenker(triangles),
+        side_length,
+    }
+}
+
+/// The hat monotile, which grows into a tiling of hats and their mirror images.
+pub fn hat() -> Seed {
+    monotile_seed(Monotile::Hat)
+}
+
+/// The spectre monotile, which grows into a tiling of spectres that all have the same handedness.
+pub fn spectre() -> Seed {
+    monotile_seed(Monotile::Spectre)
+}
+
+fn monotile_seed(monotile: Monotile) -> Seed {
+    Seed {
+        tiles: SeedTiles::Monotile(monotile),
+        side_length: 1,
+    }
+}
+
+#[cfg(test)]
+pub fn get_ammann_beenker_seeds() -> [Seed; 3] {
+    [octagram(), square(), rhomb()]
 }
 
 #[cfg(test)]
@@ -94,9 +279,39 @@ mod tests {
 
     #[test]
     fn test_side_length() {
-        for s in &get_all_seeds() {
-            for t in &s.0 {
-                assert_close!(Line(t.a, t.b).length(), 1.0);
+        let seeds = get_all_seeds();
+        let pentagon_seeds = [pentagon(), star()];
+        let ammann_beenker_seeds = get_ammann_beenker_seeds();
+        for s in seeds
+            .iter()
+            .chain(&pentagon_seeds)
+            .chain(&ammann_beenker_seeds)
+        {
+            let squared = s.side_length * s.side_length;
+            match &s.tiles {
+                SeedTiles::Robinson(triangles) => {
+                    for t in triangles {
+                        assert_eq!((t.b - t.a).norm_squared(), Golden::from(squared));
+                    }
+                }
+                SeedTiles::Pentagons(tiles) => {
+                    for t in tiles {
+                        let n = t.vertices.len();
+                        for i in 0..n {
+                            assert_eq!(
+                                (t.vertices[(i + 1) % n] - t.vertices[i]).norm_squared(),
+                                Golden::from(squared)
+                            );
+                        }
+                    }
+                }
+                SeedTiles::AmmannBeenker(triangles) => {
+                    for t in triangles {
+                        assert_eq!((t.b - t.a).norm_squared(), Silver::from(squared));
+                        assert_eq!((t.c - t.b).norm_squared(), Silver::from(squared));
+                    }
+                }
+                SeedTiles::Monotile(_) => unreachable!(),
             }
         }
     }
@@ -0,0 +1,295 @@
+use crate::geometry::*;
+use std::cmp::Ordering;
+use std::f64::consts::SQRT_2;
+use std::ops;
+
+/// The silver ratio, 1 + sqrt(2), which is the inflation factor of the Ammann-Beenker tiling
+pub const DELTA: f64 = 1.0 + SQRT_2;
+
+/// An element of the ring Z[sqrt(2)], that is, a number of the form `a + b * sqrt(2)`, where `a` and
+/// `b` are integers. Like with `Golden`, this representation is unique, so equality and hashing are
+/// exact.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, Hash)]
+pub struct Silver(pub i64, pub i64);
+
+impl Silver {
+    pub const ZERO: Silver = Silver(0, 0);
+    pub const ONE: Silver = Silver(1, 0);
+    pub const SQRT_2: Silver = Silver(0, 1);
+    pub const DELTA: Silver = Silver(1, 1);
+
+    pub fn to_f64(self) -> f64 {
+        self.0 as f64 + self.1 as f64 * SQRT_2
+    }
+
+    /// Multiplies the number by sqrt(2), that is, `(a + b * sqrt(2)) * sqrt(2) == 2b + a * sqrt(2)`.
+    pub fn mul_sqrt_2(self) -> Self {
+        Silver(2 * self.1, self.0)
+    }
+
+    /// Multiplies the number by the silver ratio δ == 1 + sqrt(2), which is the same as
+    /// `(a + b * sqrt(2))δ == (a + 2b) + (a + b) * sqrt(2)`.
+    pub fn mul_delta(self) -> Self {
+        Silver(self.0 + 2 * self.1, self.0 + self.1)
+    }
+
+    /// Divides the number by the silver ratio. Since 1 / δ == sqrt(2) - 1, this is the same as
+    /// `(a + b * sqrt(2)) / δ == (2b - a) + (a - b) * sqrt(2)`.
+    pub fn div_delta(self) -> Self {
+        Silver(2 * self.1 - self.0, self.0 - self.1)
+    }
+
+    /// Returns 1 if the number is positive, -1 if it is negative, and 0 if it is zero. This is
+    /// computed exactly, without converting the number to floating point.
+    pub fn signum(self) -> i64 {
+        // If a and b have different signs, the sign is given by whichever term has the largest
+        // absolute value, which we find by comparing their squares. Since sqrt(2) is irrational,
+        // the squares can never be equal, unless both are zero
+        let (a, b) = (self.0 as i128, self.1 as i128);
+        if a >= 0 && b >= 0 {
+            (a + b).signum() as i64
+        } else if a <= 0 && b <= 0 {
+            -1
+        } else if a * a > 2 * b * b {
+            a.signum() as i64
+        } else {
+            b.signum() as i64
+        }
+    }
+}
+
+impl From<i64> for Silver {
+    fn from(n: i64) -> Self {
+        Silver(n, 0)
+    }
+}
+
+impl Ord for Silver {
+    fn cmp(&self, other: &Self) -> Ordering {
+        (*self - *other).signum().cmp(&0)
+    }
+}
+
+impl PartialOrd for Silver {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl ops::Add for Silver {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        Silver(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+impl ops::Sub for Silver {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        Silver(self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+
+impl ops::Neg for Silver {
+    type Output = Self;
+    fn neg(self) -> Self {
+        Silver(-self.0, -self.1)
+    }
+}
+
+impl ops::Mul for Silver {
+    type Output = Self;
+    fn mul(self, rhs: Self) -> Self {
+        // (a + b * sqrt(2))(c + d * sqrt(2)) == (ac + 2bd) + (ad + bc) * sqrt(2)
+        let (Silver(a, b), Silver(c, d)) = (self, rhs);
+        Silver(a * c + 2 * b * d, a * d + b * c)
+    }
+}
+
+impl ops::Mul<Silver> for i64 {
+    type Output = Silver;
+    fn mul(self, rhs: Silver) -> Silver {
+        Silver(self * rhs.0, self * rhs.1)
+    }
+}
+
+/// A point whose coordinates can be represented exactly, as `p + qξ`, where `p` and `q` are in
+/// Z[sqrt(2)], and ξ is the unit vector that makes a 45 degree angle with the x axis. This is
+/// enough to represent every vertex of an Ammann-Beenker tiling built from a seed with integer side
+/// lengths, since every edge points in one of the eight directions given by rotating ξ, and
+/// decomposing a tile only divides its edges by the silver ratio.
+///
+/// Like with `GoldenPoint`, the ordering of points is lexicographic on their coefficients.
+#[derive(Debug, Default, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct SilverPoint(pub Silver, pub Silver);
+
+impl SilverPoint {
+    pub const ZERO: SilverPoint = SilverPoint(Silver::ZERO, Silver::ZERO);
+    pub const ONE: SilverPoint = SilverPoint(Silver::ONE, Silver::ZERO);
+
+    /// Creates a point on the x axis.
+    pub fn real(x: Silver) -> Self {
+        SilverPoint(x, Silver::ZERO)
+    }
+
+    /// Converts the point to floating point coordinates.
+    pub fn to_point(self) -> Point {
+        // Both coordinates of ξ are sqrt(2) / 2
+        let SilverPoint(p, q) = self;
+        let q = q.to_f64() * SQRT_2 / 2.0;
+        Point(p.to_f64() + q, q)
+    }
+
+    /// Returns the square of the distance from the origin to the point.
+    pub fn norm_squared(self) -> Silver {
+        // The conjugate of ξ is sqrt(2) - ξ, and ξ(sqrt(2) - ξ) == 1, since ξ^2 == sqrt(2)ξ - 1. So
+        // (p + qξ)(p + q(sqrt(2) - ξ)) == p^2 + pq * sqrt(2) + q^2
+        let SilverPoint(p, q) = self;
+        p * p + (p * q).mul_sqrt_2() + q * q
+    }
+
+    /// Rotates the point around the origin by `steps` multiples of 45 degrees.
+    pub fn rotate_steps(self, steps: i64) -> Self {
+        // Multiplying p + qξ by ξ gives pξ + q(sqrt(2)ξ - 1) == -q + (p + q * sqrt(2))ξ
+        (0..steps.rem_euclid(8)).fold(self, |SilverPoint(p, q), _| {
+            SilverPoint(-q, p + q.mul_sqrt_2())
+        })
+    }
+
+    pub fn mul_delta(self) -> Self {
+        SilverPoint(self.0.mul_delta(), self.1.mul_delta())
+    }
+
+    pub fn div_delta(self) -> Self {
+        SilverPoint(self.0.div_delta(), self.1.div_delta())
+    }
+}
+
+impl ExactPoint for SilverPoint {
+    fn to_point(self) -> Point {
+        SilverPoint::to_point(self)
+    }
+
+    fn coefficients(self) -> [[i64; 2]; 2] {
+        let SilverPoint(p, q) = self;
+        [[p.0, p.1], [q.0, q.1]]
+    }
+}
+
+impl From<Silver> for SilverPoint {
+    fn from(x: Silver) -> Self {
+        SilverPoint::real(x)
+    }
+}
+
+impl Close for SilverPoint {
+    fn is_close(&self, other: &Self) -> bool {
+        self == other
+    }
+}
+
+impl Transform for SilverPoint {
+    /// Rotates the point around the origin. The angle must be a multiple of 45 degrees, since
+    /// those are the only rotations that can be represented exactly.
+    fn rotate(&self, angle: f64) -> Self {
+        let steps = angle / 45.0;
+        assert!(
+            close(steps, steps.round()),
+            "Angle must be a multiple of 45 degrees"
+        );
+        self.rotate_steps(steps.round() as i64)
+    }
+
+    fn mirror_x(&self) -> Self {
+        -self.mirror_y()
+    }
+
+    fn mirror_y(&self) -> Self {
+        // The complex conjugate of p + qξ is p + q(sqrt(2) - ξ) == (p + q * sqrt(2)) - qξ
+        let SilverPoint(p, q) = *self;
+        SilverPoint(p + q.mul_sqrt_2(), -q)
+    }
+}
+
+impl ops::Add for SilverPoint {
+    type Output = Self;
+    fn add(self, rhs: Self) -> Self {
+        SilverPoint(self.0 + rhs.0, self.1 + rhs.1)
+    }
+}
+
+impl ops::Sub for SilverPoint {
+    type Output = Self;
+    fn sub(self, rhs: Self) -> Self {
+        SilverPoint(self.0 - rhs.0, self.1 - rhs.1)
+    }
+}
+
+impl ops::Neg for SilverPoint {
+    type Output = Self;
+    fn neg(self) -> Self {
+        SilverPoint(-self.0, -self.1)
+    }
+}
+
+impl ops::Mul<SilverPoint> for Silver {
+    type Output = SilverPoint;
+    fn mul(self, rhs: SilverPoint) -> SilverPoint {
+        SilverPoint(self * rhs.0, self * rhs.1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::Rng;
+
+    fn random_silver<R: Rng>(rng: &mut R) -> Silver {
+        Silver(rng.gen_range(-1000..1000), rng.gen_range(-1000..1000))
+    }
+
+    #[test]
+    fn test_silver_arithmetic() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..10_000 {
+            let a = random_silver(&mut rng);
+            let b = random_silver(&mut rng);
+            assert_close!((a + b).to_f64(), a.to_f64() + b.to_f64());
+            assert_close!((a - b).to_f64(), a.to_f64() - b.to_f64());
+            assert_close!((a * b).to_f64() / 1e6, a.to_f64() * b.to_f64() / 1e6);
+            assert_close!(a.mul_delta().to_f64(), a.to_f64() * DELTA);
+            assert_close!(a.div_delta().to_f64(), a.to_f64() / DELTA);
+            assert_eq!(a.mul_delta().div_delta(), a);
+            // Zero is checked on its own, since its floating point sign is positive
+            if a != Silver::ZERO {
+                assert_eq!(a.signum() as f64, a.to_f64().signum());
+            }
+            assert_eq!(a.cmp(&b), a.to_f64().partial_cmp(&b.to_f64()).unwrap());
+        }
+        assert_eq!(Silver::ZERO.signum(), 0);
+        assert_eq!(Silver::SQRT_2 * Silver::SQRT_2, Silver::from(2));
+    }
+
+    #[test]
+    fn test_silver_point_conversion() {
+        let mut rng = rand::thread_rng();
+        for _ in 0..10_000 {
+            let a = SilverPoint(random_silver(&mut rng), random_silver(&mut rng));
+            let b = SilverPoint(random_silver(&mut rng), random_silver(&mut rng));
+            let (fa, fb) = (a.to_point(), b.to_point());
+            assert_close!((a + b).to_point(), fa + fb);
+            assert_close!((a - b).to_point(), fa - fb);
+            assert_close!(
+                a.norm_squared().to_f64().sqrt(),
+                Point::ZERO.distance_to(fa)
+            );
+            assert_close!(a.mirror_x().to_point(), fa.mirror_x());
+            assert_close!(a.mirror_y().to_point(), fa.mirror_y());
+            let steps = rng.gen_range(-16..16);
+            assert_close!(
+                a.rotate_steps(steps).to_point(),
+                fa.rotate(steps as f64 * 45.0)
+            );
+        }
+    }
+}
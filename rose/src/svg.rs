@@ -1,203 +1,101 @@
 use crate::geometry::*;
+use crate::render::{Backend, Group, GroupKind, Scene, Tile};
 
-use std::fmt::Write;
-use std::fs::File;
-use std::write;
+use std::io::{self, Write};
 
-const SCALING_FACTOR: u64 = 1000;
+pub const SCALING_FACTOR: u64 = 1000;
 
 // Since writing a float to string is so slow, it is much better to convert it to an integer
 // (scaling it first so as not to lose too much precision) and write the integer to the string
 // instead. Using this technique to avoid writing floats to the SVG improves performance
 // considerably.
-fn scale_float(x: f64) -> i64 {
+pub fn scale_float(x: f64) -> i64 {
     (x * SCALING_FACTOR as f64) as i64
 }
 
-pub trait SvgPolygon {
-    fn polygon_type(&self) -> TileType;
-    fn write_points(&self, builder: &mut SvgBuilder) -> std::fmt::Result;
-    fn arcs(&self) -> (Arc, Arc);
+/// Writes the scene as an SVG file. Every element is written to `out` as soon as it's drawn, so
+/// `out` should usually be buffered.
+pub struct SvgBackend<W> {
+    out: W,
 }
 
-impl SvgPolygon for RobinsonTriangle {
-    fn polygon_type(&self) -> TileType {
-        self.triangle_type
+impl<W: Write> SvgBackend<W> {
+    pub fn new(out: W) -> Self {
+        SvgBackend { out }
     }
 
-    fn write_points(&self, builder: &mut SvgBuilder) -> std::fmt::Result {
-        write!(
-            builder.content,
-            "{},{} {},{} {},{}",
-            scale_float(self.a.0),
-            scale_float(self.a.1),
-            scale_float(self.b.0),
-            scale_float(self.b.1),
-            scale_float(self.c.0),
-            scale_float(self.c.1),
-        )
+    pub fn get_mut(&mut self) -> &mut W {
+        &mut self.out
     }
 
-    fn arcs(&self) -> (Arc, Arc) {
-        let first_radius = Line(Line(self.a, self.b).median(), self.a).length();
-        let first_arc = (
-            Line(self.a, self.b).median(),
-            self.a,
-            self.a + first_radius * (self.c - self.a).normalized(),
-            false,
-        );
-        let second_radius = Line(Line(self.c, self.b).median(), self.c).length();
-        let second_arc = (
-            Line(self.c, self.b).median(),
-            self.c,
-            self.c + second_radius * (self.a - self.c).normalized(),
-            false,
-        );
-        (first_arc, second_arc)
+    pub fn into_inner(self) -> W {
+        self.out
     }
 }
 
-impl SvgPolygon for Quadrilateral {
-    fn polygon_type(&self) -> TileType {
-        RobinsonTriangle::infer_triangle_type(self.a, self.b, self.c)
-    }
-
-    fn write_points(&self, builder: &mut SvgBuilder) -> std::fmt::Result {
+impl<W: Write> Backend for SvgBackend<W> {
+    fn begin(&mut self, scene: &Scene) -> io::Result<()> {
+        // As we are scaling all coordinates by SCALING_FACTOR, we must also scale the view box.
         write!(
-            builder.content,
-            "{},{} {},{} {},{} {},{}",
-            scale_float(self.a.0),
-            scale_float(self.a.1),
-            scale_float(self.b.0),
-            scale_float(self.b.1),
-            scale_float(self.c.0),
-            scale_float(self.c.1),
-            scale_float(self.d.0),
-            scale_float(self.d.1),
+            self.out,
+            "<?xml version=\"1.0\" encoding=\"utf-8\"?>\n<svg width=\"100%\" height=\"100%\" \
+            viewBox=\"0 0 {} {}\" preserveAspectRatio=\"xMidYMid slice\" \
+            xmlns=\"http://www.w3.org/2000/svg\">\n  <g stroke=\"{}\" stroke-width=\"{}\" \
+            stroke-linecap=\"round\" stroke-linejoin=\"round\">\n",
+            scene.view_box_width * SCALING_FACTOR,
+            scene.view_box_height * SCALING_FACTOR,
+            scene.stroke_color.css,
+            scale_float(scene.stroke_width),
         )
     }
 
-    fn arcs(&self) -> (Arc, Arc) {
-        let first_arc = (
-            Line(self.a, self.b).median(),
-            self.a,
-            Line(self.a, self.d).median(),
-            false,
-        );
-        let second_arc = (
-            Line(self.c, self.b).median(),
-            self.c,
-            Line(self.c, self.d).median(),
-            // Only in the dart tile, the second arc has an angle of more than 180 degrees
-            self.polygon_type() == TileType::Dart,
-        );
-        (first_arc, second_arc)
-    }
-}
-
-pub struct SvgConfig<'a> {
-    pub view_box_width: u64,
-    pub view_box_height: u64,
-    pub stroke_width: f64,
-    pub stroke_color: &'a str,
-    pub quad_colors: (&'a str, &'a str),
-    pub arc_colors: Option<(&'a str, &'a str)>,
-}
-
-pub struct SvgBuilder<'a> {
-    config: SvgConfig<'a>,
-    content: String,
-}
-
-impl<'a> SvgBuilder<'a> {
-    pub fn new(config: SvgConfig<'a>) -> Self {
-        // As we are scaling all coordinates by SCALING_FACTOR, we must also scale the view box.
-        let content = format!(
-            "<svg width=\"100%\" height=\"100%\" viewBox=\"0 0 {} {}\" preserveAspectRatio=\
-            \"xMidYMid slice\" xmlns=\"http://www.w3.org/2000/svg\">\n  <g stroke=\"{}\" \
-            stroke-width=\"{}\" stroke-linecap=\"round\" stroke-linejoin=\"round\">\n",
-            config.view_box_width * SCALING_FACTOR,
-            config.view_box_height * SCALING_FACTOR,
-            config.stroke_color,
-            scale_float(config.stroke_width),
-        );
-        SvgBuilder { config, content }
-    }
-
-    pub fn build(self, out_file: &mut File) -> std::io::Result<()> {
-        use std::io::prelude::*;
-        let declaration = b"<?xml version=\"1.0\" encoding=\"utf-8\"?>\n";
-        out_file.write_all(declaration)?;
-        out_file.write_all(self.content.as_bytes())?;
-        out_file.write_all(b"  </g>\n</svg>\n")?;
-        Ok(())
-    }
-
-    pub fn build_to_string(mut self) -> Result<String, std::fmt::Error> {
-        write!(self.content, "  </g>\n</svg>\n")?;
-        Ok(self.content)
-    }
-
-    pub fn add_all_polygons<T: SvgPolygon>(&mut self, polys: Vec<T>) -> std::fmt::Result {
-        macro_rules! add_polygon_group {
-            ($color:expr, $($type:expr),*) => {
-                writeln!(self.content, r#"    <g fill="{}">"#, $color)?;
-                let filtered = polys.iter().filter(
-                    |p| $(p.polygon_type() == $type)||*
-                );
-                for p in filtered {
-                    self.add_polygon(p)?
-                }
-                writeln!(self.content, "    </g>")?;
-            };
+    fn begin_group(&mut self, group: &Group) -> io::Result<()> {
+        match &group.kind {
+            GroupKind::Polygons(_) => writeln!(self.out, r#"    <g fill="{}">"#, group.color.css),
+            GroupKind::Markers { name, .. } => writeln!(
+                self.out,
+                r#"    <g fill="{}" class="{}">"#,
+                group.color.css, name
+            ),
+            GroupKind::Outlines { name, width, .. } => writeln!(
+                self.out,
+                r#"    <g fill="none" stroke="{}" stroke-width="{}" class="{}">"#,
+                group.color.css,
+                scale_float(*width),
+                name
+            ),
+            GroupKind::FirstArcs | GroupKind::SecondArcs => writeln!(
+                self.out,
+                r#"    <g fill="none" stroke="{}">"#,
+                group.color.css
+            ),
         }
-        add_polygon_group!(
-            self.config.quad_colors.0,
-            TileType::SmallRhombus,
-            TileType::Dart
-        );
-        add_polygon_group!(
-            self.config.quad_colors.1,
-            TileType::LargeRhombus,
-            TileType::Kite
-        );
-
-        if let Some((color_1, color_2)) = self.config.arc_colors {
-            let (arcs_1, arcs_2): (Vec<_>, Vec<_>) = polys.iter().map(SvgPolygon::arcs).unzip();
-            self.add_arc_group(arcs_1, &color_1)?;
-            self.add_arc_group(arcs_2, &color_2)?;
-        }
-        Ok(())
-    }
-
-    fn add_polygon(&mut self, polygon: &dyn SvgPolygon) -> std::fmt::Result {
-        write!(self.content, "      <polygon points=\"")?;
-        polygon.write_points(self)?;
-        writeln!(self.content, "\" />")
     }
 
-    fn add_arc_group(&mut self, arcs: Vec<Arc>, color: &str) -> std::fmt::Result {
-        writeln!(self.content, "    <g fill=\"none\" stroke=\"{}\">", color)?;
-        for a in arcs {
-            self.add_arc(a)?;
+    fn draw_polygon(&mut self, tile: &Tile) -> io::Result<()> {
+        write!(self.out, "      <polygon points=\"")?;
+        for (i, p) in tile.vertices.iter().enumerate() {
+            let separator = if i == 0 { "" } else { " " };
+            write!(
+                self.out,
+                "{}{},{}",
+                separator,
+                scale_float(p.0),
+                scale_float(p.1)
+            )?;
         }
-        writeln!(self.content, "    </g>")
+        writeln!(self.out, "\" />")
     }
 
-    fn add_arc(&mut self, (start, center, end, large_angle_flag): Arc) -> std::fmt::Result {
+    fn draw_arc(&mut self, arc: Arc) -> io::Result<()> {
+        let (start, center, end, large_angle_flag) = arc;
         let radius = scale_float(Line(start, center).length());
 
-        // This uses the cross product between the start and end directions in order to determine
-        // if the shortest rotation from start to end is clockwise or counterclockwise. If the arc
-        // is supposed to represent the largest rotation, we must invert the result.
-        let sweep_flag = (start - center).cross(end - center) > 0.0;
-        let sweep_flag = if large_angle_flag {
-            !sweep_flag
-        } else {
-            sweep_flag
-        };
+        // The sweep flag is set for arcs that go in the direction of increasing angles, which would
+        // be counterclockwise if the y axis pointed up
+        let sweep_flag = arc_is_counterclockwise(arc, true);
         writeln!(
-            self.content,
+            self.out,
             "      <path d=\"M {} {} A {} {} 0 {} {} {} {}\" />",
             scale_float(start.0),
             scale_float(start.1),
@@ -209,4 +107,34 @@ impl<'a> SvgBuilder<'a> {
             scale_float(end.1),
         )
     }
+
+    fn draw_marker(&mut self, Point(x, y): Point, radius: f64) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "      <circle cx=\"{}\" cy=\"{}\" r=\"{}\" />",
+            scale_float(x),
+            scale_float(y),
+            scale_float(radius),
+        )
+    }
+
+    fn draw_line(&mut self, start: Point, end: Point) -> io::Result<()> {
+        writeln!(
+            self.out,
+            "      <path d=\"M {} {} L {} {}\" />",
+            scale_float(start.0),
+            scale_float(start.1),
+            scale_float(end.0),
+            scale_float(end.1),
+        )
+    }
+
+    fn end_group(&mut self, _: &Group) -> io::Result<()> {
+        writeln!(self.out, "    </g>")
+    }
+
+    fn finish(&mut self) -> io::Result<()> {
+        write!(self.out, "  </g>\n</svg>\n")?;
+        self.out.flush()
+    }
 }
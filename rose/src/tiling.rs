@@ -1,35 +1,267 @@
 use crate::geometry::*;
+use crate::golden::*;
 use std::collections::hash_map::Entry;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 
-pub fn generate_tiling(seed: Vec<RobinsonTriangle>, num_generations: u64) -> Vec<RobinsonTriangle> {
-    let mut triangles = seed;
+#[cfg(feature = "parallel")]
+use rayon::prelude::*;
+
+// With the "parallel" feature, `TilingIter` decomposes the triangles that are this many generations
+// away from the last one all at once, using every thread, or the seed triangles, if there are fewer
+// generations. This keeps the memory usage bounded, while giving each thread enough work.
+#[cfg(feature = "parallel")]
+const PARALLEL_DEPTH: u64 = 10;
+
+/// A triangle of a substitution tiling, like the Robinson triangles of the Penrose tilings. Every
+/// tile of the tiling is split into two of these triangles, which share their base, from `a` to
+/// `c`, so the tiles are found again by merging the pairs of triangles with the same base. Since
+/// the vertices are exact, two triangles share a base if and only if the sums of their base
+/// vertices are equal.
+pub trait SubstitutionTriangle: Clone + Send + Sync {
+    type Vertex: ExactPoint;
+
+    /// The ratio between the sides of a triangle and the sides of its children.
+    const INFLATION_FACTOR: f64;
+
+    /// The vertices `a`, `b` and `c` of the triangle.
+    fn vertices(&self) -> [Self::Vertex; 3];
+
+    /// Decomposes the triangle into the triangles of the next generation. Every child must be
+    /// inside its parent, so that the triangles outside the view box can be discarded.
+    fn decompose(self) -> Vec<Self>;
+}
+
+impl SubstitutionTriangle for RobinsonTriangle {
+    type Vertex = GoldenPoint;
+    const INFLATION_FACTOR: f64 = PHI;
+
+    fn vertices(&self) -> [GoldenPoint; 3] {
+        [self.a, self.b, self.c]
+    }
+
+    fn decompose(self) -> Vec<Self> {
+        decompose(self)
+    }
+}
+
+// Twice the median of the base of the triangle, which is the same for both halves of a tile
+fn base_key<T: SubstitutionTriangle>(t: &T) -> T::Vertex {
+    let [a, _, c] = t.vertices();
+    a + c
+}
+
+// Merges two triangles that share their base into the tile they are halves of
+fn merge<T: SubstitutionTriangle>(t: &T, other: &T) -> Quadrilateral<T::Vertex> {
+    let [a, b, c] = t.vertices();
+    Quadrilateral {
+        a,
+        b,
+        c,
+        d: other.vertices()[1],
+    }
+}
+
+pub fn generate_tiling<T: SubstitutionTriangle>(seed: Vec<T>, num_generations: u64) -> Vec<T> {
+    decompose_generations(seed, num_generations, None)
+}
+
+/// Like `generate_tiling`, but only keeps the triangles that intersect `bounds`. Since the
+/// descendants of a triangle are always inside it, the triangles that don't intersect the bounds
+/// can be discarded before they are decomposed. This makes generating a small region of a deep
+/// tiling much faster.
+pub fn generate_tiling_clipped<T: SubstitutionTriangle>(
+    seed: Vec<T>,
+    num_generations: u64,
+    bounds: Rectangle,
+) -> Vec<T> {
+    let bounds = clipping_bounds(&seed, num_generations, bounds);
+    let seed = seed
+        .into_iter()
+        .filter(|t| intersects(&bounds, t))
+        .collect();
+    decompose_generations(seed, num_generations, Some(bounds))
+}
+
+/// Like `generate_tiling`, but also records the ancestors of the triangles, that is, the triangles
+/// of every earlier generation. The result has the triangles of each generation, starting with the
+/// seed, so the triangles of generation k are the supertiles that the triangles of the later
+/// generations were decomposed from. Like in `generate_tiling_clipped`, only the triangles that
+/// intersect `bounds` are kept, if it's given.
+pub fn generate_tiling_with_ancestors<T: SubstitutionTriangle>(
+    seed: Vec<T>,
+    num_generations: u64,
+    bounds: Option<Rectangle>,
+) -> Vec<Vec<T>> {
+    let is_visible = |t: &T| bounds.map_or(true, |b| intersects(&b, t));
+    let mut generations = vec![seed.into_iter().filter(is_visible).collect::<Vec<_>>()];
     for _ in 0..num_generations {
-        triangles = triangles.into_iter().flat_map(decompose).collect();
+        let previous = generations.last().unwrap().clone();
+        generations.push(decompose_generations(previous, 1, bounds));
+    }
+    generations
+}
+
+/// Returns the edges of the tiles that the triangles are halves of, each of them only once. Since
+/// the base of each triangle is inside its tile, only the other two sides are edges of the tile,
+/// so this works even if the other half of a tile is missing.
+pub fn tile_outlines(triangles: &[RobinsonTriangle]) -> Vec<(GoldenPoint, GoldenPoint)> {
+    let mut seen = HashSet::new();
+    let mut edges = Vec::new();
+    for t in triangles {
+        for &(start, end) in &[(t.a, t.b), (t.b, t.c)] {
+            if seen.insert((start.min(end), start.max(end))) {
+                edges.push((start, end));
+            }
+        }
+    }
+    edges
+}
+
+// Decomposes the triangles `num_generations` times, discarding the ones that don't intersect
+// `bounds` after each generation, if it is given. With the "parallel" feature, the triangles of
+// each generation are split across all threads. Since the order of the triangles is preserved
+// when collecting, the result is the same either way.
+fn decompose_generations<T: SubstitutionTriangle>(
+    mut triangles: Vec<T>,
+    num_generations: u64,
+    bounds: Option<Rectangle>,
+) -> Vec<T> {
+    let is_visible = |t: &T| bounds.map_or(true, |b| intersects(&b, t));
+    for _ in 0..num_generations {
+        #[cfg(not(feature = "parallel"))]
+        let next = triangles
+            .into_iter()
+            .flat_map(T::decompose)
+            .filter(is_visible)
+            .collect();
+        #[cfg(feature = "parallel")]
+        let next = triangles
+            .into_par_iter()
+            .flat_map_iter(T::decompose)
+            .filter(is_visible)
+            .collect();
+        triangles = next;
     }
     triangles
 }
 
-pub fn merge_pairs(mut triangles: Vec<RobinsonTriangle>) -> Vec<Quadrilateral> {
+/// Returns an iterator over the triangles of the tiling, in the same way as `generate_tiling`.
+/// Instead of decomposing every triangle of a generation before moving on to the next, the
+/// iterator decomposes the seed depth-first, and yields each triangle of the last generation as
+/// soon as it's generated. Because of that, it only needs memory proportional to the number of
+/// generations, instead of the number of triangles.
+pub fn iter_tiling<T: SubstitutionTriangle>(seed: Vec<T>, num_generations: u64) -> TilingIter<T> {
+    TilingIter {
+        stack: seed.into_iter().map(|t| (t, 0)).collect(),
+        num_generations,
+        bounds: None,
+        batch: Vec::new(),
+    }
+}
+
+/// Like `iter_tiling`, but only yields the triangles that intersect `bounds`, in the same way as
+/// `generate_tiling_clipped`.
+pub fn iter_tiling_clipped<T: SubstitutionTriangle>(
+    seed: Vec<T>,
+    num_generations: u64,
+    bounds: Rectangle,
+) -> TilingIter<T> {
+    let bounds = clipping_bounds(&seed, num_generations, bounds);
+    TilingIter {
+        stack: seed
+            .into_iter()
+            .filter(|t| intersects(&bounds, t))
+            .map(|t| (t, 0))
+            .collect(),
+        num_generations,
+        bounds: Some(bounds),
+        batch: Vec::new(),
+    }
+}
+
+pub struct TilingIter<T> {
+    // The triangles that are yet to be decomposed, together with their generation
+    stack: Vec<(T, u64)>,
+    num_generations: u64,
+    bounds: Option<Rectangle>,
+
+    // The triangles of the last generation that were decomposed all at once, and are yet to be
+    // yielded. This is only used with the "parallel" feature
+    batch: Vec<T>,
+}
+
+impl<T: SubstitutionTriangle> Iterator for TilingIter<T> {
+    type Item = T;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        if let Some(t) = self.batch.pop() {
+            return Some(t);
+        }
+        while let Some((t, generation)) = self.stack.pop() {
+            if generation == self.num_generations {
+                return Some(t);
+            }
+            #[cfg(feature = "parallel")]
+            if generation == self.num_generations.saturating_sub(PARALLEL_DEPTH) {
+                let depth = self.num_generations - generation;
+                self.batch = decompose_generations(vec![t], depth, self.bounds);
+                match self.batch.pop() {
+                    Some(t) => return Some(t),
+                    None => continue,
+                }
+            }
+            let bounds = self.bounds;
+            let children = t
+                .decompose()
+                .into_iter()
+                .filter(|t| bounds.map_or(true, |b| intersects(&b, t)))
+                .map(|t| (t, generation + 1));
+            self.stack.extend(children);
+        }
+        None
+    }
+}
+
+fn clipping_bounds<T: SubstitutionTriangle>(
+    seed: &[T],
+    num_generations: u64,
+    bounds: Rectangle,
+) -> Rectangle {
+    // A triangle that intersects the bounds may be paired with one that doesn't, and we need both
+    // to render the tile. Since both triangles are within one side length of each other, we grow the
+    // bounds by the largest side length a triangle will have in the last generation.
+    let largest_side = seed
+        .iter()
+        .flat_map(|t| {
+            let [a, b, c] = t.vertices().map(ExactPoint::to_point);
+            vec![a.distance_to(b), b.distance_to(c), c.distance_to(a)]
+        })
+        .fold(0.0, f64::max);
+    bounds.expand(largest_side / T::INFLATION_FACTOR.powi(num_generations as i32))
+}
+
+fn intersects<T: SubstitutionTriangle>(bounds: &Rectangle, t: &T) -> bool {
+    bounds.intersects_convex_polygon(&t.vertices().map(ExactPoint::to_point))
+}
+
+pub fn merge_pairs<T: SubstitutionTriangle>(
+    mut triangles: Vec<T>,
+) -> Vec<Quadrilateral<T::Vertex>> {
     // We could compare every triangle with every other triangle and check if their bases are
     // adjacent, but that would be O(n^2). Instead, we sort them by the position of their bases'
     // medians, so that two triangles with adjacent bases would be next to each other on the vector.
     // This step is O(n log n). After that it's just an O(n) pass through the vector to find the
     // pairs.
-    triangles.sort_by(|a, b| Point::compare(a.base_median(), b.base_median()));
+    triangles.sort_by_key(base_key);
     triangles
         .windows(2)
         .filter_map(|ts| {
             let (current, next) = (&ts[0], &ts[1]);
-            if close(current.base_median(), next.base_median()) {
+            if base_key(current) == base_key(next) {
                 // If the base medians are the same, then the base vertices should also be the same
-                assert!(close(current.a, next.a) || close(current.a, next.c));
-                Some(Quadrilateral {
-                    a: current.a,
-                    b: current.b,
-                    c: current.c,
-                    d: next.b,
-                })
+                let ([a, _, _], [next_a, _, next_c]) = (current.vertices(), next.vertices());
+                assert!(a == next_a || a == next_c);
+                Some(merge(current, next))
             } else {
                 None
             }
@@ -37,76 +269,126 @@ pub fn merge_pairs(mut triangles: Vec<RobinsonTriangle>) -> Vec<Quadrilateral> {
         .collect()
 }
 
-pub fn merge_pairs_hashing(triangles: Vec<RobinsonTriangle>) -> Vec<Quadrilateral> {
+#[cfg(not(feature = "parallel"))]
+pub fn merge_pairs_hashing<T: SubstitutionTriangle>(
+    triangles: Vec<T>,
+) -> Vec<Quadrilateral<T::Vertex>> {
     // The basic idea of this algorithm is to use a hash map indexed by the triangles bases'
     // medians, and to iterate through the triangles vector inserting them into the map. If there is
     // already a triangle with that same hash, they have the same base median, and can be merged.
-    // This would be O(n). Since the coordinates are stored as floating point numbers, we would have
-    // a problem if two triangles have base medians that are very close (closer than the `Close`
-    // trait tolerance) but still not exactly the same. In this case, their base medians would have
-    // completely different hashes, and the triangles would not be merged. Because of that, before
-    // we populate the hash map, we have to divide the points into discrete buckets and use these
-    // buckets for hashing, so that two base medians that are close will very likely be in the same
-    // bucket, and therefore will have the same hash. This introduces the possibilty for two errors:
-    // collisions and misses. If two base medians aren't close, but happen to land on the same
-    // bucket, we have a collision. If two base medians are close, but happen to land on the border
-    // between two buckets, such that they fall on different buckets, we would have a miss. Misses
-    // would leave some triangles remaining on the hash map, and we would have to go through it and
-    // try to find their pairs in the end. This is easier to do than trying to resolve collisions,
-    // so we choose the buckets accordingly.
-
-    let side_length = match triangles.get(0) {
-        Some(t) => Line(t.a, t.b).length(),
-        None => return vec![], // In case of empty triangles vector
-    };
-    let scaling_factor = 100.0 / side_length;
-
-    // This is how we divide the points into buckets. We couuld just floor each point to an integer,
-    // but that would result in very large buckets, and make collisions very likely. Instead, we
-    // scale the points first, so that when we floor them to integers each bucket will be relatively
-    // smaller, avoiding collisions. If we make the scaling factor too large, the buckets will be
-    // too small, leading to more misses. This is a balancing act.
-    let round_point_to_bucket =
-        |Point(x, y)| ((x * scaling_factor) as i32, (y * scaling_factor) as i32);
+    // Since the coordinates are exact, two triangles share a base if and only if their base medians
+    // are equal, so this is O(n) and never misses a pair. We use twice the base median as the key,
+    // since the median itself can't always be represented exactly.
 
     // We insert the indices instead of the actual triangles to save memory.
-    let mut map = HashMap::<(i32, i32), usize>::with_capacity(triangles.len());
+    let mut map = HashMap::<T::Vertex, usize>::with_capacity(triangles.len());
     let mut result = Vec::with_capacity(triangles.len() / 2);
     for (i, t) in triangles.iter().enumerate() {
-        let rounded = round_point_to_bucket(t.base_median());
-        match map.entry(rounded) {
+        match map.entry(base_key(t)) {
             Entry::Occupied(o) => {
-                // If there is a triangle in this bucket, we remove it from the hash map, and merge
-                // the triangles. We have to remove it to make fixing misses easier later.
+                // If there is a triangle with this base median, we remove it from the hash map, and
+                // merge the triangles. A base can't be shared by more than two triangles, so we
+                // won't need it again.
                 let (_, other) = o.remove_entry();
-                let other = &triangles[other];
-                assert_close!(other.base_median(), t.base_median());
-                result.push(Quadrilateral {
-                    a: t.a,
-                    b: t.b,
-                    c: t.c,
-                    d: other.b,
-                });
+                result.push(merge(t, &triangles[other]));
             }
             Entry::Vacant(v) => {
-                // If there is no triangle in this bucket, we just insert the current triangle.
+                // If there is no triangle with this base median, we just insert the current
+                // triangle.
                 v.insert(i);
             }
         }
     }
 
-    // Since we removed all triangles that were merged, the only triangles left in the map are
-    // either misses, or triangles that actually have no pair and won't be rendered. A lazy way to
-    // find the missed pairs is to just use the other O(n log n) algorithm. These misses would be
-    // very rare if the triangles were randomly positioned, but the way the tiling is generated make
-    // it so triangles are generated with round integer coordinates, resulting in misses pretty
-    // consistently. Still, they make up a small amount of the total triangles, so we don't need to
-    // worry so much about optimizing this step.
-    let remaining: Vec<_> = map.values().map(|i| triangles[*i].clone()).collect();
-    result.extend(merge_pairs(remaining));
+    // The only triangles left in the map are the ones on the border of the tiling, that have no
+    // pair and won't be rendered.
     result
 }
 
+/// Merges the pairs of triangles like the serial `merge_pairs_hashing`, but splits the triangles
+/// across all threads. Each thread merges the pairs in its chunk of the vector, and the triangles
+/// left unpaired are then merged with the ones from the neighbouring chunks. The result is the same
+/// as the serial version, up to the order of the quadrilaterals.
+#[cfg(feature = "parallel")]
+pub fn merge_pairs_hashing<T: SubstitutionTriangle>(
+    triangles: Vec<T>,
+) -> Vec<Quadrilateral<T::Vertex>> {
+    type Partial<'a, T> = (
+        HashMap<<T as SubstitutionTriangle>::Vertex, &'a T>,
+        Vec<Quadrilateral<<T as SubstitutionTriangle>::Vertex>>,
+    );
+
+    // Like in the serial version, when two triangles are merged, the one that comes later in the
+    // vector gives the first three vertices of the quadrilateral
+    fn add<'a, T: SubstitutionTriangle>((unpaired, result): &mut Partial<'a, T>, t: &'a T) {
+        match unpaired.entry(base_key(t)) {
+            Entry::Occupied(o) => {
+                let other = o.remove();
+                result.push(merge(t, other));
+            }
+            Entry::Vacant(v) => {
+                v.insert(t);
+            }
+        }
+    }
+
+    let (_, result) = triangles
+        .par_iter()
+        .fold(Partial::<T>::default, |mut partial, t| {
+            add(&mut partial, t);
+            partial
+        })
+        .reduce(Partial::<T>::default, |mut left, (unpaired, result)| {
+            // `reduce` keeps the order of the chunks, so every triangle in the right chunk comes
+            // after every triangle in the left one
+            left.1.extend(result);
+            for t in unpaired.into_values() {
+                add(&mut left, t);
+            }
+            left
+        });
+    result
+}
+
+/// Returns an iterator that merges the pairs of triangles into quadrilaterals as they're yielded
+/// by `triangles`, like `merge_pairs_hashing` does. A triangle is only kept in memory until its pair
+/// is found, so if the triangles come from `iter_tiling`, only the triangles on the boundary of the
+/// region generated so far need to be stored.
+pub fn merge_pairs_streaming<T, I>(triangles: I) -> MergePairs<T, I>
+where
+    T: SubstitutionTriangle,
+    I: Iterator<Item = T>,
+{
+    MergePairs {
+        triangles,
+        unpaired: HashMap::new(),
+    }
+}
+
+pub struct MergePairs<T: SubstitutionTriangle, I> {
+    triangles: I,
+    unpaired: HashMap<T::Vertex, T>,
+}
+
+impl<T: SubstitutionTriangle, I: Iterator<Item = T>> Iterator for MergePairs<T, I> {
+    type Item = Quadrilateral<T::Vertex>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        for t in &mut self.triangles {
+            match self.unpaired.entry(base_key(&t)) {
+                Entry::Occupied(o) => {
+                    let other = o.remove();
+                    return Some(merge(&t, &other));
+                }
+                Entry::Vacant(v) => {
+                    v.insert(t);
+                }
+            }
+        }
+        None
+    }
+}
+
 fn decompose(rt: RobinsonTriangle) -> Vec<RobinsonTriangle> {
     let RobinsonTriangle {
         triangle_type,
@@ -127,7 +409,7 @@ fn decompose(rt: RobinsonTriangle) -> Vec<RobinsonTriangle> {
             // A /__________\ C
             // It can be shown that:
             //   the length of BD == (the length of BA) / phi
-            let d = b + (a - b) / PHI;
+            let d = b + (a - b).div_phi();
             vec![
                 RobinsonTriangle::new(d, c, a),
                 RobinsonTriangle::new(c, d, b),
@@ -153,8 +435,8 @@ fn decompose(rt: RobinsonTriangle) -> Vec<RobinsonTriangle> {
             // It can be shown that:
             //   the length of AD == (the length of AB) / phi
             //   the length of AE == (the length of AC) / phi
-            let d = a + (b - a) / PHI;
-            let e = a + (c - a) / PHI;
+            let d = a + (b - a).div_phi();
+            let e = a + (c - a).div_phi();
             vec![
                 RobinsonTriangle::new(e, d, a),
                 RobinsonTriangle::new(c, e, b),
@@ -175,8 +457,8 @@ fn decompose(rt: RobinsonTriangle) -> Vec<RobinsonTriangle> {
             // It can be shown that:
             //   the length of BD == (the length of BA) / phi
             //   the length of AE == (the length of AC) / phi
-            let d = b + (a - b) / PHI;
-            let e = a + (c - a) / PHI;
+            let d = b + (a - b).div_phi();
+            let e = a + (c - a).div_phi();
             vec![
                 RobinsonTriangle::new(b, c, e),
                 RobinsonTriangle::new(b, d, e),
@@ -196,19 +478,133 @@ fn decompose(rt: RobinsonTriangle) -> Vec<RobinsonTriangle> {
             //
             // It can be shown that:
             //   the length of AD == (the length of AB) / phi
-            let d = a + (b - a) / PHI;
+            let d = a + (b - a).div_phi();
             vec![
                 RobinsonTriangle::new(a, d, c),
                 RobinsonTriangle::new(b, c, d),
             ]
         }
+        _ => panic!("{:?} is not a Robinson triangle", triangle_type),
+    }
+}
+
+/// Groups the triangles into the triangles of the previous generation that they were decomposed
+/// from, which is the inverse of `decompose`. A triangle of the previous generation is only
+/// returned if all of its children are among the given triangles, and so are the children of its
+/// mirror image, which is the other half of the same tile. The triangles near the boundary of the
+/// patch that can't be composed into whole tiles are discarded. The parents are returned in the
+/// order of their first child.
+pub fn compose(triangles: Vec<RobinsonTriangle>) -> Vec<RobinsonTriangle> {
+    let ids: HashMap<_, _> = triangles.iter().enumerate().map(|(i, t)| (t, i)).collect();
+
+    // Every triangle that could be a parent, because all of its children are in the patch,
+    // together with the ids of its children
+    let mut candidates: Vec<(RobinsonTriangle, Vec<usize>)> = Vec::new();
+    let mut is_candidate = HashSet::new();
+    for t in &triangles {
+        for parent in possible_parents(t) {
+            let is_valid = RobinsonTriangle::try_infer_triangle_type(parent.a, parent.b, parent.c)
+                == Some(parent.triangle_type);
+            if !is_valid || is_candidate.contains(&parent) {
+                continue;
+            }
+            let children: Option<Vec<_>> = decompose(parent.clone())
+                .iter()
+                .map(|c| ids.get(c).copied())
+                .collect();
+            if let Some(children) = children {
+                is_candidate.insert(parent.clone());
+                candidates.push((parent, children));
+            }
+        }
+    }
+
+    // A triangle can have more than one possible parent, since some pairs of children of a
+    // triangle are also the children of a triangle of another type. For example, the EDA and DEB
+    // children of a large triangle are the children of a small AEB triangle. Those wrong parents
+    // are never next to their mirror image, so only the parents whose mirror image was also found
+    // are kept. Since a patch could be part of different tilings, which only differ outside of
+    // it, the parents near its boundary that don't make a whole tile can't be known for sure
+    let mut has_parent = vec![false; triangles.len()];
+    let mut parents = Vec::new();
+    for (parent, children) in candidates {
+        if is_candidate.contains(&parent.mirror()) && children.iter().all(|&c| !has_parent[c]) {
+            for c in children {
+                has_parent[c] = true;
+            }
+            parents.push(parent);
+        }
+    }
+    parents
+}
+
+/// Composes the triangles `num_generations` times, in the same way as `compose`.
+pub fn compose_tiling(
+    mut triangles: Vec<RobinsonTriangle>,
+    num_generations: u64,
+) -> Vec<RobinsonTriangle> {
+    for _ in 0..num_generations {
+        triangles = compose(triangles);
+    }
+    triangles
+}
+
+// Returns the parent that the triangle would have for each of the children it could be in
+// `decompose`, found by solving the equations used there for the parent's vertices. The new points
+// there always split a side of the parent in two, so that the part next to one of its vertices is
+// 1/phi times the side, and the other part is 1/phi^2 times the side
+fn possible_parents(t: &RobinsonTriangle) -> Vec<RobinsonTriangle> {
+    let parent = |triangle_type, a, b, c| RobinsonTriangle {
+        triangle_type,
+        a,
+        b,
+        c,
+    };
+
+    // If X = P + (Q - P) / phi, these return Q given P and X, and P given Q and X
+    let from_near = |p: GoldenPoint, x: GoldenPoint| p + (x - p).mul_phi();
+    let from_far = |q: GoldenPoint, x: GoldenPoint| q + (x - q).mul_phi().mul_phi();
+
+    let (x, y, z) = (t.a, t.b, t.c);
+    match t.triangle_type {
+        TileType::SmallRhombus | TileType::LargeRhombus => vec![
+            // A DCA child of a small triangle
+            parent(TileType::SmallRhombus, z, from_far(z, x), y),
+            // A CDB child of a small triangle
+            parent(TileType::SmallRhombus, from_near(z, y), z, x),
+            // An EDA child of a large triangle
+            parent(TileType::LargeRhombus, z, from_near(z, y), from_near(z, x)),
+            // A CEB child of a large triangle
+            parent(TileType::LargeRhombus, from_far(x, y), z, x),
+            // A DEB child of a large triangle
+            {
+                let a = from_far(z, x);
+                parent(TileType::LargeRhombus, a, z, from_near(a, y))
+            },
+        ],
+        TileType::Kite | TileType::Dart => vec![
+            // A BCE child of a half-kite triangle
+            parent(TileType::Kite, from_far(y, z), x, y),
+            // A BDE child of a half-kite triangle
+            {
+                let a = from_near(x, y);
+                parent(TileType::Kite, a, x, from_near(a, z))
+            },
+            // An AED child of a half-kite triangle
+            parent(TileType::Kite, x, from_far(x, z), from_near(x, y)),
+            // An ADC child of a half-dart triangle
+            parent(TileType::Dart, x, from_near(x, y), z),
+            // A BCD child of a half-dart triangle
+            parent(TileType::Dart, from_far(x, z), x, y),
+        ],
+        _ => panic!("{:?} is not a Robinson triangle", t.triangle_type),
     }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
-    use crate::geometry::random_point;
+    use crate::golden::random_golden_point;
     use rand::Rng;
 
     #[test]
@@ -216,8 +612,8 @@ mod tests {
         let mut rng = rand::thread_rng();
         let triangles = (0..10_000)
             .flat_map(|_| {
-                let p = random_point(&mut rng, -1000.0, 1000.0);
-                let q = random_point(&mut rng, -1000.0, 1000.0);
+                let p = random_golden_point(&mut rng, -1000, 1000);
+                let q = random_golden_point(&mut rng, -1000, 1000);
                 let triangle_type = match rng.gen_range(0..4) {
                     0 => TileType::SmallRhombus,
                     1 => TileType::LargeRhombus,
@@ -238,136 +634,244 @@ mod tests {
     #[test]
     fn test_merge_pairs_hashing() {
         for seed in crate::seeds::get_all_seeds().iter() {
-            let triangles = generate_tiling(seed.clone().transform(Point(500.0, 500.0), 1000.0), 3);
+            let triangles = generate_tiling(seed.clone().into_triangles(), 3);
             let mut expected = merge_pairs(triangles.clone());
             let mut got = merge_pairs_hashing(triangles);
 
             // Make sure we didn't miss any triangles
             assert_eq!(expected.len(), got.len());
 
-            let quad_center = |&Quadrilateral { a, b, c, d }: &_| (a + b + c + d) / 4.0;
-            let quad_compare = |q1: &_, q2: &_| Point::compare(quad_center(q1), quad_center(q2));
-
-            expected.sort_by(quad_compare);
-            got.sort_by(quad_compare);
+            // The sum of the vertices is four times the center of the quadrilateral
+            let quad_center = |&Quadrilateral { a, b, c, d }: &_| a + b + c + d;
+            expected.sort_by_key(quad_center);
+            got.sort_by_key(quad_center);
             for (e, g) in expected.into_iter().zip(got) {
-                assert_close!(quad_center(&e), quad_center(&g))
+                assert_eq!(quad_center(&e), quad_center(&g))
             }
         }
     }
 
-    /// This tests many combinations of seeds, scales, and generations to make sure that
-    /// `merge_pairs_hashing` will never result in a collision. This test can be pretty slow
     #[test]
-    fn test_merge_pairs_hashing_collision() {
+    fn test_merge_pairs_orientation() {
+        // `merge_pairs_hashing` and `merge_pairs_streaming` should not only find the same pairs,
+        // but also choose the same triangle to give the first three vertices of each quadrilateral.
+        // With the "parallel" feature, this makes sure the parallel version agrees with the serial
+        // one
+        let vertices = |q: &Quadrilateral| (q.a, q.b, q.c, q.d);
         for seed in crate::seeds::get_all_seeds().iter() {
-            for size in 1..5 {
-                let scale = size as f64 * 200.0;
-                let mut triangles = seed
-                    .clone()
-                    .transform(Point(scale / 2.0, scale / 2.0), scale);
-                for _ in 0..8 {
-                    triangles = triangles.into_iter().flat_map(decompose).collect();
-                    merge_pairs_hashing(triangles.clone());
-                }
-            }
+            let triangles = generate_tiling(seed.clone().into_triangles(), 7);
+            let mut expected: Vec<_> = merge_pairs_streaming(triangles.clone().into_iter())
+                .map(|q| vertices(&q))
+                .collect();
+            let mut got: Vec<_> = merge_pairs_hashing(triangles)
+                .iter()
+                .map(vertices)
+                .collect();
+            expected.sort();
+            got.sort();
+            assert_eq!(expected, got);
         }
     }
 
     #[test]
-    fn test_matching_rules() {
-        // Create one tile of each type in a line at y=2000. They are scaled appropriately.
-        let seed = {
-            let scale = 1000.0;
-            let y = 2000.0;
-            let tiles = [
-                crate::seeds::tile(TileType::SmallRhombus).transform(Point(1000.0, y), scale),
-                crate::seeds::tile(TileType::LargeRhombus).transform(Point(4000.0, y), scale),
-                crate::seeds::tile(TileType::Kite).transform(Point(7000.0, y), scale),
-                crate::seeds::tile(TileType::Dart).transform(Point(10000.0, y), scale),
-            ];
-            tiles.concat()
+    fn test_generate_tiling_clipped() {
+        let bounds = Rectangle {
+            min: Point(0.1, -0.2),
+            max: Point(0.4, 0.3),
         };
+        let quad_points = |q: &Quadrilateral| [q.a, q.b, q.c, q.d].map(GoldenPoint::to_point);
+        let quad_center = |q: &Quadrilateral| q.a + q.b + q.c + q.d;
+        for seed in crate::seeds::get_all_seeds().iter() {
+            let full = merge_pairs_hashing(generate_tiling(seed.clone().into_triangles(), 6));
+            let clipped = merge_pairs_hashing(generate_tiling_clipped(
+                seed.clone().into_triangles(),
+                6,
+                bounds,
+            ));
+            assert!(clipped.len() < full.len());
 
-        // Decompose them for eight generations
-        let triangles = generate_tiling(seed, 8);
+            // Every tile that intersects the bounds must be in the clipped tiling, and every tile
+            // in the clipped tiling must be in the full one
+            let mut expected: Vec<_> = full
+                .iter()
+                .filter(|q| bounds.intersects_convex_polygon(&quad_points(q)))
+                .map(quad_center)
+                .collect();
+            let mut got: Vec<_> = clipped.iter().map(quad_center).collect();
+            let mut all: Vec<_> = full.iter().map(quad_center).collect();
+            expected.sort();
+            got.sort();
+            all.sort();
+            assert!(expected.iter().all(|c| got.binary_search(c).is_ok()));
+            assert!(got.iter().all(|c| all.binary_search(c).is_ok()));
+        }
+    }
+
+    #[test]
+    fn test_iter_tiling() {
+        let bounds = Rectangle {
+            min: Point(0.1, -0.2),
+            max: Point(0.4, 0.3),
+        };
+        let triangle_key = |t: &RobinsonTriangle| (t.double_base_median(), t.b);
+        let quad_center = |q: &Quadrilateral| q.a + q.b + q.c + q.d;
+        for seed in crate::seeds::get_all_seeds().iter() {
+            let triangles = || seed.clone().into_triangles();
+
+            let mut expected = generate_tiling(triangles(), 5);
+            let mut got: Vec<_> = iter_tiling(triangles(), 5).collect();
+            expected.sort_by_key(triangle_key);
+            got.sort_by_key(triangle_key);
+            assert_eq!(expected, got);
+
+            let mut expected = generate_tiling_clipped(triangles(), 5, bounds);
+            let mut got: Vec<_> = iter_tiling_clipped(triangles(), 5, bounds).collect();
+            expected.sort_by_key(triangle_key);
+            got.sort_by_key(triangle_key);
+            assert_eq!(expected, got);
 
-        #[derive(Debug, PartialEq)]
-        enum EdgeType {
-            RhombusSide1,     // Goes from B to A in a small triangle or A to B in a large one
-            RhombusSide2,     // Goes from B to C in a small or large triangle
-            SmallRhombusBase, // Goes from A to C in a small triangle
-            LargeRhombusBase, // Goes from A to C in a large triangle
-            KiteDartSide1,    // Goes from A to B in a half-kite triangle or B to A in a half-dart
-            KiteDartSide2,    // Goes from C to B in a half-kite triangle or B to C in a half-dart
-            KiteBase,         // Goes from A to C in a half-kite triangle
-            DartBase,         // Goes from A to C in a half-dart triangle
+            let mut expected: Vec<_> = merge_pairs_hashing(generate_tiling(triangles(), 5))
+                .iter()
+                .map(quad_center)
+                .collect();
+            let mut got: Vec<_> = merge_pairs_streaming(iter_tiling(triangles(), 5))
+                .map(|q| quad_center(&q))
+                .collect();
+            expected.sort();
+            got.sort();
+            assert_eq!(expected, got);
         }
+    }
 
-        // Split up the triangles into their composing edges
-        let mut edges: Vec<(EdgeType, Line)> = triangles
-            .into_iter()
-            .flat_map(|t| {
-                let RobinsonTriangle {
-                    triangle_type,
-                    a,
-                    b,
-                    c,
-                } = t;
-                match triangle_type {
-                    TileType::SmallRhombus => vec![
-                        (EdgeType::RhombusSide1, Line(b, a)),
-                        (EdgeType::RhombusSide2, Line(b, c)),
-                        (EdgeType::SmallRhombusBase, Line(a, c)),
-                    ],
-                    TileType::LargeRhombus => vec![
-                        (EdgeType::RhombusSide1, Line(a, b)),
-                        (EdgeType::RhombusSide2, Line(b, c)),
-                        (EdgeType::LargeRhombusBase, Line(a, c)),
-                    ],
-                    TileType::Kite => vec![
-                        (EdgeType::KiteDartSide1, Line(a, b)),
-                        (EdgeType::KiteDartSide2, Line(c, b)),
-                        (EdgeType::KiteBase, Line(a, c)),
-                    ],
-                    TileType::Dart => vec![
-                        (EdgeType::KiteDartSide1, Line(b, a)),
-                        (EdgeType::KiteDartSide2, Line(b, c)),
-                        (EdgeType::DartBase, Line(a, c)),
-                    ],
-                }
-            })
-            .collect();
+    #[test]
+    fn test_iter_tiling_order() {
+        // The iterator decomposes the seed depth-first, starting from its last triangle, so it
+        // yields the triangles in the reverse order of `generate_tiling`. This is the same with the
+        // "parallel" feature, which decomposes whole subtrees at once for any number of generations
+        let seed = || crate::seeds::rose().into_triangles();
+        for num_generations in 0..4 {
+            let mut expected = generate_tiling(seed(), num_generations);
+            expected.reverse();
+            let got: Vec<_> = iter_tiling(seed(), num_generations).collect();
+            assert_eq!(expected, got);
+        }
+    }
 
-        // We sort here for reasons similar to those in `merge_pairs`
-        edges.sort_by(|a, b| Point::compare(a.1.median(), b.1.median()));
-
-        // Are there any three edges that have the same median?
-        for es in edges.windows(3) {
-            if let [(_, first), (_, second), (_, third)] = es {
-                let first_median = first.median();
-                let second_median = second.median();
-                let third_median = third.median();
-                assert!(
-                    !(close(first_median, second_median) && close(second_median, third_median))
+    /// This tests many combinations of seeds and generations to make sure that
+    /// `merge_pairs_hashing` will never miss a pair. This test can be pretty slow
+    #[test]
+    fn test_merge_pairs_hashing_collision() {
+        for seed in crate::seeds::get_all_seeds().iter() {
+            let mut triangles = seed.clone().into_triangles();
+            for _ in 0..8 {
+                triangles = triangles.into_iter().flat_map(decompose).collect();
+                assert_eq!(
+                    merge_pairs_hashing(triangles.clone()).len(),
+                    merge_pairs(triangles.clone()).len()
                 );
-            } else {
-                unreachable!()
             }
         }
+    }
 
-        // Are there any adjacent edges that have different types or orientations?
-        for es in edges.windows(2) {
-            if let [(current_type, current_line), (next_type, next_line)] = es {
-                let (c_median, n_median) = (current_line.median(), next_line.median());
-                if close(c_median, n_median) {
-                    assert_eq!(current_type, next_type);
-                    assert_close!(current_line.0, next_line.0);
-                    assert_close!(current_line.1, next_line.1);
-                }
-            } else {
-                unreachable!()
+    #[test]
+    fn test_generate_tiling_with_ancestors() {
+        let bounds = Rectangle {
+            min: Point(0.1, -0.2),
+            max: Point(0.4, 0.3),
+        };
+        for seed in crate::seeds::get_all_seeds().iter() {
+            let generations =
+                generate_tiling_with_ancestors(seed.clone().into_triangles(), 4, None);
+            assert_eq!(generations.len(), 5);
+            for (k, triangles) in generations.iter().enumerate() {
+                assert_eq!(
+                    triangles,
+                    &generate_tiling(seed.clone().into_triangles(), k as u64)
+                );
             }
+
+            let generations =
+                generate_tiling_with_ancestors(seed.clone().into_triangles(), 4, Some(bounds));
+            let all = generate_tiling(seed.clone().into_triangles(), 4);
+            assert!(generations[4].iter().all(|t| all.contains(t)));
         }
+
+        // Every edge of a tile is shared by two triangles, except on the boundary
+        let triangles = crate::seeds::tile(TileType::Kite).into_triangles();
+        assert_eq!(tile_outlines(&triangles).len(), 4);
+        let triangles = generate_tiling(crate::seeds::rose().into_triangles(), 3);
+        let quads = merge_pairs(triangles.clone());
+        let expected: HashSet<_> = quads
+            .iter()
+            .flat_map(|q| vec![(q.a, q.b), (q.b, q.c), (q.c, q.d), (q.d, q.a)])
+            .map(|(start, end)| (start.min(end), start.max(end)))
+            .collect();
+        assert_eq!(tile_outlines(&triangles).len(), expected.len());
+    }
+
+    #[test]
+    fn test_compose() {
+        let triangle_key = |t: &RobinsonTriangle| (t.double_base_median(), t.b);
+        for seed in crate::seeds::get_all_seeds().iter() {
+            // Every half of a whole tile of the previous generation must be found
+            let parents = generate_tiling(seed.clone().into_triangles(), 5);
+            let mut expected: Vec<_> = parents
+                .iter()
+                .filter(|t| parents.contains(&t.mirror()))
+                .cloned()
+                .collect();
+            let mut got = compose(generate_tiling(parents.clone(), 1));
+            expected.sort_by_key(triangle_key);
+            got.sort_by_key(triangle_key);
+            assert_eq!(expected, got);
+
+            // Composing a patch cut out of the tiling finds fewer tiles, but they must all be in
+            // the tiling. The same goes for composing many times
+            let bounds = Rectangle {
+                min: Point(0.1, -0.2),
+                max: Point(0.4, 0.3),
+            };
+            let patch = generate_tiling_clipped(seed.clone().into_triangles(), 6, bounds);
+            let got = compose(patch);
+            assert!(!got.is_empty());
+            assert!(got.iter().all(|t| parents.contains(t)));
+
+            let ancestors = generate_tiling(seed.clone().into_triangles(), 4);
+            let got = compose_tiling(generate_tiling(ancestors.clone(), 3), 3);
+            assert!(!got.is_empty());
+            assert!(got.iter().all(|t| ancestors.contains(t)));
+        }
+
+        // If a triangle is missing, its parent can't be composed, and neither can the other half
+        // of the tile
+        let mut triangles = generate_tiling(crate::seeds::rose().into_triangles(), 5);
+        let num_parents = compose(triangles.clone()).len();
+        triangles.remove(0);
+        assert_eq!(compose(triangles).len(), num_parents - 2);
+    }
+
+    #[test]
+    fn test_matching_rules() {
+        // Create one tile of each type in a line along the x axis, far enough apart that they
+        // don't touch
+        let seed = {
+            let tile_at = |tile_type, x| {
+                let offset = GoldenPoint::real(Golden::from(x));
+                crate::seeds::tile(tile_type)
+                    .into_triangles()
+                    .into_iter()
+                    .map(move |t| RobinsonTriangle::new(t.a + offset, t.b + offset, t.c + offset))
+            };
+            tile_at(TileType::SmallRhombus, 0)
+                .chain(tile_at(TileType::LargeRhombus, 10))
+                .chain(tile_at(TileType::Kite, 20))
+                .chain(tile_at(TileType::Dart, 30))
+                .collect()
+        };
+
+        // Decompose them for eight generations
+        let triangles = generate_tiling(seed, 8);
+
+        assert_eq!(crate::validate::validate(&triangles), vec![]);
     }
 }
@@ -0,0 +1,293 @@
+use crate::geometry::*;
+use crate::golden::*;
+use crate::render::Polygon;
+
+use std::collections::HashMap;
+
+pub type TileId = usize;
+pub type VertexId = usize;
+pub type EdgeId = usize;
+
+/// One side of an edge of the tiling. Every edge of a tile is represented by a half-edge belonging
+/// to that tile, going counterclockwise around it. If another tile shares that edge, its half-edge
+/// goes in the opposite direction, and the two are twins.
+#[derive(Debug, Clone)]
+pub struct HalfEdge {
+    pub origin: VertexId,
+    pub tile: TileId,
+
+    /// The index of the origin in the vertices of the tile, as given by `Polygon::vertices`.
+    pub corner: usize,
+
+    /// The next and previous half-edges going around the same tile.
+    pub next: EdgeId,
+    pub prev: EdgeId,
+
+    /// The half-edge of the neighbouring tile that shares this edge, if there is one.
+    pub twin: Option<EdgeId>,
+}
+
+/// The adjacency structure of a tiling, stored as a half-edge data structure. The tiles are
+/// identified by their position in the sequence they were built from, and the vertices are
+/// identified by their exact coordinates, so tiles only share vertices and edges if their vertices
+/// are exactly the same. Because of that, only edge-to-edge tilings get their full adjacency, which
+/// is the case for the tiles given by `merge_pairs`, but not for the Robinson triangles.
+#[derive(Debug, Clone)]
+pub struct Topology {
+    vertices: Vec<GoldenPoint>,
+    vertex_ids: HashMap<GoldenPoint, VertexId>,
+    half_edges: Vec<HalfEdge>,
+    tile_types: Vec<TileType>,
+
+    // For each tile, one of its half-edges, and for each vertex, one of the half-edges that start
+    // at it. Every other half-edge can be reached from those
+    tile_edges: Vec<EdgeId>,
+    vertex_edges: Vec<EdgeId>,
+}
+
+impl Topology {
+    pub fn new<T, I>(tiles: I) -> Self
+    where
+        T: Polygon<Vertex = GoldenPoint>,
+        I: IntoIterator<Item = T>,
+    {
+        let mut topology = Topology {
+            vertices: Vec::new(),
+            vertex_ids: HashMap::new(),
+            half_edges: Vec::new(),
+            tile_types: Vec::new(),
+            tile_edges: Vec::new(),
+            vertex_edges: Vec::new(),
+        };
+
+        // The half-edges that were already created, indexed by their origin and destination, so
+        // that each new half-edge can find its twin
+        let mut edges_by_vertices: HashMap<(VertexId, VertexId), EdgeId> = HashMap::new();
+        for (tile, polygon) in tiles.into_iter().enumerate() {
+            let mut vertices: Vec<_> = polygon.vertices().into_iter().enumerate().collect();
+
+            // The vertices of a polygon may be in either order, but the twins can only be found if
+            // every tile goes around in the same direction
+            let points: Vec<_> = vertices.iter().map(|(_, v)| v.to_point()).collect();
+            let signed_area: f64 = (0..points.len())
+                .map(|i| points[i].cross(points[(i + 1) % points.len()]))
+                .sum();
+            if signed_area < 0.0 {
+                vertices.reverse();
+            }
+            let corners: Vec<_> = vertices.iter().map(|&(corner, _)| corner).collect();
+            let vertices: Vec<_> = vertices
+                .into_iter()
+                .map(|(_, v)| topology.add_vertex(v))
+                .collect();
+
+            let first = topology.half_edges.len();
+            let n = vertices.len();
+            for (i, &origin) in vertices.iter().enumerate() {
+                let id = first + i;
+                let destination = vertices[(i + 1) % n];
+                let twin = edges_by_vertices.get(&(destination, origin)).copied();
+                if let Some(twin) = twin {
+                    topology.half_edges[twin].twin = Some(id);
+                }
+                edges_by_vertices.insert((origin, destination), id);
+                topology.half_edges.push(HalfEdge {
+                    origin,
+                    tile,
+                    corner: corners[i],
+                    next: first + (i + 1) % n,
+                    prev: first + (i + n - 1) % n,
+                    twin,
+                });
+                if topology.vertex_edges.len() == origin {
+                    topology.vertex_edges.push(id);
+                }
+            }
+            topology.tile_types.push(polygon.polygon_type());
+            topology.tile_edges.push(first);
+        }
+        topology
+    }
+
+    fn add_vertex(&mut self, v: GoldenPoint) -> VertexId {
+        let vertices = &mut self.vertices;
+        *self.vertex_ids.entry(v).or_insert_with(|| {
+            vertices.push(v);
+            vertices.len() - 1
+        })
+    }
+
+    pub fn num_tiles(&self) -> usize {
+        self.tile_types.len()
+    }
+
+    pub fn num_vertices(&self) -> usize {
+        self.vertices.len()
+    }
+
+    pub fn tile_type(&self, tile: TileId) -> TileType {
+        self.tile_types[tile]
+    }
+
+    pub fn vertex(&self, vertex: VertexId) -> GoldenPoint {
+        self.vertices[vertex]
+    }
+
+    /// Returns the id of the vertex at the given point, if there is one.
+    pub fn find_vertex(&self, point: GoldenPoint) -> Option<VertexId> {
+        self.vertex_ids.get(&point).copied()
+    }
+
+    pub fn half_edge(&self, edge: EdgeId) -> &HalfEdge {
+        &self.half_edges[edge]
+    }
+
+    /// Returns the vertex where the half-edge ends.
+    pub fn destination(&self, edge: EdgeId) -> VertexId {
+        self.half_edges[self.half_edges[edge].next].origin
+    }
+
+    /// Returns the half-edges of the tile, going counterclockwise around it.
+    pub fn tile_edges(&self, tile: TileId) -> Vec<EdgeId> {
+        let first = self.tile_edges[tile];
+        let mut edges = vec![first];
+        let mut edge = self.half_edges[first].next;
+        while edge != first {
+            edges.push(edge);
+            edge = self.half_edges[edge].next;
+        }
+        edges
+    }
+
+    /// Returns the vertices of the tile, going counterclockwise around it.
+    pub fn tile_vertices(&self, tile: TileId) -> Vec<VertexId> {
+        self.tile_edges(tile)
+            .into_iter()
+            .map(|e| self.half_edges[e].origin)
+            .collect()
+    }
+
+    /// Returns the tiles that share an edge with the tile, going counterclockwise around it.
+    pub fn neighbours(&self, tile: TileId) -> Vec<TileId> {
+        self.tile_edges(tile)
+            .into_iter()
+            .filter_map(|e| self.half_edges[e].twin)
+            .map(|twin| self.half_edges[twin].tile)
+            .collect()
+    }
+
+    /// Returns the half-edge of `a` that is shared with `b`, if the two tiles share an edge. The
+    /// half-edge of `b` is its twin.
+    pub fn shared_edge(&self, a: TileId, b: TileId) -> Option<EdgeId> {
+        self.tile_edges(a).into_iter().find(|&e| {
+            self.half_edges[e]
+                .twin
+                .is_some_and(|twin| self.half_edges[twin].tile == b)
+        })
+    }
+
+    /// Returns the half-edges that start at the vertex, one for each tile that has the vertex as
+    /// one of its corners, going counterclockwise around it. If the vertex is on the boundary of
+    /// the tiling, the first and last half-edges belong to the tiles on the boundary.
+    pub fn edges_around_vertex(&self, vertex: VertexId) -> Vec<EdgeId> {
+        // Going clockwise from a half-edge that starts at the vertex means crossing to its twin,
+        // and taking the next half-edge from there. We first go clockwise until we reach the
+        // boundary, so that we can then collect every half-edge going counterclockwise
+        let first = self.first_edge_around(vertex);
+        let mut edges = Vec::new();
+        let mut edge = first;
+        loop {
+            edges.push(edge);
+            match self.half_edges[self.half_edges[edge].prev].twin {
+                Some(counterclockwise) if counterclockwise != first => edge = counterclockwise,
+                _ => break,
+            }
+        }
+        edges
+    }
+
+    /// Returns the tiles that have the vertex as one of their corners, going counterclockwise
+    /// around it, in the same order as `edges_around_vertex`.
+    pub fn tiles_around_vertex(&self, vertex: VertexId) -> Vec<TileId> {
+        self.edges_around_vertex(vertex)
+            .into_iter()
+            .map(|e| self.half_edges[e].tile)
+            .collect()
+    }
+
+    /// Returns whether the vertex is completely surrounded by tiles.
+    pub fn is_interior_vertex(&self, vertex: VertexId) -> bool {
+        self.half_edges[self.first_edge_around(vertex)]
+            .twin
+            .is_some()
+    }
+
+    // Returns the most clockwise half-edge that starts at the vertex, or any of them if the vertex
+    // is in the interior
+    fn first_edge_around(&self, vertex: VertexId) -> EdgeId {
+        let start = self.vertex_edges[vertex];
+        let mut first = start;
+        while let Some(twin) = self.half_edges[first].twin {
+            let clockwise = self.half_edges[twin].next;
+            if clockwise == start {
+                break;
+            }
+            first = clockwise;
+        }
+        first
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiling::*;
+
+    #[test]
+    fn test_topology() {
+        let quads = merge_pairs(generate_tiling(crate::seeds::rose().into_triangles(), 5));
+        let topology = Topology::new(&quads);
+        assert_eq!(topology.num_tiles(), quads.len());
+
+        for (tile, quad) in quads.iter().enumerate() {
+            assert_eq!(topology.tile_type(tile), quad.polygon_type());
+            assert_eq!(topology.tile_vertices(tile).len(), 4);
+            for e in topology.tile_edges(tile) {
+                if let Some(twin) = topology.half_edge(e).twin {
+                    assert_eq!(topology.half_edge(twin).twin, Some(e));
+                    assert_eq!(topology.half_edge(e).origin, topology.destination(twin));
+                }
+            }
+            for neighbour in topology.neighbours(tile) {
+                assert_ne!(neighbour, tile);
+                assert!(topology.neighbours(neighbour).contains(&tile));
+                let edge = topology.shared_edge(tile, neighbour).unwrap();
+                let twin = topology.half_edge(edge).twin.unwrap();
+                assert_eq!(topology.half_edge(twin).tile, neighbour);
+            }
+        }
+
+        // Every tile that has a vertex as one of its corners must be found around that vertex,
+        // exactly once
+        let mut tiles_by_vertex = vec![Vec::new(); topology.num_vertices()];
+        for tile in 0..topology.num_tiles() {
+            for v in topology.tile_vertices(tile) {
+                tiles_by_vertex[v].push(tile);
+            }
+        }
+        for (v, mut expected) in tiles_by_vertex.into_iter().enumerate() {
+            let mut found = topology.tiles_around_vertex(v);
+            found.sort_unstable();
+            expected.sort_unstable();
+            assert_eq!(found, expected);
+        }
+
+        // The center of the rose seed is surrounded by five rhombuses
+        let center = topology.find_vertex(GoldenPoint::ZERO).unwrap();
+        assert!(topology.is_interior_vertex(center));
+        assert_eq!(topology.tiles_around_vertex(center).len(), 5);
+        for e in topology.edges_around_vertex(center) {
+            assert_eq!(topology.half_edge(e).origin, center);
+        }
+    }
+}
@@ -0,0 +1,317 @@
+use crate::geometry::*;
+use crate::golden::*;
+
+use std::collections::HashMap;
+
+/// The types of the edges of the Robinson triangles. Two triangles can only share an edge if it has
+/// the same type and the same orientation in both of them, which is equivalent to the matching
+/// rules of the Penrose tiles.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, Hash)]
+pub enum EdgeType {
+    RhombusSide1,     // Goes from B to A in a small triangle or A to B in a large one
+    RhombusSide2,     // Goes from B to C in a small or large triangle
+    SmallRhombusBase, // Goes from A to C in a small triangle
+    LargeRhombusBase, // Goes from A to C in a large triangle
+    KiteDartSide1,    // Goes from A to B in a half-kite triangle or B to A in a half-dart
+    KiteDartSide2,    // Goes from C to B in a half-kite triangle or B to C in a half-dart
+    KiteBase,         // Goes from A to C in a half-kite triangle
+    DartBase,         // Goes from A to C in a half-dart triangle
+}
+
+/// An edge of a tile, oriented as its type requires.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub struct Edge {
+    pub edge_type: EdgeType,
+    pub start: GoldenPoint,
+    pub end: GoldenPoint,
+}
+
+impl Edge {
+    pub fn median(&self) -> Point {
+        Line(self.start.to_point(), self.end.to_point()).median()
+    }
+}
+
+/// A way in which a set of tiles breaks the matching rules. The tiles are identified by their
+/// indices in the slice that was validated.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Violation {
+    /// The tile doesn't have the shape of any Penrose tile, or, for a `RobinsonTriangle`, doesn't
+    /// have the shape of its `triangle_type`.
+    InvalidTile {
+        tile: usize,
+        vertices: Vec<GoldenPoint>,
+    },
+
+    /// The two tiles have an edge in the same place, but with different types or orientations.
+    Mismatch { tiles: [usize; 2], edges: [Edge; 2] },
+
+    /// The tiles overlap along the edge. Either more than two tiles have the same edge, or two of
+    /// them are on the same side of it.
+    Overlap { tiles: Vec<usize>, edge: Edge },
+}
+
+impl Violation {
+    /// Returns a point where the rules are broken, to show where the violation is.
+    pub fn location(&self) -> Point {
+        match self {
+            Violation::InvalidTile { vertices, .. } => {
+                let sum = vertices
+                    .iter()
+                    .fold(Point(0.0, 0.0), |sum, v| sum + v.to_point());
+                sum / vertices.len() as f64
+            }
+            Violation::Mismatch { edges, .. } => edges[0].median(),
+            Violation::Overlap { edge, .. } => edge.median(),
+        }
+    }
+}
+
+/// A tile whose edges can be checked against the matching rules.
+pub trait MatchingTile {
+    fn vertices(&self) -> Vec<GoldenPoint>;
+
+    /// Returns the Robinson triangles that compose the tile, or `None` if the tile doesn't have the
+    /// shape of a Penrose tile.
+    fn triangles(&self) -> Option<Vec<RobinsonTriangle>>;
+}
+
+impl MatchingTile for RobinsonTriangle {
+    fn vertices(&self) -> Vec<GoldenPoint> {
+        vec![self.a, self.b, self.c]
+    }
+
+    fn triangles(&self) -> Option<Vec<RobinsonTriangle>> {
+        let triangle_type = RobinsonTriangle::try_infer_triangle_type(self.a, self.b, self.c)?;
+        (triangle_type == self.triangle_type).then(|| vec![self.clone()])
+    }
+}
+
+impl MatchingTile for Quadrilateral {
+    fn vertices(&self) -> Vec<GoldenPoint> {
+        vec![self.a, self.b, self.c, self.d]
+    }
+
+    fn triangles(&self) -> Option<Vec<RobinsonTriangle>> {
+        // There are only two triangles of each type with the base from `a` to `c`, one on each
+        // side of it, so the two halves are mirror images if they are different triangles of the
+        // same type
+        let first = RobinsonTriangle::try_infer_triangle_type(self.a, self.b, self.c)?;
+        let second = RobinsonTriangle::try_infer_triangle_type(self.a, self.d, self.c)?;
+        (first == second && self.b != self.d).then(|| self.split().to_vec())
+    }
+}
+
+/// Checks that the tiles obey the Penrose matching rules, and returns every violation found. The
+/// tiles may come from anywhere, like a file that was edited by hand, so they are not assumed to be
+/// valid tiles. The rules are checked on the edges of the Robinson triangles that compose them.
+/// Every edge that is shared by two triangles must have the same type and orientation in both, and
+/// the triangles must be on opposite sides of it.
+///
+/// Tiles that only touch at some of their vertices, or that overlap without sharing an edge, are
+/// not detected. This is never the case for tiles given by decomposition or by a pentagrid.
+pub fn validate<T: MatchingTile>(tiles: &[T]) -> Vec<Violation> {
+    let mut violations = Vec::new();
+
+    // The edges of every triangle, together with the tile they belong to and the vertex of the
+    // triangle that is opposite to them
+    let mut edges = Vec::new();
+    for (tile, t) in tiles.iter().enumerate() {
+        match t.triangles() {
+            Some(triangles) => edges.extend(
+                triangles
+                    .iter()
+                    .flat_map(triangle_edges)
+                    .map(|(edge, opposite)| (tile, edge, opposite)),
+            ),
+            None => violations.push(Violation::InvalidTile {
+                tile,
+                vertices: t.vertices(),
+            }),
+        }
+    }
+
+    // As in `merge_pairs`, we use twice the median of each edge to find the edges that are in the
+    // same place, since it can be represented exactly. The groups are sorted by the first edge in
+    // each of them, so that the violations are always in the same order
+    let mut groups: HashMap<GoldenPoint, Vec<usize>> = HashMap::new();
+    for (i, (_, edge, _)) in edges.iter().enumerate() {
+        groups.entry(edge.start + edge.end).or_default().push(i);
+    }
+    let mut groups: Vec<_> = groups.into_values().filter(|g| g.len() > 1).collect();
+    groups.sort_unstable_by_key(|g| g[0]);
+
+    for group in groups {
+        if let [i, j] = group[..] {
+            let (first_tile, first, first_opposite) = edges[i];
+            let (second_tile, second, second_opposite) = edges[j];
+
+            // The base of a quadrilateral is shared by its two halves
+            if first_tile == second_tile && first == second {
+                continue;
+            }
+            if first != second {
+                violations.push(Violation::Mismatch {
+                    tiles: [first_tile, second_tile],
+                    edges: [first, second],
+                });
+            } else if same_side(first, first_opposite, second_opposite) {
+                violations.push(Violation::Overlap {
+                    tiles: vec![first_tile, second_tile],
+                    edge: first,
+                });
+            }
+        } else {
+            let mut tiles: Vec<_> = group.iter().map(|&i| edges[i].0).collect();
+            tiles.dedup();
+            violations.push(Violation::Overlap {
+                tiles,
+                edge: edges[group[0]].1,
+            });
+        }
+    }
+    violations
+}
+
+/// Returns the edges on the boundary of the tile, that is, the edges of its Robinson triangles that
+/// aren't shared by two of them, in the order of the vertices of the tile. Returns `None` if the
+/// tile doesn't have the shape of a Penrose tile.
+pub fn boundary_edges<T: MatchingTile>(tile: &T) -> Option<Vec<Edge>> {
+    let edges: Vec<_> = tile
+        .triangles()?
+        .iter()
+        .flat_map(triangle_edges)
+        .map(|(edge, _)| edge)
+        .collect();
+    let vertices = tile.vertices();
+    let n = vertices.len();
+    (0..n)
+        .map(|i| {
+            let (start, end) = (vertices[i], vertices[(i + 1) % n]);
+            edges
+                .iter()
+                .find(|e| (e.start, e.end) == (start, end) || (e.start, e.end) == (end, start))
+                .copied()
+        })
+        .collect()
+}
+
+// Returns the edges of the triangle, each with the vertex opposite to it
+fn triangle_edges(t: &RobinsonTriangle) -> [(Edge, GoldenPoint); 3] {
+    let RobinsonTriangle {
+        triangle_type,
+        a,
+        b,
+        c,
+    } = *t;
+    let edge = |edge_type, start, end| Edge {
+        edge_type,
+        start,
+        end,
+    };
+    match triangle_type {
+        TileType::SmallRhombus => [
+            (edge(EdgeType::RhombusSide1, b, a), c),
+            (edge(EdgeType::RhombusSide2, b, c), a),
+            (edge(EdgeType::SmallRhombusBase, a, c), b),
+        ],
+        TileType::LargeRhombus => [
+            (edge(EdgeType::RhombusSide1, a, b), c),
+            (edge(EdgeType::RhombusSide2, b, c), a),
+            (edge(EdgeType::LargeRhombusBase, a, c), b),
+        ],
+        TileType::Kite => [
+            (edge(EdgeType::KiteDartSide1, a, b), c),
+            (edge(EdgeType::KiteDartSide2, c, b), a),
+            (edge(EdgeType::KiteBase, a, c), b),
+        ],
+        TileType::Dart => [
+            (edge(EdgeType::KiteDartSide1, b, a), c),
+            (edge(EdgeType::KiteDartSide2, b, c), a),
+            (edge(EdgeType::DartBase, a, c), b),
+        ],
+        _ => panic!("{:?} is not a Robinson triangle", triangle_type),
+    }
+}
+
+// Returns whether the two points are on the same side of the line through the edge. Neither of
+// them can be on the line itself, since they are vertices of non-degenerate triangles
+fn same_side(edge: Edge, p: GoldenPoint, q: GoldenPoint) -> bool {
+    let (start, end) = (edge.start.to_point(), edge.end.to_point());
+    let side = |v: GoldenPoint| (end - start).cross(v.to_point() - start) > 0.0;
+    side(p) == side(q)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiling::*;
+
+    #[test]
+    fn test_validate() {
+        let triangles = generate_tiling(crate::seeds::rose().into_triangles(), 4);
+        assert_eq!(validate(&triangles), vec![]);
+        let quads = merge_pairs(triangles.clone());
+        assert_eq!(validate(&quads), vec![]);
+
+        // Reflecting a triangle across its base makes it cover its other half
+        let mut mirrored = triangles.clone();
+        let t = mirrored[0].clone();
+        let other_half = triangles
+            .iter()
+            .position(|u| u != &t && u.a + u.c == t.a + t.c)
+            .unwrap();
+        mirrored[0] = [true, false]
+            .map(|right_handed| {
+                RobinsonTriangle::from_base(t.a, t.c, t.triangle_type, right_handed)
+            })
+            .iter()
+            .find(|u| u.b != t.b)
+            .unwrap()
+            .clone();
+        assert!(validate(&mirrored).iter().any(|v| matches!(
+            v,
+            Violation::Overlap { tiles, .. } if tiles == &[0, other_half]
+        )));
+
+        // Rotating a rhombus by 180 degrees keeps its shape, but reverses its matching arcs, so
+        // its edges don't match the ones of its neighbours anymore
+        let mut rotated = quads.clone();
+        let Quadrilateral { a, b, c, d } = rotated[0].clone();
+        rotated[0] = Quadrilateral {
+            a: c,
+            b: d,
+            c: a,
+            d: b,
+        };
+        let violations = validate(&rotated);
+        assert!(!violations.is_empty());
+        for v in &violations {
+            assert!(matches!(v, Violation::Mismatch { tiles, .. } if tiles.contains(&0)));
+        }
+
+        // A duplicated tile overlaps with itself along its four sides and its base
+        let mut duplicated = quads.clone();
+        duplicated.push(quads[0].clone());
+        let violations = validate(&duplicated);
+        assert_eq!(violations.len(), 5);
+        for v in violations {
+            match v {
+                Violation::Overlap { tiles, .. } => assert!(tiles.contains(&quads.len())),
+                _ => panic!("Unexpected violation {:?}", v),
+            }
+        }
+
+        // A tile that has the wrong shape can't be split into Robinson triangles
+        let mut invalid = quads;
+        invalid[1].d = invalid[1].b;
+        let violations = validate(&invalid);
+        assert_eq!(
+            violations[0],
+            Violation::InvalidTile {
+                tile: 1,
+                vertices: vec![invalid[1].a, invalid[1].b, invalid[1].c, invalid[1].b],
+            }
+        );
+    }
+}
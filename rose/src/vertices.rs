@@ -0,0 +1,278 @@
+use crate::geometry::TileType;
+use crate::topology::*;
+
+use std::collections::BTreeMap;
+
+/// Which corner of a tile is at a vertex. In a `Quadrilateral`, `a` is the center of the first
+/// matching arc, `c` is the center of the second one, and `b` and `d` are the side corners.
+#[derive(Debug, Copy, Clone, PartialEq, Eq)]
+pub enum Corner {
+    FirstArc,
+    SecondArc,
+    Side,
+}
+
+/// The legal neighbourhoods of a vertex in the interior of a Penrose tiling. The kite and dart
+/// configurations have the names given to them by Conway. The rhombus ones are named after the
+/// rhombuses that meet at the vertex. Two of them have the same five thick rhombuses, and are only
+/// told apart by their matching arcs.
+#[derive(Debug, Copy, Clone, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub enum VertexConfiguration {
+    Sun,
+    Star,
+    Ace,
+    Deuce,
+    Jack,
+    Queen,
+    King,
+    FiveThickFirstArc,
+    FiveThickSecondArc,
+    FourThickTwoThin,
+    ThreeThickFourThin,
+    ThreeThickTwoThin,
+    ThreeThickOneThin,
+    TwoThickOneThin,
+    OneThickTwoThin,
+}
+
+impl VertexConfiguration {
+    pub const KITE_AND_DART: [VertexConfiguration; 7] = [
+        VertexConfiguration::Sun,
+        VertexConfiguration::Star,
+        VertexConfiguration::Ace,
+        VertexConfiguration::Deuce,
+        VertexConfiguration::Jack,
+        VertexConfiguration::Queen,
+        VertexConfiguration::King,
+    ];
+
+    pub const RHOMBUS: [VertexConfiguration; 8] = [
+        VertexConfiguration::FiveThickFirstArc,
+        VertexConfiguration::FiveThickSecondArc,
+        VertexConfiguration::FourThickTwoThin,
+        VertexConfiguration::ThreeThickFourThin,
+        VertexConfiguration::ThreeThickTwoThin,
+        VertexConfiguration::ThreeThickOneThin,
+        VertexConfiguration::TwoThickOneThin,
+        VertexConfiguration::OneThickTwoThin,
+    ];
+
+    pub fn name(self) -> &'static str {
+        use VertexConfiguration::*;
+        match self {
+            Sun => "sun",
+            Star => "star",
+            Ace => "ace",
+            Deuce => "deuce",
+            Jack => "jack",
+            Queen => "queen",
+            King => "king",
+            FiveThickFirstArc => "five-thick-first-arc",
+            FiveThickSecondArc => "five-thick-second-arc",
+            FourThickTwoThin => "four-thick-two-thin",
+            ThreeThickFourThin => "three-thick-four-thin",
+            ThreeThickTwoThin => "three-thick-two-thin",
+            ThreeThickOneThin => "three-thick-one-thin",
+            TwoThickOneThin => "two-thick-one-thin",
+            OneThickTwoThin => "one-thick-two-thin",
+        }
+    }
+
+    // The tiles around the vertex, and which of their corners is at the vertex, going around it in
+    // either direction, starting from any of them
+    fn signature(self) -> &'static [(TileType, Corner)] {
+        use Corner::*;
+        use TileType::{Dart, Kite, LargeRhombus, SmallRhombus};
+        use VertexConfiguration::*;
+        match self {
+            Sun => &[(Kite, FirstArc); 5],
+            Star => &[(Dart, FirstArc); 5],
+            Ace => &[(Dart, SecondArc), (Kite, Side), (Kite, Side)],
+            Deuce => &[
+                (Dart, Side),
+                (Dart, Side),
+                (Kite, SecondArc),
+                (Kite, SecondArc),
+            ],
+            Jack => &[
+                (Dart, Side),
+                (Kite, FirstArc),
+                (Kite, FirstArc),
+                (Dart, Side),
+                (Kite, SecondArc),
+            ],
+            Queen => &[
+                (Dart, FirstArc),
+                (Kite, Side),
+                (Kite, Side),
+                (Kite, Side),
+                (Kite, Side),
+            ],
+            King => &[
+                (Dart, FirstArc),
+                (Dart, FirstArc),
+                (Dart, FirstArc),
+                (Kite, Side),
+                (Kite, Side),
+            ],
+            FiveThickFirstArc => &[(LargeRhombus, FirstArc); 5],
+            FiveThickSecondArc => &[(LargeRhombus, SecondArc); 5],
+            FourThickTwoThin => &[
+                (LargeRhombus, FirstArc),
+                (LargeRhombus, FirstArc),
+                (LargeRhombus, FirstArc),
+                (LargeRhombus, FirstArc),
+                (SmallRhombus, Side),
+                (SmallRhombus, Side),
+            ],
+            ThreeThickFourThin => &[
+                (LargeRhombus, FirstArc),
+                (LargeRhombus, FirstArc),
+                (SmallRhombus, Side),
+                (SmallRhombus, Side),
+                (LargeRhombus, FirstArc),
+                (SmallRhombus, Side),
+                (SmallRhombus, Side),
+            ],
+            ThreeThickTwoThin => &[
+                (LargeRhombus, FirstArc),
+                (SmallRhombus, Side),
+                (LargeRhombus, Side),
+                (LargeRhombus, Side),
+                (SmallRhombus, Side),
+            ],
+            ThreeThickOneThin => &[
+                (LargeRhombus, SecondArc),
+                (LargeRhombus, SecondArc),
+                (LargeRhombus, SecondArc),
+                (SmallRhombus, SecondArc),
+            ],
+            TwoThickOneThin => &[
+                (LargeRhombus, Side),
+                (LargeRhombus, Side),
+                (SmallRhombus, FirstArc),
+            ],
+            OneThickTwoThin => &[
+                (LargeRhombus, SecondArc),
+                (SmallRhombus, SecondArc),
+                (SmallRhombus, SecondArc),
+            ],
+        }
+    }
+}
+
+/// Returns the corner of the tile that is at the origin of the half-edge. This assumes the tiles
+/// are quadrilaterals, with their vertices in the same order as in `Quadrilateral`.
+pub fn corner(topology: &Topology, edge: EdgeId) -> Corner {
+    match topology.half_edge(edge).corner {
+        0 => Corner::FirstArc,
+        2 => Corner::SecondArc,
+        _ => Corner::Side,
+    }
+}
+
+/// Returns the configuration of the tiles around the vertex. Returns `None` if the vertex is on the
+/// boundary of the tiling, or if its neighbourhood is not one of the legal ones.
+pub fn classify_vertex(topology: &Topology, vertex: VertexId) -> Option<VertexConfiguration> {
+    if !topology.is_interior_vertex(vertex) {
+        return None;
+    }
+    let around: Vec<_> = topology
+        .edges_around_vertex(vertex)
+        .into_iter()
+        .map(|e| {
+            (
+                topology.tile_type(topology.half_edge(e).tile),
+                corner(topology, e),
+            )
+        })
+        .collect();
+    let configurations = VertexConfiguration::KITE_AND_DART
+        .iter()
+        .chain(&VertexConfiguration::RHOMBUS);
+    configurations
+        .copied()
+        .find(|c| same_cycle(&around, c.signature()))
+}
+
+/// Returns how many vertices of the tiling have each configuration. The vertices that can't be
+/// classified are not counted.
+pub fn count_configurations(topology: &Topology) -> BTreeMap<VertexConfiguration, usize> {
+    let mut counts = BTreeMap::new();
+    for vertex in 0..topology.num_vertices() {
+        if let Some(configuration) = classify_vertex(topology, vertex) {
+            *counts.entry(configuration).or_insert(0) += 1;
+        }
+    }
+    counts
+}
+
+// Returns whether the two sequences are the same when going around a circle, in either direction
+fn same_cycle<T: PartialEq>(a: &[T], b: &[T]) -> bool {
+    let n = a.len();
+    n == b.len()
+        && (0..n).any(|shift| {
+            (0..n).all(|i| a[(i + shift) % n] == b[i])
+                || (0..n).all(|i| a[(n + shift - i) % n] == b[i])
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::tiling::*;
+
+    fn count_seed(
+        seed: crate::seeds::Seed,
+        num_generations: u64,
+    ) -> BTreeMap<VertexConfiguration, usize> {
+        let quads = merge_pairs(generate_tiling(seed.into_triangles(), num_generations));
+        let topology = Topology::new(&quads);
+
+        // Every interior vertex of a tiling generated by decomposition must be legal
+        for vertex in 0..topology.num_vertices() {
+            if topology.is_interior_vertex(vertex) {
+                assert!(classify_vertex(&topology, vertex).is_some());
+            }
+        }
+        count_configurations(&topology)
+    }
+
+    #[test]
+    fn test_kite_and_dart_configurations() {
+        let counts = count_seed(crate::seeds::tile(TileType::Kite), 8);
+        for c in &VertexConfiguration::KITE_AND_DART {
+            assert!(counts.contains_key(c));
+        }
+        assert_eq!(counts.len(), 7);
+    }
+
+    #[test]
+    fn test_rhombus_configurations() {
+        let counts = count_seed(crate::seeds::rose(), 8);
+        for c in &VertexConfiguration::RHOMBUS {
+            assert!(counts.contains_key(c));
+        }
+        assert_eq!(counts.len(), 8);
+
+        // The center of the rose seed is surrounded by the acute corners of five thick rhombuses
+        let quads = merge_pairs(generate_tiling(crate::seeds::rose().into_triangles(), 2));
+        let topology = Topology::new(&quads);
+        let center = topology
+            .find_vertex(crate::golden::GoldenPoint::ZERO)
+            .unwrap();
+        assert!(matches!(
+            classify_vertex(&topology, center),
+            Some(VertexConfiguration::FiveThickFirstArc)
+                | Some(VertexConfiguration::FiveThickSecondArc)
+        ));
+    }
+
+    #[test]
+    fn test_same_cycle() {
+        assert!(same_cycle(&[1, 2, 3, 4], &[3, 4, 1, 2]));
+        assert!(same_cycle(&[1, 2, 3, 4], &[2, 1, 4, 3]));
+        assert!(!same_cycle(&[1, 1, 2, 2], &[1, 2, 1, 2]));
+        assert!(!same_cycle(&[1, 2, 3], &[1, 2, 3, 1]));
+    }
+}